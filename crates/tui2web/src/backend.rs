@@ -4,7 +4,113 @@ use ratatui::{
     layout::{Rect, Size},
     style::{Color, Modifier},
 };
-use std::io;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::{fmt, io};
+use unicode_width::UnicodeWidthChar;
+
+use crate::width::{display_width, symbol_width};
+
+/// Largest buffer [`WebBackend::try_new`] accepts, in cells (e.g. 2048 × 512).
+///
+/// Each cell costs tens of bytes, so this keeps a bogus size from exhausting
+/// a WASM heap.
+pub const MAX_CELLS: usize = 1 << 20;
+
+/// Errors returned by [`WebBackend::try_new`] and [`WebBackend::restore`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendError {
+    /// `width × height` exceeds [`MAX_CELLS`].
+    TooLarge { width: u16, height: u16 },
+    /// A [`FrameSnapshot`] holds `cells` cells rather than `width × height`.
+    SizeMismatch { width: u16, height: u16, cells: usize },
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendError::TooLarge { width, height } => write!(
+                f,
+                "terminal size {width}×{height} exceeds the limit of {MAX_CELLS} cells"
+            ),
+            BackendError::SizeMismatch { width, height, cells } => {
+                write!(f, "snapshot of {width}×{height} holds {cells} cells")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+/// Cursor shape, set with [`WebBackend::set_cursor_style`] and emitted as
+/// DECSCUSR (`\x1b[n q`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorStyle {
+    /// Leave the shape to the terminal (`\x1b[0 q` is emitted only to undo
+    /// another shape).
+    #[default]
+    Default,
+    BlinkingBlock,
+    SteadyBlock,
+    BlinkingUnderline,
+    SteadyUnderline,
+    BlinkingBar,
+    SteadyBar,
+}
+
+/// How control characters in cell symbols are written to frames, set with
+/// [`WebBackend::set_control_chars`].
+///
+/// Tabs are always written as a space and are not affected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ControlChars {
+    /// Write them unchanged, leaving the terminal to act on them.
+    #[default]
+    Raw,
+    /// Show a cell holding only control characters as this one-column
+    /// character (e.g. `'\u{FFFD}'` or `'·'`), and drop them from any other
+    /// cell, so the grid stays intact.  A placeholder that is not one column
+    /// wide is replaced by `'\u{FFFD}'`.
+    Placeholder(char),
+}
+
+/// A copy of a backend's screen state, taken with [`WebBackend::capture`]
+/// and reinstated with [`WebBackend::restore`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameSnapshot {
+    pub width: u16,
+    pub height: u16,
+    /// Row-major cells (index = y * width + x).
+    pub cells: Vec<Cell>,
+    pub cursor_x: u16,
+    pub cursor_y: u16,
+    pub cursor_visible: bool,
+    pub cursor_style: CursorStyle,
+}
+
+/// A cell that differs between two buffers, as reported by [`diff_cells`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellChange {
+    pub x: u16,
+    pub y: u16,
+}
+
+/// A horizontal run of identically styled cells, as returned by
+/// [`WebBackend::take_damage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellRun {
+    /// Buffer column of the first cell.
+    pub x: u16,
+    /// Buffer row.
+    pub y: u16,
+    /// Number of cells covered, counting those under wide glyphs.
+    pub width: u16,
+    pub fg: Color,
+    pub bg: Color,
+    pub modifier: Modifier,
+    /// The symbols of the covered cells, concatenated.
+    pub text: String,
+}
 
 /// A ratatui [`Backend`] that renders terminal frames as ANSI escape-code strings
 /// suitable for display in a web-based terminal emulator such as xterm.js.
@@ -14,28 +120,476 @@ use std::io;
 /// xterm.js instance.
 pub struct WebBackend {
     width: u16,
+    /// Rows in the cell buffer: the logical height ratatui draws into.
     height: u16,
+    /// Rows shown on screen; less than `height` when the buffer scrolls.
+    viewport_rows: u16,
+    /// First buffer row shown on screen (see [`WebBackend::set_scroll_offset`]).
+    scroll_offset: u16,
     /// Flat, row-major cell buffer (index = y * width + x).
     cells: Vec<Cell>,
+    /// Cursor position, always inside the buffer (see [`Backend::set_cursor`]).
     cursor_x: u16,
     cursor_y: u16,
     cursor_visible: bool,
+    /// When set, the cursor is never shown, whatever ratatui requests.
+    force_cursor_hidden: bool,
+    cursor_style: CursorStyle,
+    /// How control characters in symbols are written (see [`WebBackend::set_control_chars`]).
+    control_chars: ControlChars,
     /// Last serialised ANSI frame, updated on every [`Backend::flush`].
     ansi_output: String,
+    /// Whether DEC private-mode sequences (`\x1b[?25l`/`\x1b[?25h`) are emitted.
+    emit_private_modes: bool,
+    /// Whether interior runs of blank cells are collapsed (see [`WebBackend::set_compact`]).
+    compact: bool,
+    /// Whether full frames may be sent as clear + non-blank cells (see [`WebBackend::set_sparse`]).
+    sparse: bool,
+    /// Set by [`WebBackend::bell`]; cleared once the next frame carries the BEL.
+    bell_pending: bool,
+    /// Whether flushes emit only the cells drawn since the previous flush.
+    incremental: bool,
+    /// Whether incremental updates use save/restore and relative cursor moves.
+    relative_moves: bool,
+    /// Set when the next flush must repaint everything (first frame, clear, resize).
+    full_redraw: bool,
+    /// Cursor position left by the last flushed frame.
+    last_cursor: Option<(u16, u16)>,
+    /// Whether the last flushed frame ended with the cursor shown.
+    last_cursor_shown: bool,
+    /// Cursor shape the terminal was last told to use.
+    last_cursor_style: CursorStyle,
+    /// Cell buffer as of the last flush, used to detect no-op frames.
+    last_flushed: Vec<Cell>,
+    /// Set when the last flush was skipped because nothing changed.
+    frame_unchanged: bool,
+    /// Cell buffer as of the last [`WebBackend::take_damage`].
+    damage_base: Vec<Cell>,
+    /// Cells shown reversed in the next flush (see [`WebBackend::set_selection`]).
+    selection: BTreeSet<usize>,
+    /// Selection shown by the last flush, repainted plain in incremental mode.
+    last_selection: BTreeSet<usize>,
+    /// Palette index → RGB replacements (see [`WebBackend::set_palette_override`]).
+    palette: BTreeMap<u8, (u8, u8, u8)>,
+    /// Background for default-background cells inside `popup_area`.
+    popup_bg: Option<Color>,
+    /// Area of the open popup (see [`WebBackend::set_popup_area`]).
+    popup_area: Option<Rect>,
 }
 
 impl WebBackend {
     /// Create a new backend with the given terminal dimensions (columns × rows).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size exceeds [`MAX_CELLS`]; see [`WebBackend::try_new`].
     pub fn new(width: u16, height: u16) -> Self {
-        WebBackend {
+        match Self::try_new(width, height) {
+            Ok(backend) => backend,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Like [`WebBackend::new`], but returns [`BackendError::TooLarge`]
+    /// instead of allocating a buffer of more than [`MAX_CELLS`] cells.
+    pub fn try_new(width: u16, height: u16) -> Result<Self, BackendError> {
+        if usize::from(width) * usize::from(height) > MAX_CELLS {
+            return Err(BackendError::TooLarge { width, height });
+        }
+        Ok(WebBackend {
             width,
             height,
+            viewport_rows: height,
+            scroll_offset: 0,
             cells: vec![Cell::default(); usize::from(width) * usize::from(height)],
             cursor_x: 0,
             cursor_y: 0,
             cursor_visible: true,
+            force_cursor_hidden: false,
+            cursor_style: CursorStyle::Default,
+            control_chars: ControlChars::Raw,
             ansi_output: String::new(),
+            emit_private_modes: true,
+            compact: false,
+            sparse: false,
+            bell_pending: false,
+            incremental: false,
+            relative_moves: false,
+            full_redraw: true,
+            last_cursor: None,
+            last_cursor_shown: false,
+            last_cursor_style: CursorStyle::Default,
+            last_flushed: Vec::new(),
+            frame_unchanged: false,
+            damage_base: Vec::new(),
+            selection: BTreeSet::new(),
+            last_selection: BTreeSet::new(),
+            palette: BTreeMap::new(),
+            popup_bg: None,
+            popup_area: None,
+        })
+    }
+
+    /// Enable or disable DEC private-mode sequences in rendered frames.
+    ///
+    /// Cursor visibility is controlled with DECTCEM (`\x1b[?25l`/`\x1b[?25h`),
+    /// which some minimal emulators reject.  When disabled, frames contain
+    /// only standard ECMA-48 sequences and the cursor visibility is left to
+    /// the emulator.  Enabled by default (xterm.js supports them).
+    pub fn set_emit_private_modes(&mut self, enabled: bool) {
+        self.emit_private_modes = enabled;
+    }
+
+    /// Keep the cursor hidden regardless of ratatui's
+    /// [`show_cursor`](Backend::show_cursor) calls.
+    ///
+    /// ratatui hides and re-shows the cursor as part of drawing a frame; an
+    /// app that never wants a visible cursor can set this so frames never end
+    /// with `\x1b[?25h`.  Disabled by default.
+    pub fn set_force_cursor_hidden(&mut self, hidden: bool) {
+        self.force_cursor_hidden = hidden;
+    }
+
+    /// Render [`Color::Indexed(index)`](Color::Indexed) as the truecolor `rgb`
+    /// instead of leaving it to the terminal's palette (`\x1b[38;5;nm`), so a
+    /// themed page can pin the colours of palette-based apps.
+    pub fn set_palette_override(&mut self, index: u8, rgb: (u8, u8, u8)) {
+        self.palette.insert(index, rgb);
+        self.full_redraw = true;
+    }
+
+    /// Remove an override set with [`WebBackend::set_palette_override`].
+    pub fn clear_palette_override(&mut self, index: u8) {
+        self.palette.remove(&index);
+        self.full_redraw = true;
+    }
+
+    /// Set the background painted behind a popup's cleared cells, or `None`
+    /// to leave them to the page's default background.
+    ///
+    /// ratatui's `Clear` widget resets cells to the default style, which
+    /// shows the themed page background rather than a distinct modal
+    /// surface.  A reset cell looks like any other blank, so the popup's
+    /// extent is declared with [`WebBackend::set_popup_area`].
+    pub fn set_popup_bg(&mut self, color: Option<Color>) {
+        self.popup_bg = color;
+        self.full_redraw = true;
+    }
+
+    /// Declare the area an open popup covers (usually the `Rect` passed to
+    /// `Clear`), or `None` once it closes.  Cells inside it that have the
+    /// default background are painted with the
+    /// [popup background](WebBackend::set_popup_bg).
+    pub fn set_popup_area(&mut self, area: Option<Rect>) {
+        self.popup_area = area;
+        self.full_redraw = true;
+    }
+
+    /// Set the cursor shape shown at the end of each frame.
+    pub fn set_cursor_style(&mut self, style: CursorStyle) {
+        self.cursor_style = style;
+        self.full_redraw = true;
+    }
+
+    /// Choose how control characters in cell symbols are written.
+    ///
+    /// Widget content taken from files or user input can carry raw control
+    /// bytes (e.g. `\x01` or `\x1b`) that xterm.js would act on instead of
+    /// showing.  [`ControlChars::Placeholder`] makes them visible; the
+    /// default, [`ControlChars::Raw`], writes them unchanged.  Only ANSI
+    /// output is affected.
+    pub fn set_control_chars(&mut self, mode: ControlChars) {
+        self.control_chars = mode;
+        self.full_redraw = true;
+    }
+
+    /// `symbol` with its control characters written as set by
+    /// [`WebBackend::set_control_chars`].
+    fn escape_controls<'a>(&self, symbol: &'a str) -> Cow<'a, str> {
+        let is_control = |c: char| c.is_control() && c != '\t';
+        let ControlChars::Placeholder(placeholder) = self.control_chars else {
+            return Cow::Borrowed(symbol);
+        };
+        if !symbol.contains(is_control) {
+            return Cow::Borrowed(symbol);
+        }
+        let placeholder = if placeholder.width() == Some(1) { placeholder } else { '\u{fffd}' };
+        // Control characters have no width, so the cell is one column only
+        // when nothing else in it is visible.
+        let visible: String = symbol.chars().filter(|&c| !is_control(c)).collect();
+        if display_width(&visible) == 0 {
+            Cow::Owned(format!("{placeholder}{visible}"))
+        } else {
+            Cow::Owned(visible)
+        }
+    }
+
+    /// Copy the cell buffer and cursor state, e.g. to replay a frame later.
+    pub fn capture(&self) -> FrameSnapshot {
+        FrameSnapshot {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.clone(),
+            cursor_x: self.cursor_x,
+            cursor_y: self.cursor_y,
+            cursor_visible: self.cursor_visible,
+            cursor_style: self.cursor_style,
+        }
+    }
+
+    /// Reinstate a [`FrameSnapshot`], including its size and cursor state.
+    /// The next flush repaints the whole screen.
+    ///
+    /// The snapshot's rows all become visible, so any scrolling set up with
+    /// [`WebBackend::set_logical_height`] is dropped.  A snapshot whose
+    /// cells do not fill its size is refused with
+    /// [`BackendError::SizeMismatch`], leaving the backend unchanged.
+    pub fn restore(&mut self, snapshot: &FrameSnapshot) -> Result<(), BackendError> {
+        let expected = usize::from(snapshot.width) * usize::from(snapshot.height);
+        if snapshot.cells.len() != expected {
+            return Err(BackendError::SizeMismatch {
+                width: snapshot.width,
+                height: snapshot.height,
+                cells: snapshot.cells.len(),
+            });
+        }
+        self.width = snapshot.width;
+        self.height = snapshot.height;
+        self.viewport_rows = snapshot.height;
+        self.scroll_offset = 0;
+        self.cells = snapshot.cells.clone();
+        self.cursor_x = snapshot.cursor_x;
+        self.cursor_y = snapshot.cursor_y;
+        self.cursor_visible = snapshot.cursor_visible;
+        self.cursor_style = snapshot.cursor_style;
+        self.full_redraw = true;
+        Ok(())
+    }
+
+    /// Rebuild the visible cells and cursor from ANSI output, e.g. a frame
+    /// saved from [`WebBackend::get_ansi_output`] or a real terminal.
+    ///
+    /// Understands what this backend emits: glyphs, absolute and forward
+    /// cursor moves, cursor save/restore, SGR attributes and colours, the
+    /// `K`, `X` and `2J` erases, cursor visibility and shape; `\r` and `\n`
+    /// also move the cursor.  Other sequences are skipped.  Cells that are
+    /// not overwritten keep their contents.
+    ///
+    /// Loading a full frame reproduces the buffer it came from, except for
+    /// what the encoding cannot carry: the foreground of blank cells, rapid
+    /// blink (read back as slow blink) and selection or popup overlays,
+    /// which become part of the cells.
+    pub fn load_ansi(&mut self, ansi: &str) {
+        let (width, rows) = (usize::from(self.width), usize::from(self.viewport_rows));
+        let top = usize::from(self.scroll_offset);
+        let (mut x, mut y, mut saved) = (0, 0, (0, 0));
+        let mut sgr = SgrState::default();
+        // The cell a zero-width character (e.g. a combining accent) joins.
+        let mut last: Option<usize> = None;
+        let mut chars = ansi.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' => {
+                    last = None;
+                    match chars.next() {
+                        Some('7') => saved = (x, y),
+                        Some('8') => (x, y) = saved,
+                        Some('[') => {
+                            let mut params = String::new();
+                            let command = chars.by_ref().find(|&c| {
+                                let done = ('\x40'..='\x7e').contains(&c);
+                                if !done {
+                                    params.push(c);
+                                }
+                                done
+                            });
+                            let private = params.starts_with('?');
+                            let args: Vec<u16> = params
+                                .trim_start_matches('?')
+                                .split(';')
+                                .map(|p| p.trim().parse().unwrap_or(0))
+                                .collect();
+                            let n = usize::from(args[0].max(1));
+                            match command {
+                                Some(mode @ ('h' | 'l')) if private && args[0] == 25 => {
+                                    self.cursor_visible = mode == 'h';
+                                }
+                                Some('q') => self.cursor_style = cursor_style_from(args[0]),
+                                Some('H') => {
+                                    y = n - 1;
+                                    x = usize::from(args.get(1).copied().unwrap_or(1).max(1)) - 1;
+                                }
+                                Some('C') => x += n,
+                                Some('K') => self.fill_row(y + top, x..width, sgr.bg),
+                                Some('X') => self.fill_row(y + top, x..x + n, sgr.bg),
+                                Some('J') if args[0] == 2 => {
+                                    for row in top..top + rows {
+                                        self.fill_row(row, 0..width, sgr.bg);
+                                    }
+                                }
+                                Some('m') => sgr.load(&args),
+                                _ => {}
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                '\r' => x = 0,
+                '\n' => y += 1,
+                c if c.is_control() => {}
+                c if c.width() == Some(0) => {
+                    if let Some(idx) = last {
+                        let symbol = format!("{}{c}", self.cells[idx].symbol());
+                        self.cells[idx].set_symbol(&symbol);
+                    }
+                }
+                c => {
+                    let cell_width = c.width().unwrap_or(1).max(1);
+                    last = None;
+                    if x < width && y < rows {
+                        let idx = self.index_of(x as u16, (y + top) as u16);
+                        let cell = &mut self.cells[idx];
+                        cell.reset();
+                        cell.set_char(c).set_fg(sgr.fg).set_bg(sgr.bg).modifier = sgr.modifier;
+                        // Cells under a wide glyph are blank, as ratatui leaves them.
+                        let covered = (x + cell_width).min(width) - x;
+                        self.cells[idx + 1..idx + covered].iter_mut().for_each(Cell::reset);
+                        last = Some(idx);
+                    }
+                    x += cell_width;
+                }
+            }
+        }
+        self.cursor_x = x.min(width.saturating_sub(1)) as u16;
+        self.cursor_y = (y.min(rows.saturating_sub(1)) + top) as u16;
+        self.full_redraw = true;
+    }
+
+    /// Blank the cells of logical row `y` in `columns`, painting `bg` as an
+    /// erase does.  Out-of-range cells are ignored.
+    fn fill_row(&mut self, y: usize, columns: std::ops::Range<usize>, bg: Color) {
+        if y >= usize::from(self.height) {
+            return;
+        }
+        let end = columns.end.min(usize::from(self.width));
+        for x in columns.start.min(end)..end {
+            let idx = self.index_of(x as u16, y as u16);
+            self.cells[idx].reset();
+            self.cells[idx].bg = bg;
+        }
+    }
+
+    /// Grow the cell buffer to `rows` rows while the screen keeps showing
+    /// only the viewport, so an app can draw content taller than the
+    /// terminal and scroll it with [`WebBackend::set_scroll_offset`].
+    ///
+    /// ratatui sees the logical size through [`Backend::size`]; the cursor,
+    /// selection, popup area and exports all use buffer coordinates.  The
+    /// buffer is cleared, and a value below the viewport height restores a
+    /// plain, unscrolled buffer.  Fails like [`WebBackend::try_new`] when the
+    /// buffer would exceed [`MAX_CELLS`].
+    pub fn set_logical_height(&mut self, rows: u16) -> Result<(), BackendError> {
+        let rows = rows.max(self.viewport_rows);
+        if usize::from(self.width) * usize::from(rows) > MAX_CELLS {
+            return Err(BackendError::TooLarge { width: self.width, height: rows });
         }
+        self.height = rows;
+        self.cells = vec![Cell::default(); usize::from(self.width) * usize::from(rows)];
+        self.clamp_cursor(self.cursor_x, self.cursor_y);
+        self.set_scroll_offset(self.scroll_offset);
+        self.full_redraw = true;
+        Ok(())
+    }
+
+    /// Rows in the cell buffer, which is the viewport height unless raised
+    /// with [`WebBackend::set_logical_height`].
+    pub fn logical_height(&self) -> u16 {
+        self.height
+    }
+
+    /// Show buffer rows `rows..rows + viewport height` on screen.  The offset
+    /// is clamped so the viewport never runs past the end of the buffer;
+    /// changing it repaints the whole screen on the next flush.
+    pub fn set_scroll_offset(&mut self, rows: u16) {
+        let rows = rows.min(self.height - self.viewport_rows);
+        if rows != self.scroll_offset {
+            self.scroll_offset = rows;
+            self.full_redraw = true;
+        }
+    }
+
+    /// The first buffer row shown on screen.
+    pub fn scroll_offset(&self) -> u16 {
+        self.scroll_offset
+    }
+
+    /// Enable or disable compact output.
+    ///
+    /// When enabled, long runs of blank cells inside a row are written as an
+    /// erase-characters/cursor-forward pair (`\x1b[nX\x1b[nC`) instead of
+    /// spaces whenever that is shorter.  Only cells whose symbol is exactly a
+    /// space, with the default background and no attributes, are collapsed;
+    /// any other glyph (including box-drawing borders) is always written.
+    pub fn set_compact(&mut self, enabled: bool) {
+        self.compact = enabled;
+    }
+
+    /// Enable or disable sparse full frames.
+    ///
+    /// When enabled, each full frame is estimated both ways and, if it is
+    /// smaller, sent as a screen clear (`\x1b[2J`) followed by only the
+    /// non-blank cells, each run positioned absolutely.  This cuts mostly
+    /// empty screens such as a splash line to a few bytes.
+    pub fn set_sparse(&mut self, enabled: bool) {
+        self.sparse = enabled;
+    }
+
+    /// Enable or disable incremental output.
+    ///
+    /// When enabled, each flush emits only the cells that changed since the
+    /// previous flush (see [`diff_cells`]), so
+    /// [`WebBackend::get_ansi_output`] must be written on top of the frames
+    /// before it.  The first frame, and the first after a clear or resize,
+    /// is still a full repaint.  Disabled by default.
+    pub fn set_incremental(&mut self, enabled: bool) {
+        self.incremental = enabled;
+        self.full_redraw = true;
+    }
+
+    /// Enable or disable relative cursor movement in incremental output.
+    ///
+    /// When enabled, a run of adjacent changed cells is written after a single
+    /// cursor move, gaps within a row use cursor-forward (`\x1b[nC`), and the
+    /// cursor is saved and restored (`\x1b7`/`\x1b8`) around the update
+    /// instead of being repositioned absolutely when it has not moved.
+    /// xterm.js supports these; some minimal emulators do not, so this is
+    /// disabled by default.  Has no effect unless incremental output is on.
+    pub fn set_relative_moves(&mut self, enabled: bool) {
+        self.relative_moves = enabled;
+    }
+
+    /// Show the cells at `cells` (`(x, y)` pairs) with the
+    /// [`REVERSED`](Modifier::REVERSED) attribute in the next flushed frame,
+    /// e.g. to highlight a text selection without restyling any widgets.
+    ///
+    /// The highlight is an overlay: the cell buffer is not modified, and the
+    /// selection is dropped after one frame unless set again.  Coordinates
+    /// outside the buffer are ignored.
+    pub fn set_selection(&mut self, cells: &[(u16, u16)]) {
+        self.selection = cells
+            .iter()
+            .filter(|&&(x, y)| x < self.width && y < self.height)
+            .map(|&(x, y)| self.index_of(x, y))
+            .collect();
+    }
+
+    /// Ring the terminal bell: queue a BEL (`\x07`) for the next flushed frame.
+    ///
+    /// The BEL is emitted once, after the frame's content, however many times
+    /// this is called before the flush.  xterm.js turns it into a sound or a
+    /// visual flash depending on its `bellStyle` option.
+    pub fn bell(&mut self) {
+        self.bell_pending = true;
     }
 
     /// Return the ANSI escape-code string produced by the most recent frame flush.
@@ -43,98 +597,902 @@ impl WebBackend {
         &self.ansi_output
     }
 
+    /// Drop the retained frame, e.g. once JavaScript has written it, to free
+    /// its memory until the next flush.
+    ///
+    /// [`WebBackend::get_ansi_output`] is empty afterwards.  The baseline
+    /// for incremental output and no-op detection is dropped too, so the
+    /// next flush produces a full frame.
+    pub fn clear_output(&mut self) {
+        self.ansi_output = String::new();
+        self.last_flushed = Vec::new();
+        self.full_redraw = true;
+    }
+
+    /// Write the most recently flushed frame to `w`, e.g. a log sink or a
+    /// relay streaming frames to a remote terminal, without copying it.
+    pub fn write_frame_to(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        w.write_str(&self.ansi_output)
+    }
+
+    /// Length of the most recently flushed frame in UTF-16 code units, i.e.
+    /// the `length` JavaScript sees once the frame crosses into a JS string.
+    /// Characters outside the Basic Multilingual Plane (most emoji) count
+    /// twice.
+    pub fn frame_utf16_len(&self) -> usize {
+        self.ansi_output.chars().map(char::len_utf16).sum()
+    }
+
+    /// A 64-bit FNV-1a hash of [`WebBackend::get_ansi_output`].
+    ///
+    /// Lets JavaScript tell whether a frame differs from the one it last
+    /// wrote by comparing a number instead of fetching the whole string.
+    pub fn frame_hash(&self) -> u64 {
+        self.ansi_output.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    /// Return the cells that changed since the previous call as runs of
+    /// identically styled cells, row by row, and forget them.
+    ///
+    /// This is the structured counterpart of incremental ANSI output, for
+    /// hosts with their own wire format.  The first call, and the first
+    /// after a resize, reports every cell.  Runs describe the cell buffer
+    /// itself, in buffer coordinates; selection, popup and palette overlays
+    /// are not applied.
+    pub fn take_damage(&mut self) -> Vec<CellRun> {
+        let width = usize::from(self.width);
+        if self.damage_base.len() != self.cells.len() {
+            self.damage_base.clear();
+        }
+        let changed = |idx: usize| self.damage_base.get(idx) != Some(&self.cells[idx]);
+        let mut runs: Vec<CellRun> = Vec::new();
+        for y in 0..self.height {
+            let row_start = self.index_of(0, y);
+            let mut x = 0;
+            // Whether the last run in `runs` ends right before `x`.
+            let mut extending = false;
+            while x < width {
+                let idx = row_start + x;
+                if !changed(idx) {
+                    extending = false;
+                    x += 1;
+                    continue;
+                }
+                let cell = &self.cells[idx];
+                let cell_width = symbol_width(cell.symbol()).min(width - x);
+                match runs.last_mut() {
+                    Some(run)
+                        if extending
+                            && (run.fg, run.bg, run.modifier)
+                                == (cell.fg, cell.bg, cell.modifier) =>
+                    {
+                        run.width += cell_width as u16;
+                        run.text.push_str(cell.symbol());
+                    }
+                    _ => runs.push(CellRun {
+                        x: x as u16,
+                        y,
+                        width: cell_width as u16,
+                        fg: cell.fg,
+                        bg: cell.bg,
+                        modifier: cell.modifier,
+                        text: cell.symbol().to_string(),
+                    }),
+                }
+                extending = true;
+                x += cell_width;
+            }
+        }
+        self.damage_base.clone_from(&self.cells);
+        runs
+    }
+
+    /// `true` when the last [`Backend::flush`] found nothing changed since
+    /// the flush before it (cells, cursor, selection, bell) and skipped
+    /// serialisation; [`WebBackend::get_ansi_output`] then still holds the
+    /// previous frame, which the caller need not write again.
+    pub fn frame_unchanged(&self) -> bool {
+        self.frame_unchanged
+    }
+
+    /// Serialise the current cell buffer as a full ANSI frame without
+    /// flushing, i.e. without replacing [`WebBackend::get_ansi_output`].
+    ///
+    /// Useful for inspecting a frame in tests or tooling without driving a
+    /// full [`ratatui::Terminal::draw`] cycle.
+    pub fn render_frame(&self) -> String {
+        let mut out = String::with_capacity(usize::from(self.width) * usize::from(self.height) * 4);
+        self.render_to_ansi_into(&mut out);
+        out
+    }
+
+    /// Return the visible text of the cell buffer, one line per row with
+    /// trailing whitespace trimmed.
+    ///
+    /// Cells with [`Modifier::HIDDEN`] are exported as spaces so concealed
+    /// content (e.g. passwords) never leaks into the text.
+    pub fn to_plain_text(&self) -> String {
+        let mut out = String::with_capacity(self.cells.len() + usize::from(self.height));
+        for y in 0..self.height {
+            self.push_row_text(&mut out, y);
+            if y + 1 < self.height {
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Return the cell under screen position `(x, y)`, e.g. for hit-testing
+    /// a mouse click, or `None` outside the viewport.
+    ///
+    /// `y` counts visible rows, so with a [scroll
+    /// offset](WebBackend::set_scroll_offset) it maps to logical row
+    /// `y + scroll_offset`.
+    pub fn cell_at(&self, x: u16, y: u16) -> Option<&Cell> {
+        if x >= self.width || y >= self.viewport_rows {
+            return None;
+        }
+        self.cells.get(self.index_of(x, y + self.scroll_offset))
+    }
+
+    /// Return the text of screen row `y`, exactly as the matching line of
+    /// [`WebBackend::to_plain_text`], or an empty string outside the viewport.
+    ///
+    /// `y` is mapped like [`WebBackend::cell_at`].
+    pub fn text_at_line(&self, y: u16) -> String {
+        let mut out = String::new();
+        if y < self.viewport_rows {
+            self.push_row_text(&mut out, y + self.scroll_offset);
+        }
+        out
+    }
+
+    /// Append the visible text of logical row `y`, trailing whitespace trimmed.
+    fn push_row_text(&self, out: &mut String, y: u16) {
+        let row_start = self.index_of(0, y);
+        let line_start = out.len();
+        let row = &self.cells[row_start..row_start + usize::from(self.width)];
+        let mut x = 0;
+        while x < row.len() {
+            let cell = &row[x];
+            push_symbol(out, visible_symbol(cell), symbol_width(cell.symbol()));
+            x += symbol_width(cell.symbol());
+        }
+        let trimmed = out[line_start..].trim_end().len();
+        out.truncate(line_start + trimmed);
+    }
+
+    /// Export the cell buffer as an HTML fragment, one line per row, with
+    /// each run of identically styled cells in a `<span>` with inline CSS.
+    ///
+    /// Default colours are left to the enclosing element (typically a
+    /// `<pre>`); a reversed cell with a default colour uses the
+    /// `--tui2web-fg` / `--tui2web-bg` custom properties instead.  Hidden
+    /// cells are exported as spaces.
+    pub fn to_html(&self) -> String {
+        let mut out = String::with_capacity(self.cells.len() * 2);
+        let mut run = String::new();
+        for y in 0..self.height {
+            let mut run_style = SgrState::default();
+            let mut x = 0;
+            while x < usize::from(self.width) {
+                let idx = self.index_of(x as u16, y);
+                let cell = &self.cells[idx];
+                let width = symbol_width(cell.symbol());
+                let style = self.style_at(idx);
+                if style != run_style {
+                    push_html_run(&mut out, run_style, &run);
+                    run.clear();
+                    run_style = style;
+                }
+                push_symbol(&mut run, visible_symbol(cell), width);
+                x += width;
+            }
+            push_html_run(&mut out, run_style, &run);
+            run.clear();
+            if y + 1 < self.height {
+                out.push('\n');
+            }
+        }
+        out
+    }
+
     /// Resize the internal cell buffer to new dimensions.
+    ///
+    /// A cursor left outside the new bounds is moved to the nearest cell.
+    /// A logical height set with [`WebBackend::set_logical_height`] is kept,
+    /// growing to the new viewport if that is taller.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer would exceed [`MAX_CELLS`]; see
+    /// [`WebBackend::try_resize`].
     pub fn resize(&mut self, width: u16, height: u16) {
+        if let Err(e) = self.try_resize(width, height) {
+            panic!("{e}");
+        }
+    }
+
+    /// Like [`WebBackend::resize`], but returns [`BackendError::TooLarge`]
+    /// and leaves the backend unchanged instead of allocating a buffer of
+    /// more than [`MAX_CELLS`] cells.
+    pub fn try_resize(&mut self, width: u16, height: u16) -> Result<(), BackendError> {
+        let scrolling = self.height > self.viewport_rows;
+        let rows = if scrolling { self.height.max(height) } else { height };
+        if usize::from(width) * usize::from(rows) > MAX_CELLS {
+            return Err(BackendError::TooLarge { width, height: rows });
+        }
         self.width = width;
-        self.height = height;
-        self.cells = vec![Cell::default(); usize::from(width) * usize::from(height)];
+        self.viewport_rows = height;
+        self.height = rows;
+        self.scroll_offset = self.scroll_offset.min(self.height - height);
+        self.cells = vec![Cell::default(); usize::from(width) * usize::from(self.height)];
+        self.clamp_cursor(self.cursor_x, self.cursor_y);
+        self.full_redraw = true;
+        Ok(())
     }
 
-    /// Serialise the current cell buffer into a complete ANSI escape-code string.
-    fn render_to_ansi(&self) -> String {
-        let capacity = usize::from(self.width) * usize::from(self.height) * 4;
-        let mut out = String::with_capacity(capacity);
+    /// Reset the cells inside `rect` to blanks, leaving the rest untouched.
+    ///
+    /// The rectangle is clamped to the buffer bounds.  In incremental mode
+    /// the cleared cells are included in the next flush, so clearing a panel
+    /// does not force a full repaint.
+    pub fn clear_region(&mut self, rect: Rect) {
+        let rect = rect.intersection(Rect::new(0, 0, self.width, self.height));
+        for y in rect.top()..rect.bottom() {
+            for x in rect.left()..rect.right() {
+                let idx = self.index_of(x, y);
+                self.cells[idx] = Cell::default();
+            }
+        }
+    }
 
-        // Hide cursor during render to avoid flicker.
-        out.push_str("\x1b[?25l");
+    /// Serialise only the cells inside `rect` as ANSI, positioning each row
+    /// absolutely so the result can be composited over a larger screen.
+    ///
+    /// The rectangle is clamped to the buffer bounds.  Unlike a full frame,
+    /// the output neither erases outside the region nor touches the cursor.
+    pub fn render_region_to_ansi(&self, rect: Rect) -> String {
+        let rect = rect.intersection(Rect::new(0, 0, self.width, self.height));
+        let mut out = String::with_capacity(usize::from(rect.width) * usize::from(rect.height) * 4);
+        let mut sgr = SgrState::default();
 
-        let mut prev_fg = Color::Reset;
-        let mut prev_bg = Color::Reset;
-        let mut prev_modifier = Modifier::empty();
+        for y in rect.top()..rect.bottom() {
+            push_cursor_position(&mut out, rect.x, y);
+            let mut x = usize::from(rect.left());
+            while x < usize::from(rect.right()) {
+                let idx = self.index_of(x as u16, y);
+                let cell = &self.cells[idx];
+                let width = symbol_width(cell.symbol());
+                sgr.apply(&mut out, self.style_at(idx));
+                push_symbol(&mut out, &self.escape_controls(cell.symbol()), width);
+                x += width;
+            }
+        }
 
-        for y in 0..self.height {
-            // Move cursor to start of row (1-based ANSI coordinates).
+        if !rect.is_empty() {
+            out.push_str("\x1b[0m");
+        }
+        out
+    }
+
+    /// Store the cursor position `(x, y)`, moved to the nearest cell inside
+    /// the buffer.
+    fn clamp_cursor(&mut self, x: u16, y: u16) {
+        self.cursor_x = x.min(self.width.saturating_sub(1));
+        self.cursor_y = y.min(self.height.saturating_sub(1));
+    }
+
+    /// Append the cursor shape and visibility that end every frame.
+    fn push_cursor_suffix(&self, out: &mut String) {
+        if !self.cursor_shown() {
+            return;
+        }
+        // Going back to the default shape has to be said once, as `0 q`.
+        let changed = self.last_cursor_style != self.cursor_style;
+        if self.cursor_style != CursorStyle::Default || changed {
             out.push_str("\x1b[");
-            push_u16(&mut out, y + 1);
-            out.push_str(";1H");
+            push_u16(out, self.cursor_style as u16);
+            out.push_str(" q");
+        }
+        if self.emit_private_modes {
+            out.push_str("\x1b[?25h");
+        }
+    }
 
-            for x in 0..self.width {
-                let cell = &self.cells[usize::from(y) * usize::from(self.width) + usize::from(x)];
-                let fg = cell.fg;
-                let bg = cell.bg;
-                let modifier = cell.modifier;
+    /// Whether a frame should end with the cursor visible.  A cursor
+    /// scrolled out of the viewport is hidden.
+    fn cursor_shown(&self) -> bool {
+        let top = self.scroll_offset;
+        self.cursor_visible
+            && !self.force_cursor_hidden
+            && (top..top + self.viewport_rows).contains(&self.cursor_y)
+    }
 
-                if fg != prev_fg || bg != prev_bg || modifier != prev_modifier {
-                    out.push_str("\x1b[0m");
+    /// Append a move to the cursor's position on screen.
+    fn push_screen_cursor(&self, out: &mut String) {
+        let y = self.cursor_y.saturating_sub(self.scroll_offset);
+        push_cursor_position(out, self.cursor_x, y.min(self.viewport_rows.saturating_sub(1)));
+    }
 
-                    if modifier.contains(Modifier::BOLD) {
-                        out.push_str("\x1b[1m");
-                    }
-                    if modifier.contains(Modifier::DIM) {
-                        out.push_str("\x1b[2m");
+    /// The style to emit for the cell at `idx`: its own colours with palette
+    /// overrides applied, plus the selection overlay.
+    fn style_at(&self, idx: usize) -> SgrState {
+        let cell = &self.cells[idx];
+        let overlay = if self.selection.contains(&idx) {
+            Modifier::REVERSED
+        } else {
+            Modifier::empty()
+        };
+        let bg = match (cell.bg, self.popup_bg) {
+            (Color::Reset, Some(popup)) if self.in_popup(idx) => popup,
+            (bg, _) => bg,
+        };
+        SgrState {
+            fg: self.palette_color(cell.fg),
+            bg: self.palette_color(bg),
+            modifier: cell.modifier | overlay,
+        }
+    }
+
+    /// `true` when the cell at `idx` lies inside the popup area.
+    fn in_popup(&self, idx: usize) -> bool {
+        let width = usize::from(self.width.max(1));
+        let (x, y) = ((idx % width) as u16, (idx / width) as u16);
+        self.popup_area.is_some_and(|area| {
+            (area.left()..area.right()).contains(&x) && (area.top()..area.bottom()).contains(&y)
+        })
+    }
+
+    /// `color`, with an overridden palette index replaced by its RGB value.
+    fn palette_color(&self, color: Color) -> Color {
+        match color {
+            Color::Indexed(n) => match self.palette.get(&n) {
+                Some(&(r, g, b)) => Color::Rgb(r, g, b),
+                None => color,
+            },
+            _ => color,
+        }
+    }
+
+    /// Index of `(x, y)` in the flat cell buffer.
+    fn index_of(&self, x: u16, y: u16) -> usize {
+        usize::from(y) * usize::from(self.width) + usize::from(x)
+    }
+
+    /// Append the current cell buffer to `out` as a complete ANSI frame, the
+    /// same frame [`WebBackend::render_frame`] returns.
+    ///
+    /// Lets a host that manages its own buffers (e.g. a pool of strings)
+    /// serialise frames without this crate allocating.
+    pub fn render_to_ansi_into(&self, out: &mut String) {
+        // Hide cursor during render to avoid flicker.
+        if self.emit_private_modes {
+            out.push_str("\x1b[?25l");
+        }
+
+        if self.sparse {
+            let (dense, sparse) = self.frame_size_estimates();
+            if sparse < dense {
+                self.render_sparse_into(out);
+                return;
+            }
+        }
+
+        let mut sgr = SgrState::default();
+
+        for y in 0..self.viewport_rows {
+            // Move cursor to start of row.
+            push_cursor_position(out, 0, y);
+
+            let row_start = self.index_of(0, y + self.scroll_offset);
+            let row = &self.cells[row_start..row_start + usize::from(self.width)];
+            let blank = |x: usize| self.blank_at(row_start + x);
+            // Trailing default blanks are replaced by a single erase-to-EOL.
+            let content_end = (0..row.len()).rposition(|x| !blank(x)).map_or(0, |i| i + 1);
+
+            let mut x = 0;
+            while x < content_end {
+                if self.compact {
+                    let run = (x..content_end).take_while(|&x| blank(x)).count();
+                    if run > 0 && run > erase_run_len(run) {
+                        // ECH paints with the current background, like EL.
+                        sgr.reset(out);
+                        push_erase_run(out, run);
+                        x += run;
+                        continue;
                     }
-                    if modifier.contains(Modifier::ITALIC) {
-                        out.push_str("\x1b[3m");
+                }
+                let cell = &row[x];
+                let width = symbol_width(cell.symbol());
+                sgr.apply(out, self.style_at(row_start + x));
+                push_symbol(out, &self.escape_controls(cell.symbol()), width);
+                // The cells under a wide glyph may hold stale content; the
+                // terminal has already advanced past them.
+                x += width;
+            }
+
+            if x < row.len() {
+                // EL paints with the current background, so drop any active
+                // attributes first.
+                sgr.reset(out);
+                out.push_str("\x1b[K");
+            }
+        }
+
+        out.push_str("\x1b[0m");
+
+        // Reposition cursor.
+        self.push_screen_cursor(out);
+
+        self.push_cursor_suffix(out);
+    }
+
+    /// The rest of a full frame, after the cursor is hidden, in sparse form:
+    /// clear the screen, then write each run of non-blank cells.
+    fn render_sparse_into(&self, out: &mut String) {
+        out.push_str("\x1b[2J");
+        let mut sgr = SgrState::default();
+        for y in 0..self.viewport_rows {
+            let row_start = self.index_of(0, y + self.scroll_offset);
+            let mut in_run = false;
+            let mut x = 0;
+            while x < usize::from(self.width) {
+                let idx = row_start + x;
+                if self.blank_at(idx) {
+                    in_run = false;
+                    x += 1;
+                    continue;
+                }
+                if !in_run {
+                    push_cursor_position(out, x as u16, y);
+                    in_run = true;
+                }
+                let cell = &self.cells[idx];
+                let width = symbol_width(cell.symbol());
+                sgr.apply(out, self.style_at(idx));
+                push_symbol(out, &self.escape_controls(cell.symbol()), width);
+                x += width;
+            }
+        }
+        out.push_str("\x1b[0m");
+        self.push_screen_cursor(out);
+        self.push_cursor_suffix(out);
+    }
+
+    /// An upper bound on the byte length of [`WebBackend::render_frame`],
+    /// computed without serialising, e.g. to size a transport buffer or
+    /// choose between sending a frame whole or in chunks.
+    ///
+    /// Every cell's symbol bytes are counted plus the worst case for each
+    /// escape sequence, so the bound is usually within a small factor of
+    /// the real length.  Incremental updates are not covered: scattered
+    /// changes can take more bytes than a full frame.
+    pub fn estimate_frame_len(&self) -> usize {
+        // `\x1b[row;colH` with two five-digit numbers.
+        const MOVE: usize = 14;
+        // Hide and show cursor, cursor shape, final reset and move, BEL.
+        const FRAME_OVERHEAD: usize = 6 + 6 + 5 + 4 + MOVE + 1;
+        let width = usize::from(self.width);
+        let (mut dense, mut sparse) = (0, 4);
+        let (mut dense_sgr, mut sparse_sgr) = (SgrState::default(), SgrState::default());
+        for y in 0..self.viewport_rows {
+            let row_start = self.index_of(0, y + self.scroll_offset);
+            // Row move, plus a reset and erase-to-EOL.
+            dense += MOVE + 4 + 3;
+            let content_end =
+                (0..width).rposition(|x| !self.blank_at(row_start + x)).map_or(0, |x| x + 1);
+            let (mut erased, mut after_blank) = (false, true);
+            let mut x = 0;
+            while x < width {
+                let idx = row_start + x;
+                let cell = &self.cells[idx];
+                let cell_width = symbol_width(cell.symbol());
+                let text = self.escape_controls(cell.symbol()).len() + cell_width;
+                let style = self.style_at(idx);
+                let blank = self.blank_at(idx);
+
+                if x < content_end {
+                    if style != dense_sgr || (erased && !blank) {
+                        dense += style.escape_len_bound();
+                        dense_sgr = style;
                     }
-                    if modifier.contains(Modifier::UNDERLINED) {
-                        out.push_str("\x1b[4m");
+                    // A compacted blank run resets the style first, so the
+                    // next cell re-applies it; its ECH is shorter than the
+                    // spaces counted for it.
+                    if self.compact && blank && !erased {
+                        dense += 4;
                     }
-                    if modifier.contains(Modifier::SLOW_BLINK)
-                        || modifier.contains(Modifier::RAPID_BLINK)
-                    {
-                        out.push_str("\x1b[5m");
+                    erased = self.compact && blank;
+                    dense += text;
+                }
+
+                if !blank {
+                    if after_blank {
+                        sparse += MOVE;
                     }
-                    if modifier.contains(Modifier::REVERSED) {
-                        out.push_str("\x1b[7m");
+                    if style != sparse_sgr {
+                        sparse += style.escape_len_bound();
+                        sparse_sgr = style;
                     }
-                    if modifier.contains(Modifier::CROSSED_OUT) {
-                        out.push_str("\x1b[9m");
+                    sparse += text;
+                }
+                after_blank = blank;
+                x += cell_width;
+            }
+            dense_sgr = SgrState::default();
+        }
+        let body = if self.sparse { dense.max(sparse) } else { dense };
+        body + FRAME_OVERHEAD
+    }
+
+    /// Rough byte counts of the dense and sparse encodings of the buffer,
+    /// ignoring the SGR codes both need.
+    fn frame_size_estimates(&self) -> (usize, usize) {
+        // A typical `\x1b[row;colH`.
+        const MOVE: usize = 8;
+        let width = usize::from(self.width);
+        let (mut dense, mut sparse) = (0, 4);
+        for y in 0..self.viewport_rows {
+            let row_start = self.index_of(0, y + self.scroll_offset);
+            let (mut content_end, mut cells, mut runs) = (0, 0, 0);
+            for x in 0..width {
+                if !self.blank_at(row_start + x) {
+                    if x == 0 || self.blank_at(row_start + x - 1) {
+                        runs += 1;
                     }
+                    content_end = x + 1;
+                    cells += 1;
+                }
+            }
+            dense += MOVE + content_end + if content_end < width { 3 } else { 0 };
+            sparse += runs * MOVE + cells;
+        }
+        (dense, sparse)
+    }
+
+    /// `true` for a cell a cleared screen already shows: a default blank
+    /// that is neither selected nor inside a coloured popup.
+    fn blank_at(&self, idx: usize) -> bool {
+        is_default_blank(&self.cells[idx])
+            && !self.selection.contains(&idx)
+            && !(self.popup_bg.is_some() && self.in_popup(idx))
+    }
 
-                    if fg != Color::Reset {
-                        push_fg_color(&mut out, fg);
+    /// Append just the cells at the (sorted, deduplicated) buffer indices in
+    /// `dirty` to `out`, for incremental output.
+    fn render_dirty_into(&self, dirty: &[usize], out: &mut String) {
+        if self.emit_private_modes {
+            out.push_str("\x1b[?25l");
+        }
+        let cursor = (self.cursor_x, self.cursor_y);
+        let restore = self.relative_moves && self.last_cursor == Some(cursor);
+        if restore {
+            out.push_str("\x1b7");
+        }
+
+        let width = usize::from(self.width);
+        let top = usize::from(self.scroll_offset);
+        let mut sgr = SgrState::default();
+        // Where the terminal's cursor is after the previous write.
+        let mut at: Option<(usize, usize)> = None;
+        for &idx in dirty {
+            let (x, y) = (idx % width, idx / width);
+            if !(top..top + usize::from(self.viewport_rows)).contains(&y) {
+                continue;
+            }
+            let y = y - top;
+            match at {
+                Some((ax, ay)) if self.relative_moves && ay == y && ax <= x => {
+                    if ax < x {
+                        out.push_str("\x1b[");
+                        push_u16(out, (x - ax) as u16);
+                        out.push('C');
                     }
-                    if bg != Color::Reset {
-                        push_bg_color(&mut out, bg);
+                }
+                _ => push_cursor_position(out, x as u16, y as u16),
+            }
+            let cell = &self.cells[idx];
+            let cell_width = symbol_width(cell.symbol());
+            sgr.apply(out, self.style_at(idx));
+            push_symbol(out, &self.escape_controls(cell.symbol()), cell_width);
+            at = Some((x + cell_width, y));
+        }
+
+        out.push_str("\x1b[0m");
+        if restore {
+            out.push_str("\x1b8");
+        } else {
+            self.push_screen_cursor(out);
+        }
+        self.push_cursor_suffix(out);
+    }
+}
+
+/// Tracks the SGR attributes last written to the output so that escape codes
+/// are only emitted when the style actually changes between cells.
+#[derive(Clone, Copy, PartialEq)]
+struct SgrState {
+    fg: Color,
+    bg: Color,
+    modifier: Modifier,
+}
+
+impl Default for SgrState {
+    fn default() -> Self {
+        SgrState {
+            fg: Color::Reset,
+            bg: Color::Reset,
+            modifier: Modifier::empty(),
+        }
+    }
+}
+
+impl SgrState {
+    /// Emit whatever codes are needed to switch to `target`.
+    fn apply(&mut self, out: &mut String, target: SgrState) {
+        let SgrState { fg, bg, modifier } = target;
+
+        if fg == self.fg && bg == self.bg && modifier == self.modifier {
+            return;
+        }
+
+        out.push_str("\x1b[0m");
+
+        if modifier.contains(Modifier::BOLD) {
+            out.push_str("\x1b[1m");
+        }
+        if modifier.contains(Modifier::DIM) {
+            out.push_str("\x1b[2m");
+        }
+        if modifier.contains(Modifier::ITALIC) {
+            out.push_str("\x1b[3m");
+        }
+        if modifier.contains(Modifier::UNDERLINED) {
+            out.push_str("\x1b[4m");
+        }
+        if modifier.contains(Modifier::SLOW_BLINK) || modifier.contains(Modifier::RAPID_BLINK) {
+            out.push_str("\x1b[5m");
+        }
+        if modifier.contains(Modifier::REVERSED) {
+            out.push_str("\x1b[7m");
+        }
+        if modifier.contains(Modifier::HIDDEN) {
+            out.push_str("\x1b[8m");
+        }
+        if modifier.contains(Modifier::CROSSED_OUT) {
+            out.push_str("\x1b[9m");
+        }
+
+        if fg != Color::Reset {
+            push_fg_color(out, fg);
+        }
+        if bg != Color::Reset {
+            push_bg_color(out, bg);
+        }
+
+        self.fg = fg;
+        self.bg = bg;
+        self.modifier = modifier;
+    }
+
+    /// Update the state from the parameters of an SGR (`\x1b[…m`) sequence.
+    fn load(&mut self, args: &[u16]) {
+        let mut args = args.iter().copied();
+        while let Some(code) = args.next() {
+            match code {
+                0 => *self = SgrState::default(),
+                1 => self.modifier |= Modifier::BOLD,
+                2 => self.modifier |= Modifier::DIM,
+                3 => self.modifier |= Modifier::ITALIC,
+                4 => self.modifier |= Modifier::UNDERLINED,
+                5 => self.modifier |= Modifier::SLOW_BLINK,
+                6 => self.modifier |= Modifier::RAPID_BLINK,
+                7 => self.modifier |= Modifier::REVERSED,
+                8 => self.modifier |= Modifier::HIDDEN,
+                9 => self.modifier |= Modifier::CROSSED_OUT,
+                22 => self.modifier -= Modifier::BOLD | Modifier::DIM,
+                23 => self.modifier -= Modifier::ITALIC,
+                24 => self.modifier -= Modifier::UNDERLINED,
+                25 => self.modifier -= Modifier::SLOW_BLINK | Modifier::RAPID_BLINK,
+                27 => self.modifier -= Modifier::REVERSED,
+                28 => self.modifier -= Modifier::HIDDEN,
+                29 => self.modifier -= Modifier::CROSSED_OUT,
+                30..=37 | 90..=97 => self.fg = basic_color(code % 10, code >= 90),
+                40..=47 | 100..=107 => self.bg = basic_color(code % 10, code >= 100),
+                39 => self.fg = Color::Reset,
+                49 => self.bg = Color::Reset,
+                38 | 48 => {
+                    let color = match args.next() {
+                        Some(5) => args.next().map(|n| Color::Indexed(n as u8)),
+                        Some(2) => {
+                            let mut channel = || args.next().map(|n| n as u8);
+                            match (channel(), channel(), channel()) {
+                                (Some(r), Some(g), Some(b)) => Some(Color::Rgb(r, g, b)),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        if code == 38 {
+                            self.fg = color;
+                        } else {
+                            self.bg = color;
+                        }
                     }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The most bytes [`SgrState::apply`] can write to switch to `self`.
+    fn escape_len_bound(self) -> usize {
+        let color = |color: Color| match color {
+            Color::Reset => 0,
+            // `\x1b[38;2;255;255;255m`
+            Color::Rgb(..) => 19,
+            // `\x1b[38;5;255m`
+            Color::Indexed(_) => 11,
+            // `\x1b[100m`
+            _ => 6,
+        };
+        4 + 4 * self.modifier.bits().count_ones() as usize + color(self.fg) + color(self.bg)
+    }
+
+    /// Return to the default style if anything else is active.
+    fn reset(&mut self, out: &mut String) {
+        if self.fg != Color::Reset || self.bg != Color::Reset || !self.modifier.is_empty() {
+            out.push_str("\x1b[0m");
+            *self = SgrState::default();
+        }
+    }
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+/// Encode `cell`'s text attributes as a stable bit set, for exporters and
+/// frontends that need to interpret styling without ratatui.
+///
+/// | Bit | Value | Attribute     |
+/// |-----|-------|---------------|
+/// | 0   | 1     | bold          |
+/// | 1   | 2     | dim           |
+/// | 2   | 4     | italic        |
+/// | 3   | 8     | underlined    |
+/// | 4   | 16    | slow blink    |
+/// | 5   | 32    | rapid blink   |
+/// | 6   | 64    | reversed      |
+/// | 7   | 128   | hidden        |
+/// | 8   | 256   | crossed out   |
+///
+/// The encoding is part of this crate's API and will not change, even if
+/// ratatui's own `Modifier` representation does.
+pub fn modifier_bits(cell: &Cell) -> u16 {
+    const BITS: [Modifier; 9] = [
+        Modifier::BOLD,
+        Modifier::DIM,
+        Modifier::ITALIC,
+        Modifier::UNDERLINED,
+        Modifier::SLOW_BLINK,
+        Modifier::RAPID_BLINK,
+        Modifier::REVERSED,
+        Modifier::HIDDEN,
+        Modifier::CROSSED_OUT,
+    ];
+    BITS.iter()
+        .enumerate()
+        .filter(|(_, m)| cell.modifier.contains(**m))
+        .fold(0, |bits, (i, _)| bits | 1 << i)
+}
+
+/// Compare two row-major buffers of the given `width` and return the
+/// coordinates of every cell that differs, in buffer order.
+///
+/// Cells present in only one buffer (when the lengths differ) count as
+/// changed.  This is what incremental output sends; it is also handy for
+/// comparing two [`FrameSnapshot`]s in tests.
+pub fn diff_cells(old: &[Cell], new: &[Cell], width: u16) -> Vec<CellChange> {
+    let width = usize::from(width.max(1));
+    (0..old.len().max(new.len()))
+        .filter(|&i| old.get(i) != new.get(i))
+        .map(|i| CellChange {
+            x: (i % width) as u16,
+            y: (i / width) as u16,
+        })
+        .collect()
+}
+
+/// The symbol an exporter should show for `cell`: blank when concealed.
+fn visible_symbol(cell: &Cell) -> &str {
+    if cell.modifier.contains(Modifier::HIDDEN) {
+        " "
+    } else {
+        cell.symbol()
+    }
+}
 
-                    prev_fg = fg;
-                    prev_bg = bg;
-                    prev_modifier = modifier;
-                }
+/// Append `symbol`, padded with spaces to cover `width` columns. A tab is
+/// written as a single space, the one cell ratatui gave it, so the result
+/// never depends on the terminal's own tab stops.
+fn push_symbol(out: &mut String, symbol: &str, width: usize) {
+    if symbol.contains('\t') {
+        out.extend(symbol.chars().map(|c| if c == '\t' { ' ' } else { c }));
+    } else {
+        out.push_str(symbol);
+    }
+    out.extend(std::iter::repeat_n(' ', width.saturating_sub(symbol_width(symbol))));
+}
 
-                out.push_str(cell.symbol());
-            }
-        }
+/// The colour of SGR code `30 + n` (or `90 + n` when `bright`), the inverse
+/// of [`push_fg_color`] for the sixteen named colours.
+fn basic_color(n: u16, bright: bool) -> Color {
+    match (n, bright) {
+        (0, false) => Color::Black,
+        (1, false) => Color::Red,
+        (2, false) => Color::Green,
+        (3, false) => Color::Yellow,
+        (4, false) => Color::Blue,
+        (5, false) => Color::Magenta,
+        (6, false) => Color::Cyan,
+        (7, false) => Color::Gray,
+        (0, true) => Color::DarkGray,
+        (1, true) => Color::LightRed,
+        (2, true) => Color::LightGreen,
+        (3, true) => Color::LightYellow,
+        (4, true) => Color::LightBlue,
+        (5, true) => Color::LightMagenta,
+        (6, true) => Color::LightCyan,
+        _ => Color::White,
+    }
+}
 
-        out.push_str("\x1b[0m");
+/// The shape selected by `\x1b[n q`.
+fn cursor_style_from(n: u16) -> CursorStyle {
+    match n {
+        1 => CursorStyle::BlinkingBlock,
+        2 => CursorStyle::SteadyBlock,
+        3 => CursorStyle::BlinkingUnderline,
+        4 => CursorStyle::SteadyUnderline,
+        5 => CursorStyle::BlinkingBar,
+        6 => CursorStyle::SteadyBar,
+        _ => CursorStyle::Default,
+    }
+}
 
-        // Reposition cursor.
-        out.push_str("\x1b[");
-        push_u16(&mut out, self.cursor_y + 1);
-        out.push(';');
-        push_u16(&mut out, self.cursor_x + 1);
-        out.push('H');
+/// Append `\x1b[nX\x1b[nC`: erase `n` cells and step over them.
+fn push_erase_run(out: &mut String, n: usize) {
+    let n = u16::try_from(n).unwrap_or(u16::MAX);
+    out.push_str("\x1b[");
+    push_u16(out, n);
+    out.push_str("X\x1b[");
+    push_u16(out, n);
+    out.push('C');
+}
 
-        if self.cursor_visible {
-            out.push_str("\x1b[?25h");
-        }
+/// Byte length of the sequence written by [`push_erase_run`].
+fn erase_run_len(n: usize) -> usize {
+    let digits = n.checked_ilog10().map_or(1, |d| d as usize + 1);
+    2 * (3 + digits)
+}
 
-        out
-    }
+/// Append an absolute cursor move to the 0-based cell `(x, y)`.
+fn push_cursor_position(out: &mut String, x: u16, y: u16) {
+    out.push_str("\x1b[");
+    push_u16(out, y.saturating_add(1));
+    out.push(';');
+    push_u16(out, x.saturating_add(1));
+    out.push('H');
 }
 
-// ── Helpers ──────────────────────────────────────────────────────────────────
+/// `true` for a space with the default background and no attributes, i.e. a
+/// cell that erase-to-end-of-line would reproduce exactly.
+fn is_default_blank(cell: &Cell) -> bool {
+    cell.symbol() == " " && cell.bg == Color::Reset && cell.modifier.is_empty()
+}
 
 /// Append a `u16` to a `String` without allocating an intermediate `String`.
 fn push_u16(s: &mut String, n: u16) {
@@ -153,6 +1511,123 @@ fn push_u16(s: &mut String, n: u16) {
     s.push((b'0' + (n % 10) as u8) as char);
 }
 
+/// xterm's default RGB values for the 16 ANSI colours.
+const ANSI_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The RGB value xterm shows for `color`, or `None` for [`Color::Reset`].
+fn xterm_rgb(color: Color) -> Option<(u8, u8, u8)> {
+    let index = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => return Some((r, g, b)),
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+        Color::Indexed(n) => n,
+    };
+    Some(match index {
+        0..=15 => ANSI_RGB[usize::from(index)],
+        16..=231 => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + 40 * n };
+            let n = index - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        _ => {
+            let gray = 8 + 10 * (index - 232);
+            (gray, gray, gray)
+        }
+    })
+}
+
+/// `color` as a CSS hex colour, or `None` for [`Color::Reset`].
+fn css_color(color: Color) -> Option<String> {
+    xterm_rgb(color).map(|(r, g, b)| format!("#{r:02x}{g:02x}{b:02x}"))
+}
+
+/// Append `text` to `out`, wrapped in a `<span>` when `style` is not the
+/// default.
+fn push_html_run(out: &mut String, style: SgrState, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    let (mut fg, mut bg) = (css_color(style.fg), css_color(style.bg));
+    if style.modifier.contains(Modifier::REVERSED) {
+        (fg, bg) = (
+            Some(bg.unwrap_or_else(|| "var(--tui2web-bg)".to_string())),
+            Some(fg.unwrap_or_else(|| "var(--tui2web-fg)".to_string())),
+        );
+    }
+    let mut css = Vec::new();
+    if let Some(fg) = fg {
+        css.push(format!("color:{fg}"));
+    }
+    if let Some(bg) = bg {
+        css.push(format!("background-color:{bg}"));
+    }
+    let modifier = style.modifier;
+    if modifier.contains(Modifier::BOLD) {
+        css.push("font-weight:bold".to_string());
+    }
+    if modifier.contains(Modifier::DIM) {
+        css.push("opacity:0.5".to_string());
+    }
+    if modifier.contains(Modifier::ITALIC) {
+        css.push("font-style:italic".to_string());
+    }
+    match (modifier.contains(Modifier::UNDERLINED), modifier.contains(Modifier::CROSSED_OUT)) {
+        (true, true) => css.push("text-decoration:underline line-through".to_string()),
+        (true, false) => css.push("text-decoration:underline".to_string()),
+        (false, true) => css.push("text-decoration:line-through".to_string()),
+        (false, false) => {}
+    }
+
+    if !css.is_empty() {
+        out.push_str("<span style=\"");
+        out.push_str(&css.join(";"));
+        out.push_str("\">");
+    }
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+    if !css.is_empty() {
+        out.push_str("</span>");
+    }
+}
+
 fn push_fg_color(out: &mut String, color: Color) {
     match color {
         Color::Reset => out.push_str("\x1b[39m"),
@@ -234,7 +1709,7 @@ impl Backend for WebBackend {
     {
         for (x, y, cell) in content {
             if x < self.width && y < self.height {
-                let idx = usize::from(y) * usize::from(self.width) + usize::from(x);
+                let idx = self.index_of(x, y);
                 self.cells[idx] = cell.clone();
             }
         }
@@ -255,9 +1730,11 @@ impl Backend for WebBackend {
         Ok((self.cursor_x, self.cursor_y))
     }
 
+    /// Positions outside the buffer are clamped to its last column and row,
+    /// so the backend never stores an out-of-bounds cursor, e.g. when
+    /// ratatui positions it for the old size during a shrink.
     fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
-        self.cursor_x = x;
-        self.cursor_y = y;
+        self.clamp_cursor(x, y);
         Ok(())
     }
 
@@ -265,6 +1742,7 @@ impl Backend for WebBackend {
         for cell in &mut self.cells {
             *cell = Cell::default();
         }
+        self.full_redraw = true;
         Ok(())
     }
 
@@ -283,7 +1761,45 @@ impl Backend for WebBackend {
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.ansi_output = self.render_to_ansi();
+        // A frame that rang the bell is never kept, so it cannot ring twice.
+        self.frame_unchanged = !self.full_redraw
+            && !self.bell_pending
+            && !self.ansi_output.ends_with('\x07')
+            && self.selection == self.last_selection
+            && self.last_cursor == Some((self.cursor_x, self.cursor_y))
+            && self.last_cursor_shown == self.cursor_shown()
+            && self.cells == self.last_flushed;
+        if self.frame_unchanged {
+            return Ok(());
+        }
+        // Reuse the previous frame's allocation.
+        let mut out = std::mem::take(&mut self.ansi_output);
+        out.clear();
+        if self.incremental && !self.full_redraw {
+            let mut dirty: Vec<usize> = diff_cells(&self.last_flushed, &self.cells, self.width)
+                .into_iter()
+                .map(|c| self.index_of(c.x, c.y))
+                .collect();
+            // Selected cells change appearance without being drawn.
+            dirty.extend(self.selection.symmetric_difference(&self.last_selection));
+            dirty.sort_unstable();
+            dirty.dedup();
+            self.render_dirty_into(&dirty, &mut out);
+        } else {
+            self.render_to_ansi_into(&mut out);
+        }
+        self.ansi_output = out;
+        self.last_selection = std::mem::take(&mut self.selection);
+        self.full_redraw = false;
+        self.last_cursor = Some((self.cursor_x, self.cursor_y));
+        self.last_cursor_shown = self.cursor_shown();
+        if self.last_cursor_shown {
+            self.last_cursor_style = self.cursor_style;
+        }
+        self.last_flushed.clone_from(&self.cells);
+        if std::mem::take(&mut self.bell_pending) {
+            self.ansi_output.push('\x07');
+        }
         Ok(())
     }
 }
@@ -306,6 +1822,27 @@ mod tests {
         assert_eq!(rect.height, 24);
     }
 
+    #[test]
+    fn click_coordinates_resolve_to_list_rows() {
+        use ratatui::widgets::{Block, Borders, List};
+
+        let mut terminal = Terminal::new(WebBackend::new(20, 6)).unwrap();
+        terminal
+            .draw(|f| {
+                let list = List::new(["apples", "bananas", "cherries"])
+                    .block(Block::default().borders(Borders::ALL));
+                f.render_widget(list, f.size());
+            })
+            .unwrap();
+
+        let backend = terminal.backend();
+        // Row 0 is the border, so a click on row 2 hits the second item.
+        assert_eq!(backend.text_at_line(2), "│bananas           │");
+        assert_eq!(backend.cell_at(1, 2).map(Cell::symbol), Some("b"));
+        assert_eq!(backend.cell_at(20, 2), None);
+        assert_eq!(backend.text_at_line(6), "");
+    }
+
     #[test]
     fn flush_produces_ansi_output() {
         let backend = WebBackend::new(20, 5);
@@ -321,6 +1858,66 @@ mod tests {
         assert!(ansi.contains("hello"), "expected cell content in ANSI output");
     }
 
+    #[test]
+    fn identical_frame_skips_flush() {
+        let mut terminal = Terminal::new(WebBackend::new(20, 3)).unwrap();
+        let draw = |f: &mut ratatui::Frame| f.render_widget(Paragraph::new("idle"), f.size());
+        terminal.draw(draw).unwrap();
+        assert!(!terminal.backend().frame_unchanged());
+        let first = terminal.backend().get_ansi_output().to_string();
+
+        terminal.draw(draw).unwrap();
+        assert!(terminal.backend().frame_unchanged());
+        assert_eq!(terminal.backend().get_ansi_output(), first);
+
+        terminal.backend_mut().bell();
+        terminal.draw(draw).unwrap();
+        assert!(!terminal.backend().frame_unchanged());
+        assert!(terminal.backend().get_ansi_output().ends_with('\x07'));
+    }
+
+    #[test]
+    fn write_frame_to_streams_the_flushed_frame() {
+        let mut terminal = Terminal::new(WebBackend::new(12, 2)).unwrap();
+        terminal.draw(|f| f.render_widget(Paragraph::new("relay"), f.size())).unwrap();
+
+        let mut sink = String::new();
+        terminal.backend().write_frame_to(&mut sink).unwrap();
+        assert_eq!(sink, terminal.backend().get_ansi_output());
+    }
+
+    #[test]
+    fn frame_utf16_len_counts_surrogate_pairs() {
+        let mut terminal = Terminal::new(WebBackend::new(12, 2)).unwrap();
+        terminal.draw(|f| f.render_widget(Paragraph::new("hi 😀"), f.size())).unwrap();
+
+        let backend = terminal.backend();
+        let chars = backend.get_ansi_output().chars().count();
+        assert_eq!(backend.frame_utf16_len(), chars + 1);
+        assert_eq!(backend.frame_utf16_len(), backend.get_ansi_output().encode_utf16().count());
+    }
+
+    #[test]
+    fn frame_hash_tracks_frame_contents() {
+        let mut terminal = Terminal::new(WebBackend::new(12, 2)).unwrap();
+        let mut draw = |text: &'static str| {
+            terminal.draw(|f| f.render_widget(Paragraph::new(text), f.size())).unwrap();
+            terminal.backend().frame_hash()
+        };
+        let first = draw("same");
+        assert_eq!(draw("same"), first);
+        assert_ne!(draw("changed"), first);
+        assert_eq!(WebBackend::new(1, 1).frame_hash(), 0xcbf2_9ce4_8422_2325);
+    }
+
+    #[test]
+    fn try_new_rejects_absurd_sizes() {
+        let err = WebBackend::try_new(u16::MAX, u16::MAX).err().unwrap();
+        assert_eq!(err, BackendError::TooLarge { width: u16::MAX, height: u16::MAX });
+        assert!(WebBackend::try_new(1024, 1024).is_ok());
+        assert!(WebBackend::try_new(0, 0).is_ok());
+    }
+
     #[test]
     fn resize_updates_dimensions() {
         let mut backend = WebBackend::new(40, 10);
@@ -333,6 +1930,125 @@ mod tests {
             80 * 24,
             "cell buffer length should match new dimensions"
         );
+
+        let err = backend.try_resize(u16::MAX, u16::MAX).unwrap_err();
+        assert_eq!(err, BackendError::TooLarge { width: u16::MAX, height: u16::MAX });
+        assert_eq!(backend.size().unwrap(), Rect::new(0, 0, 80, 24));
+    }
+
+    /// A 100-row buffer behind a 24-row viewport, row `n` reading `row n`.
+    fn scrolling_terminal() -> Terminal<WebBackend> {
+        let mut backend = WebBackend::new(20, 24);
+        backend.set_logical_height(100).unwrap();
+        let mut terminal = Terminal::new(backend).unwrap();
+        let lines: Vec<ratatui::text::Line> =
+            (0..100).map(|n| format!("row {n}").into()).collect();
+        terminal.draw(|f| f.render_widget(Paragraph::new(lines), f.size())).unwrap();
+        terminal
+    }
+
+    #[test]
+    fn scroll_offset_selects_the_visible_rows() {
+        let mut terminal = scrolling_terminal();
+        assert_eq!(terminal.size().unwrap().height, 100);
+        let ansi = terminal.backend().get_ansi_output();
+        assert!(ansi.contains("\x1b[24;1Hrow 23\x1b"));
+        assert!(!ansi.contains("row 24") && !ansi.contains("\x1b[25;"));
+
+        terminal.backend_mut().set_scroll_offset(50);
+        terminal.backend_mut().flush().unwrap();
+        let ansi = terminal.backend().get_ansi_output();
+        assert!(ansi.contains("\x1b[1;1Hrow 50\x1b"));
+        assert!(ansi.contains("\x1b[24;1Hrow 73\x1b"));
+        assert!(!ansi.contains("row 49") && !ansi.contains("row 74"));
+
+        terminal.backend_mut().set_scroll_offset(500);
+        assert_eq!(terminal.backend().scroll_offset(), 76);
+        terminal.backend_mut().flush().unwrap();
+        assert!(terminal.backend().get_ansi_output().contains("\x1b[24;1Hrow 99\x1b"));
+    }
+
+    #[test]
+    fn scrolled_out_cursor_is_hidden_and_resize_keeps_logical_height() {
+        let mut terminal = scrolling_terminal();
+        let backend = terminal.backend_mut();
+        backend.set_incremental(true);
+        backend.show_cursor().unwrap();
+        backend.set_cursor(2, 80).unwrap();
+        backend.flush().unwrap();
+        assert!(!backend.get_ansi_output().ends_with("\x1b[?25h"));
+
+        backend.set_scroll_offset(70);
+        backend.flush().unwrap();
+        assert!(backend.get_ansi_output().ends_with("\x1b[11;3H\x1b[?25h"));
+
+        backend.resize(20, 30);
+        assert_eq!((backend.logical_height(), backend.scroll_offset()), (100, 70));
+        backend.set_logical_height(0).unwrap();
+        assert_eq!((backend.logical_height(), backend.scroll_offset()), (30, 0));
+        assert!(backend.set_logical_height(u16::MAX).is_err());
+    }
+
+    #[test]
+    fn cursor_is_clamped_to_the_buffer() {
+        let mut backend = WebBackend::new(10, 4);
+        backend.set_cursor(25, 9).unwrap();
+        assert_eq!(backend.get_cursor().unwrap(), (9, 3));
+
+        backend.set_cursor(7, 2).unwrap();
+        backend.resize(5, 2);
+        assert_eq!(backend.get_cursor().unwrap(), (4, 1));
+        assert!(backend.render_frame().contains("\x1b[2;5H"));
+    }
+
+    #[test]
+    fn sparse_frames_send_only_non_blank_cells() {
+        let mut backend = WebBackend::new(40, 10);
+        let mut cell = Cell::default();
+        cell.set_symbol("x").set_fg(Color::Green);
+        let cells: Vec<_> = (15..25).map(|x| (x, 4, &cell)).collect();
+        backend.draw(cells.into_iter()).unwrap();
+        let dense = backend.render_frame();
+
+        backend.set_sparse(true);
+        let sparse = backend.render_frame();
+        assert!(sparse.len() < dense.len());
+        assert!(sparse.contains("\x1b[2J\x1b[5;16H\x1b[0m\x1b[32mxxxxxxxxxx\x1b[0m"));
+
+        // A full screen is still sent densely.
+        let full: Vec<_> = (0..40).flat_map(|x| (0..10).map(move |y| (x, y))).collect();
+        backend.draw(full.iter().map(|&(x, y)| (x, y, &cell))).unwrap();
+        assert!(!backend.render_frame().contains("\x1b[2J"));
+    }
+
+    #[test]
+    fn diff_cells_reports_changed_coordinates() {
+        let mut backend = WebBackend::new(4, 3);
+        let before = backend.capture();
+        let mut cell = Cell::default();
+        cell.set_symbol("x");
+        backend.draw([(3, 0, &cell), (1, 2, &cell), (2, 2, &Cell::default())].into_iter()).unwrap();
+
+        let changes = diff_cells(&before.cells, &backend.capture().cells, 4);
+        assert_eq!(changes, [CellChange { x: 3, y: 0 }, CellChange { x: 1, y: 2 }]);
+    }
+
+    #[test]
+    fn cleared_popup_uses_popup_background() {
+        let mut terminal = Terminal::new(WebBackend::new(8, 3)).unwrap();
+        let popup = Rect::new(2, 1, 3, 1);
+        terminal.backend_mut().set_popup_bg(Some(Color::Blue));
+        terminal.backend_mut().set_popup_area(Some(popup));
+        terminal
+            .draw(|f| {
+                f.render_widget(Paragraph::new("abcdefgh\nijklmnop"), f.size());
+                f.render_widget(ratatui::widgets::Clear, popup);
+            })
+            .unwrap();
+
+        let ansi = terminal.backend().get_ansi_output();
+        assert!(ansi.contains("ij\x1b[0m\x1b[44m   \x1b[0mnop"), "unexpected output: {ansi:?}");
+        assert_eq!(ansi.matches("\x1b[44m").count(), 1);
     }
 
     #[test]
@@ -350,6 +2066,412 @@ mod tests {
         }
     }
 
+    #[test]
+    fn trailing_blanks_use_erase_to_end_of_line() {
+        let backend = WebBackend::new(40, 2);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| f.render_widget(Paragraph::new("hi"), f.size()))
+            .unwrap();
+        let ansi = terminal.backend().get_ansi_output();
+        assert!(ansi.contains("hi\x1b[K"), "expected EL right after the text");
+        assert!(!ansi.contains("  "), "trailing spaces should not be emitted");
+    }
+
+    #[test]
+    fn render_region_only_covers_the_rect() {
+        let mut backend = WebBackend::new(10, 5);
+        for (i, cell) in backend.cells.iter_mut().enumerate() {
+            cell.set_symbol(&((b'a' + (i % 26) as u8) as char).to_string());
+        }
+        // Cells (3,1) (4,1) (3,2) (4,2) hold "n", "o", "x", "y".
+        let ansi = backend.render_region_to_ansi(Rect::new(3, 1, 2, 2));
+        assert_eq!(ansi, "\x1b[2;4Hno\x1b[3;4Hxy\x1b[0m");
+
+        // Rects hanging off the buffer are clamped.
+        let clamped = backend.render_region_to_ansi(Rect::new(9, 4, 5, 5));
+        assert_eq!(clamped, "\x1b[5;10Hx\x1b[0m");
+    }
+
+    #[test]
+    fn clear_output_empties_until_the_next_full_frame() {
+        let mut backend = WebBackend::new(10, 2);
+        backend.set_incremental(true);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| f.render_widget(Paragraph::new("hello"), f.size())).unwrap();
+        let full = terminal.backend().get_ansi_output().to_string();
+
+        terminal.backend_mut().clear_output();
+        assert_eq!(terminal.backend().get_ansi_output(), "");
+
+        // Nothing changed, yet the next frame is a full repaint.
+        terminal.draw(|f| f.render_widget(Paragraph::new("hello"), f.size())).unwrap();
+        assert_eq!(terminal.backend().get_ansi_output(), full);
+    }
+
+    #[test]
+    fn control_characters_are_escaped_when_enabled() {
+        let mut backend = WebBackend::new(4, 1);
+        for (cell, symbol) in backend.cells.iter_mut().zip(["a", "\x01", "\u{9b}", "b"]) {
+            cell.set_symbol(symbol);
+        }
+        let row = Rect::new(0, 0, 4, 1);
+        assert_eq!(backend.render_region_to_ansi(row), "\x1b[1;1Ha\x01\u{9b}b\x1b[0m");
+
+        backend.set_control_chars(ControlChars::Placeholder('·'));
+        assert_eq!(backend.render_region_to_ansi(row), "\x1b[1;1Ha··b\x1b[0m");
+        backend.flush().unwrap();
+        let ansi = backend.get_ansi_output();
+        assert!(ansi.contains("a··b") && !ansi.contains('\x01'), "{ansi:?}");
+        assert!(backend.estimate_frame_len() >= ansi.len());
+
+        // The grid stays one column per cell whatever the cell holds.
+        backend.cells[0].set_symbol("x\x1b");
+        backend.set_control_chars(ControlChars::Placeholder('日'));
+        assert_eq!(backend.render_region_to_ansi(row), "\x1b[1;1Hx\u{fffd}\u{fffd}b\x1b[0m");
+    }
+
+    #[test]
+    fn incremental_update_uses_save_restore_and_relative_moves() {
+        let mut backend = WebBackend::new(10, 2);
+        backend.set_incremental(true);
+        backend.set_relative_moves(true);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| f.render_widget(Paragraph::new("hello"), f.size())).unwrap();
+        assert!(terminal.backend().get_ansi_output().contains("\x1b[K"), "first frame is full");
+
+        terminal.draw(|f| f.render_widget(Paragraph::new("help!"), f.size())).unwrap();
+        assert_eq!(terminal.backend().get_ansi_output(), "\x1b[?25l\x1b7\x1b[1;4Hp!\x1b[0m\x1b8");
+
+        terminal.draw(|f| f.render_widget(Paragraph::new("Help.."), f.size())).unwrap();
+        let ansi = terminal.backend().get_ansi_output();
+        assert!(ansi.contains("\x1b[1;1HH\x1b[3C.."), "gap uses a relative move: {ansi:?}");
+
+        // Without the flag every update is positioned absolutely.
+        terminal.backend_mut().set_relative_moves(false);
+        terminal.draw(|f| f.render_widget(Paragraph::new("Help!."), f.size())).unwrap();
+        assert_eq!(terminal.backend().get_ansi_output(), "\x1b[?25l\x1b[1;5H!\x1b[0m\x1b[1;1H");
+    }
+
+    #[test]
+    fn clear_region_only_resets_cells_inside() {
+        let mut terminal = Terminal::new(WebBackend::new(6, 3)).unwrap();
+        terminal
+            .draw(|f| f.render_widget(Paragraph::new("abcdef\nghijkl\nmnopqr"), f.size()))
+            .unwrap();
+        let backend = terminal.backend_mut();
+        backend.clear_region(Rect::new(1, 1, 3, 5));
+        assert_eq!(backend.to_plain_text(), "abcdef\ng   kl\nm   qr");
+
+        backend.set_incremental(true);
+        backend.flush().unwrap();
+        backend.clear_region(Rect::new(4, 0, 1, 1));
+        backend.flush().unwrap();
+        assert!(backend.get_ansi_output().contains("\x1b[1;5H \x1b[0m"));
+    }
+
+    #[test]
+    fn selection_reverses_cells_for_one_frame() {
+        let mut terminal = Terminal::new(WebBackend::new(6, 1)).unwrap();
+        terminal.backend_mut().set_selection(&[(1, 0), (2, 0), (9, 9)]);
+        terminal.draw(|f| f.render_widget(Paragraph::new("abcd"), f.size())).unwrap();
+        let ansi = terminal.backend().get_ansi_output();
+        assert!(ansi.contains("a\x1b[0m\x1b[7mbc\x1b[0md"), "unexpected output: {ansi:?}");
+
+        terminal.draw(|f| f.render_widget(Paragraph::new("abcd"), f.size())).unwrap();
+        assert!(!terminal.backend().get_ansi_output().contains("\x1b[7m"));
+    }
+
+    #[test]
+    fn modifier_bits_are_stable() {
+        let mut cell = Cell::default();
+        assert_eq!(modifier_bits(&cell), 0);
+        cell.modifier = Modifier::BOLD | Modifier::ITALIC;
+        assert_eq!(modifier_bits(&cell), 0b101);
+        cell.modifier = Modifier::all();
+        assert_eq!(modifier_bits(&cell), 0x1ff);
+    }
+
+    #[test]
+    fn render_into_appends_to_caller_buffer() {
+        let mut terminal = Terminal::new(WebBackend::new(12, 2)).unwrap();
+        terminal.draw(|f| f.render_widget(Paragraph::new("pooled"), f.size())).unwrap();
+        let backend = terminal.backend();
+
+        let mut buf = String::with_capacity(4096);
+        buf.push_str("prefix:");
+        let capacity = buf.capacity();
+        backend.render_to_ansi_into(&mut buf);
+        assert_eq!(buf.strip_prefix("prefix:"), Some(backend.get_ansi_output()));
+        assert_eq!(buf.capacity(), capacity);
+    }
+
+    #[test]
+    fn palette_override_emits_truecolor() {
+        let mut backend = WebBackend::new(4, 1);
+        let mut cell = Cell::default();
+        cell.set_symbol("x").set_fg(Color::Indexed(1)).set_bg(Color::Indexed(200));
+        backend.draw([(0, 0, &cell)].into_iter()).unwrap();
+        assert!(backend.render_frame().contains("\x1b[38;5;1m\x1b[48;5;200mx"));
+
+        backend.set_palette_override(1, (250, 80, 10));
+        let ansi = backend.render_frame();
+        assert!(ansi.contains("\x1b[38;2;250;80;10m\x1b[48;5;200mx"), "{ansi:?}");
+
+        backend.clear_palette_override(1);
+        assert!(backend.render_frame().contains("\x1b[38;5;1m"));
+    }
+
+    #[test]
+    fn load_ansi_round_trips_a_rendered_frame() {
+        use ratatui::{style::Stylize, text::Line};
+
+        for compact in [false, true] {
+            let mut terminal = Terminal::new(WebBackend::new(24, 3)).unwrap();
+            terminal.backend_mut().set_compact(compact);
+            terminal.backend_mut().set_cursor_style(CursorStyle::SteadyBar);
+            terminal
+                .draw(|f| {
+                    let lines = vec![
+                        Line::from(vec![
+                            Span::styled("Hi", Style::default().fg(Color::Red).bold()),
+                            Span::raw("      gap"),
+                            Span::styled(" rgb ", Style::default().bg(Color::Rgb(1, 2, 3))),
+                        ]),
+                        Line::from(vec![
+                            Span::styled("日本", Style::default().fg(Color::Indexed(200))),
+                            Span::styled("cafe\u{301}", Style::default().reversed().italic()),
+                        ]),
+                        Line::from(Span::styled("x", Style::default().bg(Color::LightCyan))),
+                    ];
+                    f.render_widget(Paragraph::new(lines), f.size());
+                    f.set_cursor(3, 1);
+                })
+                .unwrap();
+            let original = terminal.backend();
+
+            let mut loaded = WebBackend::new(24, 3);
+            loaded.hide_cursor().unwrap();
+            loaded.load_ansi(original.get_ansi_output());
+            assert_eq!(loaded.capture(), original.capture(), "compact: {compact}");
+        }
+    }
+
+    #[test]
+    fn take_damage_reports_changed_cells_as_runs() {
+        let mut backend = WebBackend::new(6, 2);
+        assert_eq!(backend.take_damage().len(), 2, "first call reports everything");
+        assert!(backend.take_damage().is_empty());
+
+        let red = Style::default().fg(Color::Red);
+        let mut cells = [Cell::default(), Cell::default(), Cell::default(), Cell::default()];
+        cells[0].set_symbol("a");
+        cells[1].set_symbol("b");
+        cells[2].set_symbol("c").set_style(red);
+        cells[3].set_symbol("日");
+        let updates = [(1, 0, &cells[0]), (2, 0, &cells[1]), (3, 0, &cells[2]), (4, 1, &cells[3])];
+        backend.draw(updates.into_iter()).unwrap();
+
+        let runs = backend.take_damage();
+        let spans: Vec<_> = runs.iter().map(|r| (r.x, r.y, r.width, r.text.as_str())).collect();
+        assert_eq!(spans, [(1, 0, 2, "ab"), (3, 0, 1, "c"), (4, 1, 2, "日")]);
+        assert_eq!((runs[1].fg, runs[0].fg), (Color::Red, Color::Reset));
+        assert!(backend.take_damage().is_empty());
+    }
+
+    #[test]
+    fn estimate_frame_len_bounds_the_rendered_frame() {
+        use ratatui::{style::Stylize, text::Line};
+
+        for (compact, sparse) in [(false, false), (true, false), (false, true)] {
+            let mut terminal = Terminal::new(WebBackend::new(30, 4)).unwrap();
+            terminal.backend_mut().set_compact(compact);
+            terminal.backend_mut().set_sparse(sparse);
+            terminal
+                .draw(|f| {
+                    let lines = vec![
+                        Line::from(vec![
+                            "bold".bold().red(),
+                            Span::raw("          "),
+                            "x".fg(Color::Rgb(255, 255, 255)).on_blue(),
+                        ]),
+                        Line::from("日本語 tab\there".italic().underlined()),
+                        Line::from("idx".fg(Color::Indexed(123))),
+                    ];
+                    f.render_widget(Paragraph::new(lines), f.size());
+                })
+                .unwrap();
+            let backend = terminal.backend();
+            let (estimate, actual) = (backend.estimate_frame_len(), backend.render_frame().len());
+            assert!(estimate >= actual, "{estimate} < {actual} (compact {compact})");
+            assert!(estimate < actual * 2, "{estimate} is far above {actual}");
+        }
+    }
+
+    #[test]
+    fn restore_reinstates_cursor_state() {
+        let mut backend = WebBackend::new(8, 2);
+        backend.set_cursor(3, 1).unwrap();
+        backend.set_cursor_style(CursorStyle::SteadyBar);
+        backend.hide_cursor().unwrap();
+        let snapshot = backend.capture();
+
+        backend.show_cursor().unwrap();
+        backend.set_cursor(0, 0).unwrap();
+        backend.set_cursor_style(CursorStyle::Default);
+        assert!(backend.render_frame().ends_with("\x1b[1;1H\x1b[?25h"));
+
+        backend.restore(&snapshot).unwrap();
+        assert_eq!(backend.capture(), snapshot);
+        assert!(backend.render_frame().ends_with("\x1b[2;4H"));
+        backend.show_cursor().unwrap();
+        assert!(backend.render_frame().ends_with("\x1b[2;4H\x1b[6 q\x1b[?25h"));
+
+        let before = backend.capture();
+        let mut short = snapshot.clone();
+        short.cells.pop();
+        let err = backend.restore(&short).unwrap_err();
+        assert_eq!(err, BackendError::SizeMismatch { width: 8, height: 2, cells: 15 });
+        assert_eq!(backend.capture(), before);
+    }
+
+    #[test]
+    fn cursor_shape_is_reset_when_returning_to_default() {
+        let mut backend = WebBackend::new(4, 1);
+        backend.set_cursor_style(CursorStyle::SteadyBar);
+        backend.flush().unwrap();
+        assert!(backend.get_ansi_output().ends_with("\x1b[6 q\x1b[?25h"));
+
+        backend.set_cursor_style(CursorStyle::Default);
+        backend.flush().unwrap();
+        assert!(backend.get_ansi_output().ends_with("\x1b[0 q\x1b[?25h"));
+        backend.set_cursor(1, 0).unwrap();
+        backend.flush().unwrap();
+        assert!(!backend.get_ansi_output().contains(" q"));
+    }
+
+    #[test]
+    fn html_export_styles_runs_and_escapes_text() {
+        let mut backend = WebBackend::new(6, 2);
+        let mut bold = Cell::default();
+        bold.set_symbol("<").set_fg(Color::Indexed(196)).modifier = Modifier::BOLD;
+        let mut reversed = Cell::default();
+        reversed.set_symbol("&").modifier = Modifier::REVERSED;
+        let plain = Cell::default().set_symbol("a").clone();
+        backend
+            .draw([(0, 0, &bold), (1, 0, &plain), (0, 1, &reversed)].into_iter())
+            .unwrap();
+
+        assert_eq!(
+            backend.to_html(),
+            "<span style=\"color:#ff0000;font-weight:bold\">&lt;</span>a    \n\
+             <span style=\"color:var(--tui2web-bg);background-color:var(--tui2web-fg)\">\
+             &amp;</span>     "
+        );
+    }
+
+    #[test]
+    fn bell_is_emitted_once() {
+        let mut backend = WebBackend::new(4, 1);
+        backend.bell();
+        backend.bell();
+        backend.flush().unwrap();
+        assert_eq!(backend.get_ansi_output().matches('\x07').count(), 1);
+        assert!(backend.get_ansi_output().ends_with('\x07'));
+
+        backend.flush().unwrap();
+        assert!(!backend.get_ansi_output().contains('\x07'));
+    }
+
+    #[test]
+    fn render_frame_does_not_flush() {
+        let mut backend = WebBackend::new(4, 1);
+        let mut o = Cell::default();
+        o.set_symbol("o");
+        let mut k = Cell::default();
+        k.set_symbol("k").set_fg(Color::Green);
+        backend.draw([(0, 0, &o), (1, 0, &k)].into_iter()).unwrap();
+
+        let frame = backend.render_frame();
+        assert!(frame.contains("o\x1b[0m\x1b[32mk"), "unexpected frame: {frame:?}");
+        assert!(backend.get_ansi_output().is_empty(), "render_frame must not flush");
+
+        backend.flush().unwrap();
+        assert_eq!(backend.get_ansi_output(), frame);
+    }
+
+    #[test]
+    fn hidden_text_is_concealed() {
+        let backend = WebBackend::new(20, 1);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| {
+                let line = ratatui::text::Line::from(vec![
+                    Span::raw("pw: "),
+                    Span::styled("secret", Style::default().add_modifier(Modifier::HIDDEN)),
+                ]);
+                f.render_widget(Paragraph::new(line), f.size());
+            })
+            .unwrap();
+        let backend = terminal.backend();
+        assert!(backend.get_ansi_output().contains("\x1b[8msecret"));
+        assert_eq!(backend.to_plain_text(), "pw:");
+    }
+
+    #[test]
+    fn private_modes_can_be_disabled() {
+        let mut backend = WebBackend::new(10, 2);
+        assert!(backend.render_frame().contains("\x1b[?25"));
+        backend.set_emit_private_modes(false);
+        for visible in [true, false] {
+            backend.cursor_visible = visible;
+            assert!(!backend.render_frame().contains("?25"));
+        }
+    }
+
+    #[test]
+    fn forced_hidden_cursor_is_never_shown() {
+        let mut backend = WebBackend::new(10, 2);
+        backend.set_force_cursor_hidden(true);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| {
+                f.render_widget(Paragraph::new("> "), f.size());
+                f.set_cursor(2, 0);
+            })
+            .unwrap();
+        terminal.show_cursor().unwrap();
+        terminal.draw(|f| f.set_cursor(2, 0)).unwrap();
+
+        let ansi = terminal.backend().get_ansi_output();
+        assert!(ansi.starts_with("\x1b[?25l"));
+        assert!(!ansi.contains("\x1b[?25h"));
+        assert!(ansi.ends_with("\x1b[1;3H"));
+    }
+
+    #[test]
+    fn compact_mode_keeps_border_glyphs() {
+        use ratatui::widgets::{Block, Borders};
+
+        let mut backend = WebBackend::new(40, 6);
+        backend.set_compact(true);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| {
+                let block = Block::default().borders(Borders::ALL).title("box");
+                f.render_widget(Paragraph::new("hi").block(block), f.size());
+            })
+            .unwrap();
+        let ansi = terminal.backend().get_ansi_output();
+
+        // The interior of each row collapses, but every border survives.
+        assert!(ansi.contains("│\x1b[38X\x1b[38C│"), "expected erase runs: {ansi:?}");
+        assert!(ansi.contains("┌box"));
+        assert_eq!(ansi.matches('│').count(), 8);
+        assert_eq!(ansi.matches('─').count(), 35 + 38);
+        assert!(ansi.contains('┘'));
+    }
+
     #[test]
     fn color_and_style_appear_in_output() {
         let backend = WebBackend::new(40, 5);
@@ -368,4 +2490,27 @@ mod tests {
         assert!(ansi.contains("\x1b[31m"), "expected red foreground escape code");
         assert!(ansi.contains("\x1b[44m"), "expected blue background escape code");
     }
+
+    #[test]
+    fn text_after_a_tab_is_kept() {
+        let mut terminal = Terminal::new(WebBackend::new(20, 1)).unwrap();
+        terminal.draw(|f| f.render_widget(Paragraph::new("a\tbcdefghijk"), f.size())).unwrap();
+        let backend = terminal.backend();
+
+        assert_eq!(backend.to_plain_text(), "a bcdefghijk");
+        assert!(backend.get_ansi_output().contains("a bcdefghijk\x1b[K"));
+        assert!(!backend.get_ansi_output().contains('\t'));
+    }
+
+    #[test]
+    fn wide_glyphs_skip_the_cells_they_cover() {
+        let mut terminal = Terminal::new(WebBackend::new(10, 1)).unwrap();
+        terminal.draw(|f| f.render_widget(Paragraph::new("abcd"), f.size())).unwrap();
+        terminal.draw(|f| f.render_widget(Paragraph::new("日x"), f.size())).unwrap();
+        let backend = terminal.backend();
+
+        assert_eq!(backend.to_plain_text(), "日x");
+        assert!(backend.get_ansi_output().contains("日x\x1b[K"));
+        assert_eq!(crate::display_width(&backend.to_plain_text()), 3);
+    }
 }
@@ -1,12 +1,21 @@
 //! Virtual filesystem abstraction for running TUI applications in the browser.
 //!
 //! Provides a [`Filesystem`] trait that abstracts file operations, plus an
-//! [`MemoryFilesystem`] implementation backed by in-memory storage.  When
+//! [`MemoryFilesystem`] implementation backed by in-memory storage and an
+//! [`OverlayFilesystem`] that layers writes over a read-only base.  When
 //! running under WebAssembly the memory filesystem can optionally be
 //! persisted to `localStorage` via the JavaScript bridge in `web/main.js`.
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fmt;
+use std::io;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::tar::EntryKind;
+pub use crate::tar::{ExtractProgress, TarExtractor};
 
 // ── Error types ──────────────────────────────────────────────────────────────
 
@@ -22,7 +31,12 @@ pub enum FsError {
     /// The directory is not empty.
     NotEmpty(String),
     /// The operation expected a file but found a directory, or vice-versa.
+    /// Also returned when trying to remove or move the root directory.
     WrongKind(String),
+    /// The path is not valid UTF-8 (the lossy rendering is carried for display).
+    InvalidPath(String),
+    /// An archive could not be decoded.
+    InvalidArchive(String),
 }
 
 impl fmt::Display for FsError {
@@ -33,12 +47,29 @@ impl fmt::Display for FsError {
             FsError::ParentNotFound(p) => write!(f, "parent directory not found: {p}"),
             FsError::NotEmpty(p) => write!(f, "directory not empty: {p}"),
             FsError::WrongKind(p) => write!(f, "wrong kind: {p}"),
+            FsError::InvalidPath(p) => write!(f, "invalid path (not UTF-8): {p}"),
+            FsError::InvalidArchive(msg) => write!(f, "invalid archive: {msg}"),
         }
     }
 }
 
 impl std::error::Error for FsError {}
 
+/// Lets filesystem errors flow into `std::io` APIs (and `?` in functions
+/// returning `io::Result`), with the closest [`io::ErrorKind`].
+impl From<FsError> for io::Error {
+    fn from(e: FsError) -> Self {
+        let kind = match e {
+            FsError::NotFound(_) | FsError::ParentNotFound(_) => io::ErrorKind::NotFound,
+            FsError::AlreadyExists(_) => io::ErrorKind::AlreadyExists,
+            FsError::NotEmpty(_) => io::ErrorKind::DirectoryNotEmpty,
+            FsError::WrongKind(_) | FsError::InvalidPath(_) => io::ErrorKind::InvalidInput,
+            FsError::InvalidArchive(_) => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
 // ── Data types ───────────────────────────────────────────────────────────────
 
 /// Entry returned by [`Filesystem::read_dir`].
@@ -50,6 +81,17 @@ pub struct DirEntry {
     pub is_dir: bool,
 }
 
+/// Entry returned by [`Filesystem::read_dir_depth`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkEntry {
+    /// Full normalised path of the entry (no leading `/`).
+    pub path: String,
+    /// Depth below the listed directory (immediate children are depth 1).
+    pub depth: usize,
+    /// Whether this entry is a directory.
+    pub is_dir: bool,
+}
+
 /// Metadata about a file or directory.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Metadata {
@@ -59,16 +101,100 @@ pub struct Metadata {
     pub len: u64,
 }
 
+/// How [`MemoryFilesystem::merge_snapshot`] resolves a path whose contents
+/// differ between the two sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Keep the local contents.
+    PreferMine,
+    /// Take the incoming contents.
+    PreferTheirs,
+    /// Take whichever side was saved later.  Files carry no modification
+    /// times of their own, so these are snapshot-level timestamps (e.g.
+    /// [`NamedSnapshot::saved_at`]); a tie keeps the local contents.
+    NewestWins { mine: u64, theirs: u64 },
+}
+
+/// Byte ranges of a file's lines, as returned by
+/// [`MemoryFilesystem::line_index`].
+type LineIndex = Rc<[(u64, u64)]>;
+
+/// Iterator over a file's lines, returned by [`MemoryFilesystem::lines`].
+///
+/// Lines borrow the file's contents and are split with the cached
+/// [`MemoryFilesystem::line_index`], so seeking is constant-time and only
+/// the lines actually pulled are decoded.  Invalid UTF-8 is replaced with
+/// `U+FFFD`.
+#[derive(Debug, Clone)]
+pub struct LineCursor<'a> {
+    data: &'a [u8],
+    /// Shared with the cache, so creating a cursor never copies the index.
+    index: LineIndex,
+    /// Line returned by the next call to `next`.
+    line: usize,
+}
+
+impl LineCursor<'_> {
+    /// Move to line `n` (0-based), so the next line returned is line `n`.
+    /// Seeking past the end leaves the cursor exhausted.
+    pub fn seek_line(&mut self, n: usize) {
+        self.line = n.min(self.index.len());
+    }
+
+    /// The line the next call to `next` returns.
+    pub fn position(&self) -> usize {
+        self.line
+    }
+
+    /// Number of lines in the file.
+    pub fn line_count(&self) -> usize {
+        self.index.len()
+    }
+}
+
+impl<'a> Iterator for LineCursor<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let &(start, end) = self.index.get(self.line)?;
+        self.line += 1;
+        Some(String::from_utf8_lossy(&self.data[start as usize..end as usize]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.index.len() - self.line;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for LineCursor<'_> {}
+
 // ── Trait ─────────────────────────────────────────────────────────────────────
 
 /// Abstraction over filesystem operations.
 ///
 /// Implementations must treat paths as forward-slash separated, UTF-8 strings.
 /// A leading `/` is optional; paths are normalised internally.
+///
+/// The root directory (`""` or `"/"`) always exists: [`exists`](Self::exists)
+/// and [`is_dir`](Self::is_dir) are `true` for it, [`metadata`](Self::metadata)
+/// reports a directory, and [`read_dir`](Self::read_dir) lists the top level.
+/// It cannot be created, removed, or renamed.
+///
+/// Names that are not valid UTF-8 (e.g. from an imported tar archive) are
+/// never stored: importers reject them with [`FsError::InvalidPath`] instead of
+/// lossily converting them, so every stored path round-trips unchanged.
 pub trait Filesystem {
     /// Read the entire contents of a file.
     fn read_file(&self, path: &str) -> Result<Vec<u8>, FsError>;
 
+    /// Read the bytes `start..end` of a file.  The range is clamped to the
+    /// file length, so reading past the end yields a short (or empty) result.
+    fn read_range(&self, path: &str, start: u64, end: u64) -> Result<Vec<u8>, FsError> {
+        let data = self.read_file(path)?;
+        Ok(clamp_range(&data, start, end).to_vec())
+    }
+
     /// Read a file as a UTF-8 string (convenience wrapper).
     fn read_to_string(&self, path: &str) -> Result<String, FsError> {
         let bytes = self.read_file(path)?;
@@ -79,6 +205,21 @@ pub trait Filesystem {
     /// Parent directories must already exist.
     fn write_file(&mut self, path: &str, content: &[u8]) -> Result<(), FsError>;
 
+    /// Create an empty file at `path` unless a file is already there, like
+    /// Unix `touch`.  An existing file's contents are left untouched.
+    ///
+    /// The parent directory must already exist; touching a directory fails
+    /// with [`FsError::WrongKind`].
+    fn touch(&mut self, path: &str) -> Result<(), FsError> {
+        if self.is_file(path) {
+            return Ok(());
+        }
+        if self.is_dir(path) {
+            return Err(FsError::WrongKind(normalise(path)));
+        }
+        self.write_file(path, b"")
+    }
+
     /// Remove a file.  Returns an error if the path is a directory or does not exist.
     fn remove_file(&mut self, path: &str) -> Result<(), FsError>;
 
@@ -86,12 +227,14 @@ pub trait Filesystem {
     fn remove_dir(&mut self, path: &str) -> Result<(), FsError>;
 
     /// Check whether a path exists (file or directory).
+    ///
+    /// Equivalent to `is_dir(path) || is_file(path)`.
     fn exists(&self, path: &str) -> bool;
 
-    /// Check whether a path is a directory.
+    /// Check whether a path exists and is a directory.
     fn is_dir(&self, path: &str) -> bool;
 
-    /// Check whether a path is a file.
+    /// Check whether a path exists and is a file.
     fn is_file(&self, path: &str) -> bool;
 
     /// Create a single directory.  The parent must already exist.
@@ -103,12 +246,54 @@ pub trait Filesystem {
     /// List the immediate children of a directory.
     fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, FsError>;
 
+    /// List the descendants of a directory up to `max_depth` levels deep, in
+    /// depth-first order (each directory is followed by its children).
+    ///
+    /// A depth of 1 yields the same entries as [`read_dir`](Self::read_dir);
+    /// a depth of 0 yields nothing.
+    fn read_dir_depth(&self, path: &str, max_depth: usize) -> Result<Vec<WalkEntry>, FsError> {
+        let mut out = Vec::new();
+        walk_depth(self, &normalise(path), 1, max_depth, &mut out)?;
+        Ok(out)
+    }
+
+    /// List the paths of every file beneath a directory, recursively, in
+    /// lexicographic order.  Paths are full normalised paths (e.g.
+    /// `src/ui/app.rs`), so this is the scoped counterpart of
+    /// [`list_files`](Self::list_files).
+    fn read_dir_all(&self, path: &str) -> Result<Vec<String>, FsError> {
+        let mut files: Vec<String> = self
+            .read_dir_depth(path, usize::MAX)?
+            .into_iter()
+            .filter(|e| !e.is_dir)
+            .map(|e| e.path)
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
     /// Return metadata for a path.
     fn metadata(&self, path: &str) -> Result<Metadata, FsError>;
 
-    /// List every file path in the filesystem (non-recursive convenience).
+    /// List every file path in the filesystem.
+    ///
+    /// Paths are full normalised paths in lexicographic (byte-wise) order;
+    /// implementations must guarantee this ordering.
     fn list_files(&self) -> Vec<String>;
 
+    /// List every file path, ordered by a custom comparator (e.g. by depth
+    /// then name, as a tree builder needs).  The sort is stable, so paths
+    /// comparing equal keep their lexicographic order.
+    fn list_files_sorted_by<F>(&self, mut cmp: F) -> Vec<String>
+    where
+        Self: Sized,
+        F: FnMut(&str, &str) -> std::cmp::Ordering,
+    {
+        let mut files = self.list_files();
+        files.sort_by(|a, b| cmp(a, b));
+        files
+    }
+
     /// Rename / move a file or directory.
     fn rename(&mut self, from: &str, to: &str) -> Result<(), FsError>;
 }
@@ -123,6 +308,46 @@ pub trait Filesystem {
 pub struct MemoryFilesystem {
     files: BTreeMap<String, Vec<u8>>,
     dirs: BTreeSet<String>,
+    /// Per-file line offsets computed by [`MemoryFilesystem::line_index`],
+    /// dropped whenever the file changes.
+    line_cache: RefCell<BTreeMap<String, LineIndex>>,
+    /// See [`MemoryFilesystem::revision`].
+    revision: u64,
+    /// Undo history, present while journaling is enabled.
+    journal: Option<Journal>,
+}
+
+/// Maximum number of steps kept for [`MemoryFilesystem::undo`].
+const JOURNAL_CAPACITY: usize = 100;
+
+/// Undo and redo stacks.  Each step is a list of inverse operations, applied
+/// in order to revert one mutation.
+#[derive(Debug, Clone, Default)]
+struct Journal {
+    undo: VecDeque<Vec<JournalOp>>,
+    redo: Vec<Vec<JournalOp>>,
+}
+
+/// An operation replayed by [`MemoryFilesystem::undo`] or
+/// [`MemoryFilesystem::redo`].
+#[derive(Debug, Clone)]
+enum JournalOp {
+    /// Set `path` to `content`, or remove it when `None`.
+    Restore { path: String, content: Option<Vec<u8>> },
+    /// Move `from` to `to`.
+    Rename { from: String, to: String },
+    /// Create the directory `path`; its parent must exist.
+    CreateDir { path: String },
+    /// Remove the empty directory `path`.
+    RemoveDir { path: String },
+}
+
+/// Source of revision numbers, shared by every [`MemoryFilesystem`] so that
+/// two distinct filesystems never report the same revision.
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
 }
 
 impl Default for MemoryFilesystem {
@@ -140,7 +365,102 @@ impl MemoryFilesystem {
         MemoryFilesystem {
             files: BTreeMap::new(),
             dirs,
+            line_cache: RefCell::default(),
+            revision: next_revision(),
+            journal: None,
+        }
+    }
+
+    /// Start or stop recording an undo history.
+    ///
+    /// While enabled, every file write, removal and rename, and every
+    /// directory creation and removal, can be reverted with
+    /// [`undo`](Self::undo); the oldest steps are dropped beyond 100.
+    /// [`restore`](Self::restore), [`merge_snapshot`](Self::merge_snapshot)
+    /// and [`import_tar`](Self::import_tar) are not recorded and clear the
+    /// history instead, since the steps before them may no longer apply.
+    /// Disabling discards the history.
+    pub fn enable_journal(&mut self, enabled: bool) {
+        match (enabled, &self.journal) {
+            (true, None) => self.journal = Some(Journal::default()),
+            (false, _) => self.journal = None,
+            (true, Some(_)) => {}
+        }
+    }
+
+    /// Revert the most recent journaled change.  Returns `false` when there
+    /// is nothing to undo.  On error the filesystem is left as it was and
+    /// the step stays on the history, so it can be retried.
+    pub fn undo(&mut self) -> Result<bool, FsError> {
+        let Some(step) = self.journal.as_mut().and_then(|j| j.undo.pop_back()) else {
+            return Ok(false);
+        };
+        let result = self.replay(&step);
+        let Some(journal) = &mut self.journal else {
+            return result.map(|_| true);
+        };
+        match result {
+            Ok(inverse) => journal.redo.push(inverse),
+            Err(err) => {
+                journal.undo.push_back(step);
+                return Err(err);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Re-apply the most recently undone change.  Returns `false` when there
+    /// is nothing to redo; any new change clears the redo history.  Errors
+    /// are handled as for [`undo`](Self::undo).
+    pub fn redo(&mut self) -> Result<bool, FsError> {
+        let Some(step) = self.journal.as_mut().and_then(|j| j.redo.pop()) else {
+            return Ok(false);
+        };
+        let result = self.replay(&step);
+        let Some(journal) = &mut self.journal else {
+            return result.map(|_| true);
+        };
+        match result {
+            Ok(inverse) => journal.undo.push_back(inverse),
+            Err(err) => {
+                journal.redo.push(step);
+                return Err(err);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Build a filesystem from `(path, contents)` pairs, creating parent
+    /// directories automatically.
+    ///
+    /// Contents may be anything byte-like (`&str`, `String`, `Vec<u8>`, …),
+    /// which makes this the one-call way to seed a demo from a flat map such
+    /// as `{ "src/a.rs": "...", "README.md": "..." }`.  A path that is also
+    /// used as a directory by another entry yields [`FsError::WrongKind`].
+    pub fn from_str_map<I, K, V>(entries: I) -> Result<Self, FsError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<[u8]>,
+    {
+        let mut fs = MemoryFilesystem::new();
+        for (path, content) in entries {
+            let norm = normalise(path.as_ref());
+            if let Some(p) = parent(&norm) {
+                fs.create_dir_all(&p)?;
+            }
+            fs.write_file(&norm, content.as_ref())?;
         }
+        Ok(fs)
+    }
+
+    /// Return a number that changes whenever the filesystem is mutated.
+    ///
+    /// Revisions are unique across all filesystems (a clone shares its
+    /// original's revision until either is modified), so callers can cache
+    /// derived data keyed by this value.
+    pub fn revision(&self) -> u64 {
+        self.revision
     }
 
     /// Serialise the entire filesystem to a flat `Vec` of `(path, contents)`
@@ -149,35 +469,345 @@ impl MemoryFilesystem {
         self.files.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
     }
 
+    /// Like [`snapshot`](Self::snapshot), but tagged with `name` and the
+    /// timestamp `now` so several projects can be kept side by side in a
+    /// [`SnapshotRegistry`].
+    pub fn snapshot_named(&self, name: &str, now: u64) -> NamedSnapshot {
+        NamedSnapshot {
+            name: name.to_string(),
+            saved_at: now,
+            entries: self.snapshot(),
+        }
+    }
+
     /// Restore the filesystem from a snapshot created by [`snapshot`].
     pub fn restore(&mut self, entries: Vec<(String, Vec<u8>)>) {
+        self.revision = next_revision();
+        self.line_cache.get_mut().clear();
+        self.forget_history();
         self.files.clear();
         self.dirs.clear();
         self.dirs.insert(String::new()); // root
 
         for (path, content) in entries {
             let norm = normalise(&path);
-            // Ensure all parent directories exist.
-            let mut prefix = String::new();
-            for part in norm.split('/') {
-                if !prefix.is_empty() || !part.is_empty() {
-                    if !prefix.is_empty() {
-                        prefix.push('/');
-                    }
-                    prefix.push_str(part);
+            self.insert_parents(&norm);
+            self.files.insert(norm, content);
+        }
+    }
+
+    /// Merge a snapshot from another filesystem into this one.
+    ///
+    /// Files only present in `other` are added (with their parent
+    /// directories) and local-only files are kept.  When both sides have a
+    /// file with different contents, `strategy` picks the winner.  Returns
+    /// the conflicting paths, sorted.
+    ///
+    /// An incoming file that collides with a local directory (or sits below
+    /// a local file or another incoming file) yields [`FsError::WrongKind`]
+    /// and nothing is merged.
+    /// Like [`restore`](Self::restore), merging is not journaled and clears
+    /// the undo history.
+    pub fn merge_snapshot(
+        &mut self,
+        other: Vec<(String, Vec<u8>)>,
+        strategy: MergeStrategy,
+    ) -> Result<Vec<String>, FsError> {
+        let take_theirs = match strategy {
+            MergeStrategy::PreferMine => false,
+            MergeStrategy::PreferTheirs => true,
+            MergeStrategy::NewestWins { mine, theirs } => theirs > mine,
+        };
+        let mut incoming = BTreeMap::new();
+        for (path, content) in other {
+            let norm = normalise(&path);
+            if norm.is_empty() {
+                return Err(FsError::WrongKind(ROOT.to_string()));
+            }
+            if self.dirs.contains(&norm) {
+                return Err(FsError::WrongKind(norm));
+            }
+            let mut ancestor = parent(&norm);
+            while let Some(p) = ancestor.filter(|p| !p.is_empty()) {
+                if self.files.contains_key(&p) {
+                    return Err(FsError::WrongKind(p));
+                }
+                ancestor = parent(&p);
+            }
+            incoming.insert(norm, content);
+        }
+        for path in incoming.keys() {
+            let mut ancestor = parent(path);
+            while let Some(p) = ancestor.filter(|p| !p.is_empty()) {
+                if incoming.contains_key(&p) {
+                    return Err(FsError::WrongKind(p));
                 }
-                // Don't insert the file itself as a dir.
-                if prefix != norm {
-                    self.dirs.insert(prefix.clone());
+                ancestor = parent(&p);
+            }
+        }
+
+        self.forget_history();
+        let mut conflicts = Vec::new();
+        for (norm, content) in incoming {
+            match self.files.get(&norm) {
+                Some(mine) if *mine == content => continue,
+                Some(_) => {
+                    conflicts.push(norm.clone());
+                    if !take_theirs {
+                        continue;
+                    }
                 }
+                None => self.insert_parents(&norm),
             }
+            self.revision = next_revision();
+            self.line_cache.get_mut().remove(&norm);
             self.files.insert(norm, content);
         }
+        Ok(conflicts)
+    }
+
+    /// Import the regular files and directories of a `ustar` archive,
+    /// creating parent directories as needed.  Existing files at the same
+    /// paths are overwritten.
+    ///
+    /// The archive is decoded and checked in full before anything is
+    /// written, so an error leaves the filesystem untouched: e.g.
+    /// [`FsError::InvalidPath`] for a non-UTF-8 entry name, or
+    /// [`FsError::WrongKind`] for a path that would be both a file and a
+    /// directory, whether the clash is with existing paths or within the
+    /// archive.
+    pub fn import_tar(&mut self, data: &[u8]) -> Result<(), FsError> {
+        let mut files = BTreeMap::new();
+        let mut dirs = BTreeSet::new();
+        for entry in crate::tar::entries(data) {
+            let entry = entry?;
+            let norm = normalise(&entry.path);
+            if norm.is_empty() {
+                continue;
+            }
+            match entry.kind {
+                EntryKind::Dir => {
+                    dirs.insert(norm);
+                }
+                EntryKind::File => {
+                    files.insert(norm, entry.data);
+                }
+            }
+        }
+        let is_file = |p: &str| self.files.contains_key(p) || files.contains_key(p);
+        for path in files.keys() {
+            if self.dirs.contains(path) || dirs.contains(path) {
+                return Err(FsError::WrongKind(path.clone()));
+            }
+        }
+        for path in files.keys().chain(&dirs) {
+            if dirs.contains(path) && is_file(path) {
+                return Err(FsError::WrongKind(path.clone()));
+            }
+            let mut ancestor = parent(path);
+            while let Some(p) = ancestor.filter(|p| !p.is_empty()) {
+                if is_file(&p) {
+                    return Err(FsError::WrongKind(p));
+                }
+                ancestor = parent(&p);
+            }
+        }
+
+        self.revision = next_revision();
+        self.line_cache.get_mut().clear();
+        self.forget_history();
+        for dir in dirs {
+            self.insert_parents(&dir);
+            self.dirs.insert(dir);
+        }
+        for (path, data) in files {
+            self.insert_parents(&path);
+            self.files.insert(path, data.to_vec());
+        }
+        Ok(())
+    }
+
+    /// Export every file and directory (including empty ones) as a ZIP
+    /// archive, e.g. for a browser download.  Entries are stored
+    /// uncompressed, in path order.
+    ///
+    /// Fails with [`FsError::InvalidArchive`] past the plain ZIP limits of
+    /// 65535 entries or 4 GiB.
+    pub fn to_zip(&self) -> Result<Vec<u8>, FsError> {
+        let dirs = self.dirs.iter().filter(|d| !d.is_empty()).map(|d| (d.as_str(), None));
+        let files = self.files.iter().map(|(path, data)| (path.as_str(), Some(data.as_slice())));
+        let mut entries: Vec<_> = dirs.chain(files).collect();
+        // A directory sorts before its contents.
+        entries.sort_by_key(|&(path, _)| path);
+        crate::zip::write(entries)
+    }
+
+    /// Build a filesystem from a ZIP archive, such as one written by
+    /// [`to_zip`](Self::to_zip), creating parent directories as needed.
+    ///
+    /// Only uncompressed (STORED) entries can be read; anything else, or a
+    /// checksum mismatch, yields [`FsError::InvalidArchive`].
+    pub fn from_zip(data: &[u8]) -> Result<Self, FsError> {
+        let mut fs = MemoryFilesystem::new();
+        for entry in crate::zip::read(data)? {
+            let norm = normalise(&entry.path);
+            if norm.is_empty() {
+                continue;
+            }
+            match entry.data {
+                None => fs.create_dir_all(&norm)?,
+                Some(content) => {
+                    if let Some(p) = parent(&norm) {
+                        fs.create_dir_all(&p)?;
+                    }
+                    fs.write_file(&norm, content)?;
+                }
+            }
+        }
+        Ok(fs)
+    }
+
+    /// Return the byte range `(start, end)` of every line in a file, with the
+    /// line terminator (`\n` or `\r\n`) excluded, following the same rules
+    /// as [`str::lines`].
+    ///
+    /// The index is cached until the file is next modified and handed out
+    /// shared, so a pager can call this every frame without copying it and
+    /// fetch only the visible lines with [`Filesystem::read_range`].
+    pub fn line_index(&self, path: &str) -> Result<Rc<[(u64, u64)]>, FsError> {
+        let norm = normalise(path);
+        if let Some(index) = self.line_cache.borrow().get(&norm) {
+            return Ok(Rc::clone(index));
+        }
+        let data = self
+            .files
+            .get(&norm)
+            .ok_or_else(|| FsError::NotFound(norm.clone()))?;
+        let index: LineIndex = compute_line_index(data).into();
+        self.line_cache.borrow_mut().insert(norm, Rc::clone(&index));
+        Ok(index)
+    }
+
+    /// Iterate the lines of a file, as split by
+    /// [`line_index`](Self::line_index), without copying them.  A pager can
+    /// [`seek_line`](LineCursor::seek_line) to the top of its viewport and
+    /// `take` the visible rows.
+    pub fn lines(&self, path: &str) -> Result<LineCursor<'_>, FsError> {
+        let index = self.line_index(path)?;
+        let data = &self.files[&normalise(path)];
+        Ok(LineCursor { data, index, line: 0 })
+    }
+
+    /// Files from path `start` onwards, in path order, so a walk can skip a
+    /// whole subtree by starting again past it.
+    pub(crate) fn files_from<'a>(
+        &'a self,
+        start: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a [u8])> + 'a {
+        self.files
+            .range::<str, _>((std::ops::Bound::Included(start), std::ops::Bound::Unbounded))
+            .map(|(path, data)| (path.as_str(), data.as_slice()))
+    }
+
+    /// Push one undo step, if journaling.
+    fn record(&mut self, step: Vec<JournalOp>) {
+        if let Some(journal) = &mut self.journal {
+            journal.redo.clear();
+            journal.undo.push_back(step);
+            if journal.undo.len() > JOURNAL_CAPACITY {
+                journal.undo.pop_front();
+            }
+        }
+    }
+
+    /// Drop the undo and redo history, if journaling.
+    fn forget_history(&mut self) {
+        if let Some(journal) = &mut self.journal {
+            *journal = Journal::default();
+        }
+    }
+
+    /// Apply `step` and return the operations that revert it.  If an
+    /// operation fails, the ones already applied are reverted first.
+    fn replay(&mut self, step: &[JournalOp]) -> Result<Vec<JournalOp>, FsError> {
+        // Run the step against a scratch journal so the regular mutation
+        // paths compute the inverse.
+        let saved = self.journal.replace(Journal::default());
+        let result = step.iter().try_for_each(|op| self.apply(op));
+        let scratch = self.journal.take().unwrap_or_default();
+        let inverse: Vec<JournalOp> = scratch.undo.into_iter().rev().flatten().collect();
+        if result.is_err() {
+            for op in &inverse {
+                let _ = self.apply(op);
+            }
+        }
+        self.journal = saved;
+        result.map(|()| inverse)
+    }
+
+    /// Apply one journal operation through the regular mutation paths.
+    fn apply(&mut self, op: &JournalOp) -> Result<(), FsError> {
+        match op {
+            JournalOp::Restore { path, content: Some(content) } => {
+                if let Some(p) = parent(path) {
+                    self.create_dir_all(&p)?;
+                }
+                self.write_file(path, content)
+            }
+            JournalOp::Restore { path, content: None } => self.remove_file(path),
+            JournalOp::Rename { from, to } => self.rename(from, to),
+            JournalOp::CreateDir { path } => self.create_dir(path),
+            JournalOp::RemoveDir { path } => self.remove_dir(path),
+        }
+    }
+
+    /// Record every ancestor directory of the normalised path `norm`.
+    fn insert_parents(&mut self, norm: &str) {
+        let mut current = parent(norm);
+        while let Some(p) = current {
+            if p.is_empty() {
+                break;
+            }
+            current = parent(&p);
+            self.dirs.insert(p);
+        }
+    }
+}
+
+/// Compute the line ranges described in [`MemoryFilesystem::line_index`].
+fn compute_line_index(data: &[u8]) -> Vec<(u64, u64)> {
+    let mut index = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let newline = data[start..].iter().position(|&b| b == b'\n');
+        let (end, next) = match newline {
+            Some(pos) => (start + pos, start + pos + 1),
+            None => (data.len(), data.len()),
+        };
+        let end = if newline.is_some() && end > start && data[end - 1] == b'\r' {
+            end - 1
+        } else {
+            end
+        };
+        index.push((start as u64, end as u64));
+        start = next;
     }
+    index
+}
+
+/// Clamp `start..end` to the bounds of `data`.
+fn clamp_range(data: &[u8], start: u64, end: u64) -> &[u8] {
+    let len = data.len() as u64;
+    let end = end.min(len);
+    let start = start.min(end);
+    &data[start as usize..end as usize]
 }
 
+/// How the root directory is named in errors (its normalised form is `""`).
+const ROOT: &str = "/";
+
 /// Normalise a path: strip leading `/`, collapse duplicate `/`.
-fn normalise(path: &str) -> String {
+pub(crate) fn normalise(path: &str) -> String {
     path.trim_start_matches('/')
         .split('/')
         .filter(|s| !s.is_empty())
@@ -185,6 +815,36 @@ fn normalise(path: &str) -> String {
         .join("/")
 }
 
+/// Recursive worker for [`Filesystem::read_dir_depth`].
+fn walk_depth<F: Filesystem + ?Sized>(
+    fs: &F,
+    dir: &str,
+    depth: usize,
+    max_depth: usize,
+    out: &mut Vec<WalkEntry>,
+) -> Result<(), FsError> {
+    let entries = fs.read_dir(dir)?;
+    if depth > max_depth {
+        return Ok(());
+    }
+    for entry in entries {
+        let path = if dir.is_empty() {
+            entry.name
+        } else {
+            format!("{dir}/{}", entry.name)
+        };
+        out.push(WalkEntry {
+            path: path.clone(),
+            depth,
+            is_dir: entry.is_dir,
+        });
+        if entry.is_dir && depth < max_depth {
+            walk_depth(fs, &path, depth + 1, max_depth, out)?;
+        }
+    }
+    Ok(())
+}
+
 /// Return the parent of a normalised path (empty string = root).
 fn parent(path: &str) -> Option<String> {
     if path.is_empty() {
@@ -202,11 +862,20 @@ impl Filesystem for MemoryFilesystem {
         self.files
             .get(&norm)
             .cloned()
-            .ok_or_else(|| FsError::NotFound(norm))
+            .ok_or(FsError::NotFound(norm))
+    }
+
+    fn read_range(&self, path: &str, start: u64, end: u64) -> Result<Vec<u8>, FsError> {
+        let norm = normalise(path);
+        let data = self.files.get(&norm).ok_or(FsError::NotFound(norm))?;
+        Ok(clamp_range(data, start, end).to_vec())
     }
 
     fn write_file(&mut self, path: &str, content: &[u8]) -> Result<(), FsError> {
         let norm = normalise(path);
+        if norm.is_empty() {
+            return Err(FsError::WrongKind(ROOT.to_string()));
+        }
         if self.dirs.contains(&norm) {
             return Err(FsError::WrongKind(norm));
         }
@@ -216,23 +885,41 @@ impl Filesystem for MemoryFilesystem {
                 return Err(FsError::ParentNotFound(norm));
             }
         }
-        self.files.insert(norm, content.to_vec());
+        self.revision = next_revision();
+        self.line_cache.get_mut().remove(&norm);
+        let previous = self.files.insert(norm.clone(), content.to_vec());
+        self.record(vec![JournalOp::Restore {
+            path: norm,
+            content: previous,
+        }]);
         Ok(())
     }
 
     fn remove_file(&mut self, path: &str) -> Result<(), FsError> {
         let norm = normalise(path);
+        if norm.is_empty() {
+            return Err(FsError::WrongKind(ROOT.to_string()));
+        }
         if self.dirs.contains(&norm) {
             return Err(FsError::WrongKind(norm));
         }
-        self.files
-            .remove(&norm)
-            .map(|_| ())
-            .ok_or_else(|| FsError::NotFound(norm))
+        let Some(previous) = self.files.remove(&norm) else {
+            return Err(FsError::NotFound(norm));
+        };
+        self.revision = next_revision();
+        self.line_cache.get_mut().remove(&norm);
+        self.record(vec![JournalOp::Restore {
+            path: norm,
+            content: Some(previous),
+        }]);
+        Ok(())
     }
 
     fn remove_dir(&mut self, path: &str) -> Result<(), FsError> {
         let norm = normalise(path);
+        if norm.is_empty() {
+            return Err(FsError::WrongKind(ROOT.to_string()));
+        }
         if !self.dirs.contains(&norm) {
             return Err(FsError::NotFound(norm));
         }
@@ -253,7 +940,9 @@ impl Filesystem for MemoryFilesystem {
         if has_children {
             return Err(FsError::NotEmpty(norm));
         }
+        self.revision = next_revision();
         self.dirs.remove(&norm);
+        self.record(vec![JournalOp::CreateDir { path: norm }]);
         Ok(())
     }
 
@@ -274,6 +963,9 @@ impl Filesystem for MemoryFilesystem {
 
     fn create_dir(&mut self, path: &str) -> Result<(), FsError> {
         let norm = normalise(path);
+        if norm.is_empty() {
+            return Err(FsError::AlreadyExists(ROOT.to_string()));
+        }
         if self.dirs.contains(&norm) || self.files.contains_key(&norm) {
             return Err(FsError::AlreadyExists(norm));
         }
@@ -282,7 +974,9 @@ impl Filesystem for MemoryFilesystem {
                 return Err(FsError::ParentNotFound(norm));
             }
         }
-        self.dirs.insert(norm);
+        self.revision = next_revision();
+        self.dirs.insert(norm.clone());
+        self.record(vec![JournalOp::RemoveDir { path: norm }]);
         Ok(())
     }
 
@@ -293,17 +987,29 @@ impl Filesystem for MemoryFilesystem {
         }
         let parts: Vec<&str> = norm.split('/').collect();
         let mut current = String::new();
+        let mut created = Vec::new();
+        let mut result = Ok(());
         for part in &parts {
             if !current.is_empty() {
                 current.push('/');
             }
             current.push_str(part);
             if self.files.contains_key(&current) {
-                return Err(FsError::WrongKind(current));
+                result = Err(FsError::WrongKind(current));
+                break;
+            }
+            if self.dirs.insert(current.clone()) {
+                self.revision = next_revision();
+                created.push(JournalOp::RemoveDir { path: current.clone() });
             }
-            self.dirs.insert(current.clone());
         }
-        Ok(())
+        // Record the directories made before any failure too, so undo
+        // always matches what changed.
+        if !created.is_empty() {
+            created.reverse();
+            self.record(created);
+        }
+        result
     }
 
     fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, FsError> {
@@ -357,6 +1063,24 @@ impl Filesystem for MemoryFilesystem {
         Ok(entries.into_iter().collect())
     }
 
+    fn read_dir_all(&self, path: &str) -> Result<Vec<String>, FsError> {
+        let norm = normalise(path);
+        if !self.dirs.contains(&norm) {
+            return Err(FsError::NotFound(norm));
+        }
+        if norm.is_empty() {
+            return Ok(self.list_files());
+        }
+        let prefix = format!("{norm}/");
+        Ok(self
+            .files
+            .range(prefix.clone()..)
+            .map(|(k, _)| k)
+            .take_while(|k| k.starts_with(&prefix))
+            .cloned()
+            .collect())
+    }
+
     fn metadata(&self, path: &str) -> Result<Metadata, FsError> {
         let norm = normalise(path);
         if self.dirs.contains(&norm) {
@@ -378,6 +1102,17 @@ impl Filesystem for MemoryFilesystem {
     fn rename(&mut self, from: &str, to: &str) -> Result<(), FsError> {
         let from_norm = normalise(from);
         let to_norm = normalise(to);
+        if from_norm.is_empty() {
+            return Err(FsError::WrongKind(ROOT.to_string()));
+        }
+        if to_norm.is_empty() {
+            return Err(FsError::AlreadyExists(ROOT.to_string()));
+        }
+        if !self.exists(&from_norm) {
+            return Err(FsError::NotFound(from_norm));
+        }
+        self.revision = next_revision();
+        self.line_cache.get_mut().clear();
 
         if self.files.contains_key(&from_norm) {
             // Rename a file.
@@ -387,7 +1122,18 @@ impl Filesystem for MemoryFilesystem {
                 }
             }
             let data = self.files.remove(&from_norm).unwrap();
-            self.files.insert(to_norm, data);
+            let displaced = self.files.insert(to_norm.clone(), data);
+            let mut step = vec![JournalOp::Rename {
+                from: to_norm.clone(),
+                to: from_norm,
+            }];
+            if displaced.is_some() {
+                step.push(JournalOp::Restore {
+                    path: to_norm,
+                    content: displaced,
+                });
+            }
+            self.record(step);
             Ok(())
         } else if self.dirs.contains(&from_norm) {
             // Rename a directory (and all children).
@@ -426,15 +1172,36 @@ impl Filesystem for MemoryFilesystem {
                 })
                 .collect();
 
+            // The inverse moves back only what was moved, so a directory
+            // merged into an existing one is split out again: recreate the
+            // source directories, move each file back, restore displaced
+            // files and drop the directories the merge created.
+            let mut step: Vec<JournalOp> = dir_moves
+                .iter()
+                .map(|(old, _)| JournalOp::CreateDir { path: old.clone() })
+                .collect();
+            let mut restores = Vec::new();
             for (old, new) in file_moves {
                 let data = self.files.remove(&old).unwrap();
-                self.files.insert(new, data);
+                if let Some(displaced) = self.files.insert(new.clone(), data) {
+                    restores.push(JournalOp::Restore {
+                        path: new.clone(),
+                        content: Some(displaced),
+                    });
+                }
+                step.push(JournalOp::Rename { from: new, to: old });
             }
+            step.extend(restores);
+            let mut created = Vec::new();
             for (old, new) in dir_moves {
                 self.dirs.remove(&old);
-                self.dirs.insert(new);
+                if self.dirs.insert(new.clone()) {
+                    created.push(JournalOp::RemoveDir { path: new });
+                }
             }
+            step.extend(created.into_iter().rev());
 
+            self.record(step);
             Ok(())
         } else {
             Err(FsError::NotFound(from_norm))
@@ -442,6 +1209,408 @@ impl Filesystem for MemoryFilesystem {
     }
 }
 
+// ── Overlay implementation ───────────────────────────────────────────────────
+
+/// A [`Filesystem`] that layers a writable [`MemoryFilesystem`] over a
+/// read-only base.
+///
+/// Reads fall through to the base unless the path has been written in the
+/// overlay; every mutation lands in the overlay, so the base is never
+/// modified.  Removing something that exists in the base records a
+/// *whiteout* that hides it (and, for a directory, everything below it).
+/// This makes it possible to preview a set of edits and throw them away by
+/// dropping the overlay.
+#[derive(Debug, Clone)]
+pub struct OverlayFilesystem<B> {
+    base: B,
+    upper: MemoryFilesystem,
+    /// Normalised base paths hidden by a removal.
+    whiteouts: BTreeSet<String>,
+}
+
+impl<B: Filesystem> OverlayFilesystem<B> {
+    /// Create an overlay with no changes on top of `base`.
+    pub fn new(base: B) -> Self {
+        OverlayFilesystem {
+            base,
+            upper: MemoryFilesystem::new(),
+            whiteouts: BTreeSet::new(),
+        }
+    }
+
+    /// Return the read-only base filesystem.
+    pub fn base(&self) -> &B {
+        &self.base
+    }
+
+    /// Return the writable layer holding every file written so far.
+    pub fn upper(&self) -> &MemoryFilesystem {
+        &self.upper
+    }
+
+    /// Iterate over the base paths hidden by removals, in sorted order.
+    pub fn whiteouts(&self) -> impl Iterator<Item = &str> {
+        self.whiteouts.iter().map(String::as_str)
+    }
+
+    /// `true` when the normalised path, or one of its ancestors, is hidden.
+    fn whited_out(&self, norm: &str) -> bool {
+        let mut current = Some(norm.to_string());
+        while let Some(p) = current {
+            if self.whiteouts.contains(&p) {
+                return true;
+            }
+            current = parent(&p);
+        }
+        false
+    }
+
+    /// `true` when reads of the normalised path should reach the base.
+    fn falls_through(&self, norm: &str) -> bool {
+        !self.upper.exists(norm) && !self.whited_out(norm)
+    }
+}
+
+impl<B: Filesystem> Filesystem for OverlayFilesystem<B> {
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, FsError> {
+        let norm = normalise(path);
+        if self.falls_through(&norm) {
+            self.base.read_file(&norm)
+        } else {
+            self.upper.read_file(&norm)
+        }
+    }
+
+    fn read_range(&self, path: &str, start: u64, end: u64) -> Result<Vec<u8>, FsError> {
+        let norm = normalise(path);
+        if self.falls_through(&norm) {
+            self.base.read_range(&norm, start, end)
+        } else {
+            self.upper.read_range(&norm, start, end)
+        }
+    }
+
+    fn write_file(&mut self, path: &str, content: &[u8]) -> Result<(), FsError> {
+        let norm = normalise(path);
+        if norm.is_empty() {
+            return Err(FsError::WrongKind(ROOT.to_string()));
+        }
+        if self.is_dir(&norm) {
+            return Err(FsError::WrongKind(norm));
+        }
+        let p = parent(&norm).unwrap_or_default();
+        if !self.is_dir(&p) {
+            return Err(FsError::ParentNotFound(norm));
+        }
+        self.upper.create_dir_all(&p)?;
+        self.upper.write_file(&norm, content)
+    }
+
+    fn remove_file(&mut self, path: &str) -> Result<(), FsError> {
+        let norm = normalise(path);
+        if norm.is_empty() {
+            return Err(FsError::WrongKind(ROOT.to_string()));
+        }
+        if self.is_dir(&norm) {
+            return Err(FsError::WrongKind(norm));
+        }
+        if !self.is_file(&norm) {
+            return Err(FsError::NotFound(norm));
+        }
+        if self.upper.is_file(&norm) {
+            self.upper.remove_file(&norm)?;
+        }
+        if !self.whited_out(&norm) && self.base.is_file(&norm) {
+            self.whiteouts.insert(norm);
+        }
+        Ok(())
+    }
+
+    fn remove_dir(&mut self, path: &str) -> Result<(), FsError> {
+        let norm = normalise(path);
+        if norm.is_empty() {
+            return Err(FsError::WrongKind(ROOT.to_string()));
+        }
+        if !self.is_dir(&norm) {
+            return Err(FsError::NotFound(norm));
+        }
+        if !self.read_dir(&norm)?.is_empty() {
+            return Err(FsError::NotEmpty(norm));
+        }
+        if self.upper.is_dir(&norm) {
+            self.upper.remove_dir(&norm)?;
+        }
+        if !self.whited_out(&norm) && self.base.is_dir(&norm) {
+            self.whiteouts.insert(norm);
+        }
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        let norm = normalise(path);
+        self.upper.exists(&norm) || (!self.whited_out(&norm) && self.base.exists(&norm))
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        let norm = normalise(path);
+        self.upper.is_dir(&norm) || (self.falls_through(&norm) && self.base.is_dir(&norm))
+    }
+
+    fn is_file(&self, path: &str) -> bool {
+        let norm = normalise(path);
+        self.upper.is_file(&norm) || (self.falls_through(&norm) && self.base.is_file(&norm))
+    }
+
+    fn create_dir(&mut self, path: &str) -> Result<(), FsError> {
+        let norm = normalise(path);
+        if norm.is_empty() {
+            return Err(FsError::AlreadyExists(ROOT.to_string()));
+        }
+        if self.exists(&norm) {
+            return Err(FsError::AlreadyExists(norm));
+        }
+        if !self.is_dir(&parent(&norm).unwrap_or_default()) {
+            return Err(FsError::ParentNotFound(norm));
+        }
+        // Any whiteout on the path stays, so a re-created directory starts
+        // out empty rather than exposing the base's old children.
+        self.upper.create_dir_all(&norm)
+    }
+
+    fn create_dir_all(&mut self, path: &str) -> Result<(), FsError> {
+        let norm = normalise(path);
+        let mut current = String::new();
+        for part in norm.split('/').filter(|p| !p.is_empty()) {
+            if !current.is_empty() {
+                current.push('/');
+            }
+            current.push_str(part);
+            if self.is_file(&current) {
+                return Err(FsError::WrongKind(current));
+            }
+        }
+        self.upper.create_dir_all(&norm)
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, FsError> {
+        let norm = normalise(path);
+        if !self.is_dir(&norm) {
+            return Err(FsError::NotFound(norm));
+        }
+        let mut entries = BTreeSet::new();
+        if !self.whited_out(&norm) && self.base.is_dir(&norm) {
+            for entry in self.base.read_dir(&norm)? {
+                let child = if norm.is_empty() {
+                    entry.name.clone()
+                } else {
+                    format!("{norm}/{}", entry.name)
+                };
+                if !self.whiteouts.contains(&child) {
+                    entries.insert(entry);
+                }
+            }
+        }
+        if self.upper.is_dir(&norm) {
+            for entry in self.upper.read_dir(&norm)? {
+                entries.replace(entry);
+            }
+        }
+        Ok(entries.into_iter().collect())
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata, FsError> {
+        let norm = normalise(path);
+        if self.falls_through(&norm) {
+            self.base.metadata(&norm)
+        } else {
+            self.upper.metadata(&norm)
+        }
+    }
+
+    fn list_files(&self) -> Vec<String> {
+        let mut files: BTreeSet<String> = self.upper.list_files().into_iter().collect();
+        files.extend(
+            self.base
+                .list_files()
+                .into_iter()
+                .filter(|p| self.falls_through(p)),
+        );
+        files.into_iter().collect()
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), FsError> {
+        let from_norm = normalise(from);
+        let to_norm = normalise(to);
+        if from_norm.is_empty() {
+            return Err(FsError::WrongKind(ROOT.to_string()));
+        }
+        if to_norm.is_empty() {
+            return Err(FsError::AlreadyExists(ROOT.to_string()));
+        }
+        if self.is_file(&from_norm) {
+            let data = self.read_file(&from_norm)?;
+            self.write_file(&to_norm, &data)?;
+            return self.remove_file(&from_norm);
+        }
+        if !self.is_dir(&from_norm) {
+            return Err(FsError::NotFound(from_norm));
+        }
+
+        // Copy the subtree up into the overlay, then remove the original
+        // deepest-first so that every directory is empty when it goes.
+        let entries = self.read_dir_depth(&from_norm, usize::MAX)?;
+        if !self.is_dir(&parent(&to_norm).unwrap_or_default()) {
+            return Err(FsError::ParentNotFound(to_norm));
+        }
+        self.create_dir_all(&to_norm)?;
+        let old_prefix = format!("{from_norm}/");
+        for entry in &entries {
+            let target = format!("{to_norm}/{}", &entry.path[old_prefix.len()..]);
+            if entry.is_dir {
+                self.create_dir_all(&target)?;
+            } else {
+                let data = self.read_file(&entry.path)?;
+                self.write_file(&target, &data)?;
+            }
+        }
+        for entry in entries.iter().rev() {
+            if entry.is_dir {
+                self.remove_dir(&entry.path)?;
+            } else {
+                self.remove_file(&entry.path)?;
+            }
+        }
+        self.remove_dir(&from_norm)
+    }
+}
+
+// ── Named snapshots ──────────────────────────────────────────────────────────
+
+/// A [`MemoryFilesystem::snapshot`] tagged with a name and the time it was
+/// taken, created by [`MemoryFilesystem::snapshot_named`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedSnapshot {
+    /// Name shown to the user, e.g. in a project picker.
+    pub name: String,
+    /// Caller-supplied timestamp (e.g. milliseconds since the Unix epoch).
+    pub saved_at: u64,
+    /// `(path, contents)` pairs, as accepted by [`MemoryFilesystem::restore`].
+    pub entries: Vec<(String, Vec<u8>)>,
+}
+
+/// A set of [`NamedSnapshot`]s, one per name, that can be stored under a
+/// single `localStorage` key.
+///
+/// [`to_storage_string`](Self::to_storage_string) produces plain ASCII text
+/// (names, paths and contents are base64-encoded), so the result can be
+/// passed to `localStorage.setItem` as-is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotRegistry {
+    snapshots: Vec<NamedSnapshot>,
+}
+
+/// First line of the registry storage format.
+const REGISTRY_HEADER: &str = "tui2web-snapshots 1";
+
+impl SnapshotRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `snapshot`, replacing any existing snapshot with the same name.
+    pub fn insert(&mut self, snapshot: NamedSnapshot) {
+        match self.snapshots.iter_mut().find(|s| s.name == snapshot.name) {
+            Some(existing) => *existing = snapshot,
+            None => self.snapshots.push(snapshot),
+        }
+    }
+
+    /// Return the snapshot called `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&NamedSnapshot> {
+        self.snapshots.iter().find(|s| s.name == name)
+    }
+
+    /// Remove and return the snapshot called `name`.
+    pub fn remove(&mut self, name: &str) -> Option<NamedSnapshot> {
+        let pos = self.snapshots.iter().position(|s| s.name == name)?;
+        Some(self.snapshots.remove(pos))
+    }
+
+    /// Iterate over the snapshots in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = &NamedSnapshot> {
+        self.snapshots.iter()
+    }
+
+    /// Serialise every snapshot into a single string for `localStorage`.
+    pub fn to_storage_string(&self) -> String {
+        let mut out = String::from(REGISTRY_HEADER);
+        out.push('\n');
+        for snap in &self.snapshots {
+            out.push_str(&format!(
+                "snapshot {} {} {}\n",
+                snap.saved_at,
+                crate::base64::encode(snap.name.as_bytes()),
+                snap.entries.len()
+            ));
+            for (path, content) in &snap.entries {
+                out.push_str(&crate::base64::encode(path.as_bytes()));
+                out.push(' ');
+                out.push_str(&crate::base64::encode(content));
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Parse a string produced by [`to_storage_string`](Self::to_storage_string).
+    ///
+    /// Malformed input yields [`FsError::InvalidArchive`].
+    pub fn from_storage_string(text: &str) -> Result<Self, FsError> {
+        let invalid = |what: &str| FsError::InvalidArchive(format!("snapshot registry: {what}"));
+        let decode_str = |b64: &str| {
+            crate::base64::decode(b64)
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(|| invalid("bad name or path"))
+        };
+
+        let mut lines = text.lines();
+        if lines.next() != Some(REGISTRY_HEADER) {
+            return Err(invalid("missing header"));
+        }
+        let mut registry = SnapshotRegistry::new();
+        while let Some(line) = lines.next() {
+            let mut fields = line.split(' ');
+            let (Some("snapshot"), Some(saved_at), Some(name), Some(count), None) = (
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+            ) else {
+                return Err(invalid("bad snapshot line"));
+            };
+            let saved_at = saved_at.parse().map_err(|_| invalid("bad timestamp"))?;
+            let count: usize = count.parse().map_err(|_| invalid("bad entry count"))?;
+            let mut entries = Vec::new();
+            for _ in 0..count {
+                let (path, content) = lines
+                    .next()
+                    .and_then(|l| l.split_once(' '))
+                    .ok_or_else(|| invalid("truncated snapshot"))?;
+                let content = crate::base64::decode(content).ok_or_else(|| invalid("bad content"))?;
+                entries.push((decode_str(path)?, content));
+            }
+            registry.insert(NamedSnapshot {
+                name: decode_str(name)?,
+                saved_at,
+                entries,
+            });
+        }
+        Ok(registry)
+    }
+}
+
 // We need Ord/PartialOrd for BTreeSet.
 impl PartialOrd for DirEntry {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
@@ -461,6 +1630,22 @@ impl Ord for DirEntry {
 mod tests {
     use super::*;
 
+    #[test]
+    fn errors_convert_to_io_errors() {
+        let kind = |e: FsError| io::Error::from(e).kind();
+        let p = || "p".to_string();
+        assert_eq!(kind(FsError::NotFound(p())), io::ErrorKind::NotFound);
+        assert_eq!(kind(FsError::AlreadyExists(p())), io::ErrorKind::AlreadyExists);
+        assert_eq!(kind(FsError::ParentNotFound(p())), io::ErrorKind::NotFound);
+        assert_eq!(kind(FsError::NotEmpty(p())), io::ErrorKind::DirectoryNotEmpty);
+        assert_eq!(kind(FsError::WrongKind(p())), io::ErrorKind::InvalidInput);
+        assert_eq!(kind(FsError::InvalidPath(p())), io::ErrorKind::InvalidInput);
+        assert_eq!(kind(FsError::InvalidArchive(p())), io::ErrorKind::InvalidData);
+
+        let e = io::Error::from(FsError::NotFound("a.txt".into()));
+        assert_eq!(e.to_string(), "not found: a.txt");
+    }
+
     #[test]
     fn create_and_read_file() {
         let mut fs = MemoryFilesystem::new();
@@ -469,6 +1654,117 @@ mod tests {
         assert_eq!(fs.read_to_string("hello.txt").unwrap(), "world");
     }
 
+    #[test]
+    fn journal_undoes_write_and_remove() {
+        let mut fs = MemoryFilesystem::from_str_map([("keep.txt", "kept")]).unwrap();
+        let original = fs.snapshot();
+        fs.enable_journal(true);
+        fs.write_file("a.txt", b"draft").unwrap();
+        fs.remove_file("a.txt").unwrap();
+
+        assert!(fs.undo().unwrap());
+        assert_eq!(fs.read_file("a.txt").unwrap(), b"draft");
+        assert!(fs.undo().unwrap());
+        assert_eq!(fs.snapshot(), original);
+        assert!(!fs.undo().unwrap());
+
+        assert!(fs.redo().unwrap());
+        assert!(fs.is_file("a.txt"));
+        fs.write_file("b.txt", b"new").unwrap();
+        assert!(!fs.redo().unwrap());
+    }
+
+    #[test]
+    fn journal_undoes_overwriting_rename() {
+        let mut fs = MemoryFilesystem::from_str_map([("a.txt", "a"), ("b.txt", "b")]).unwrap();
+        fs.enable_journal(true);
+        fs.rename("a.txt", "b.txt").unwrap();
+        assert_eq!(fs.list_files(), ["b.txt"]);
+
+        fs.undo().unwrap();
+        assert_eq!(fs.read_file("a.txt").unwrap(), b"a");
+        assert_eq!(fs.read_file("b.txt").unwrap(), b"b");
+        fs.redo().unwrap();
+        assert_eq!(fs.snapshot(), [("b.txt".to_string(), b"a".to_vec())]);
+    }
+
+    #[test]
+    fn journal_splits_a_merged_directory_rename() {
+        let mut fs = MemoryFilesystem::from_str_map([
+            ("src/a.txt", "a"),
+            ("src/sub/b.txt", "b"),
+            ("dst/a.txt", "old"),
+            ("dst/keep.txt", "k"),
+        ])
+        .unwrap();
+        let original = fs.snapshot();
+        fs.enable_journal(true);
+        fs.rename("src", "dst").unwrap();
+        let merged = fs.snapshot();
+        assert_eq!(fs.list_files(), ["dst/a.txt", "dst/keep.txt", "dst/sub/b.txt"]);
+
+        fs.undo().unwrap();
+        assert_eq!(fs.snapshot(), original);
+        assert!(!fs.exists("dst/sub"));
+        fs.redo().unwrap();
+        assert_eq!(fs.snapshot(), merged);
+        assert!(!fs.exists("src"));
+    }
+
+    #[test]
+    fn journal_undoes_directory_changes() {
+        let mut fs = MemoryFilesystem::from_str_map([("old/x.txt", "x")]).unwrap();
+        fs.enable_journal(true);
+        fs.create_dir_all("a/b/c").unwrap();
+        fs.remove_file("old/x.txt").unwrap();
+        fs.remove_dir("old").unwrap();
+
+        fs.undo().unwrap();
+        assert!(fs.is_dir("old"));
+        fs.undo().unwrap();
+        fs.undo().unwrap();
+        assert!(!fs.exists("a"));
+        assert_eq!(fs.list_files(), ["old/x.txt"]);
+        fs.redo().unwrap();
+        assert!(fs.is_dir("a/b/c"));
+    }
+
+    #[test]
+    fn bulk_changes_clear_the_journal() {
+        let mut fs = MemoryFilesystem::new();
+        fs.enable_journal(true);
+        fs.write_file("a.txt", b"mine").unwrap();
+        fs.import_tar(&tar_entry(b"a.txt", b'0', b"theirs")).unwrap();
+        assert!(!fs.undo().unwrap());
+        assert_eq!(fs.read_file("a.txt").unwrap(), b"theirs");
+
+        fs.write_file("b.txt", b"b").unwrap();
+        fs.restore(vec![("c.txt".to_string(), b"c".to_vec())]);
+        assert!(!fs.undo().unwrap());
+        fs.write_file("d.txt", b"d").unwrap();
+        fs.merge_snapshot(Vec::new(), MergeStrategy::PreferMine).unwrap();
+        assert!(!fs.undo().unwrap());
+        assert_eq!(fs.list_files(), ["c.txt", "d.txt"]);
+    }
+
+    #[test]
+    fn failed_undo_can_be_retried() {
+        let mut fs = MemoryFilesystem::from_str_map([("src/a.txt", "a"), ("src/b.txt", "b")])
+            .unwrap();
+        fs.enable_journal(true);
+        fs.rename("src", "dst").unwrap();
+        // Change the tree behind the journal's back so the second move fails.
+        let b = fs.files.remove("dst/b.txt").unwrap();
+
+        assert_eq!(fs.undo(), Err(FsError::NotFound("dst/b.txt".into())));
+        assert_eq!(fs.list_files(), ["dst/a.txt"]);
+        assert!(!fs.exists("src"));
+
+        fs.files.insert("dst/b.txt".into(), b);
+        assert!(fs.undo().unwrap());
+        assert_eq!(fs.list_files(), ["src/a.txt", "src/b.txt"]);
+    }
+
     #[test]
     fn write_requires_parent_directory() {
         let mut fs = MemoryFilesystem::new();
@@ -513,6 +1809,64 @@ mod tests {
         assert!(entries.iter().find(|e| e.name == "sub").unwrap().is_dir);
     }
 
+    #[test]
+    fn read_dir_depth_stops_at_max_depth() {
+        let mut fs = MemoryFilesystem::new();
+        fs.create_dir_all("a/b/c").unwrap();
+        fs.write_file("a/top.txt", b"").unwrap();
+        fs.write_file("a/b/mid.txt", b"").unwrap();
+        fs.write_file("a/b/c/deep.txt", b"").unwrap();
+
+        let entries = fs.read_dir_depth("a", 2).unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| (e.path.as_str(), e.depth)).collect();
+        assert_eq!(
+            paths,
+            vec![("a/b", 1), ("a/b/c", 2), ("a/b/mid.txt", 2), ("a/top.txt", 1)]
+        );
+
+        let shallow = fs.read_dir_depth("a", 1).unwrap();
+        let names: Vec<_> = fs.read_dir("a").unwrap().into_iter().map(|e| e.name).collect();
+        let shallow_names: Vec<_> = shallow
+            .iter()
+            .map(|e| e.path.trim_start_matches("a/"))
+            .collect();
+        assert_eq!(shallow_names, names);
+    }
+
+    #[test]
+    fn read_dir_all_is_scoped_to_the_directory() {
+        let mut fs = MemoryFilesystem::new();
+        fs.create_dir_all("src/ui").unwrap();
+        fs.create_dir("src-old").unwrap();
+        fs.write_file("src/lib.rs", b"").unwrap();
+        fs.write_file("src/ui/app.rs", b"").unwrap();
+        fs.write_file("src-old/lib.rs", b"").unwrap();
+        fs.write_file("README.md", b"").unwrap();
+
+        let expected = vec!["src/lib.rs".to_string(), "src/ui/app.rs".to_string()];
+        assert_eq!(fs.read_dir_all("src").unwrap(), expected);
+        assert_eq!(fs.read_dir_all("/src/").unwrap(), expected);
+        assert_eq!(fs.read_dir_all("").unwrap(), fs.list_files());
+        assert!(fs.read_dir_all("missing").is_err());
+    }
+
+    #[test]
+    fn touch_creates_once_and_keeps_contents() {
+        let mut fs = MemoryFilesystem::new();
+        fs.touch("new.txt").unwrap();
+        assert_eq!(fs.read_file("new.txt").unwrap(), b"");
+
+        fs.write_file("new.txt", b"kept").unwrap();
+        let revision = fs.revision();
+        fs.touch("/new.txt").unwrap();
+        assert_eq!(fs.read_file("new.txt").unwrap(), b"kept");
+        assert_eq!(fs.revision(), revision);
+
+        fs.create_dir("dir").unwrap();
+        assert_eq!(fs.touch("dir"), Err(FsError::WrongKind("dir".to_string())));
+        assert!(matches!(fs.touch("missing/a.txt"), Err(FsError::ParentNotFound(_))));
+    }
+
     #[test]
     fn metadata_works() {
         let mut fs = MemoryFilesystem::new();
@@ -548,6 +1902,79 @@ mod tests {
         assert!(files.contains(&"src/main.rs".to_string()));
     }
 
+    #[test]
+    fn line_index_ranges_and_invalidation() {
+        let mut fs = MemoryFilesystem::new();
+        fs.write_file("log.txt", b"first\nsecond line\r\nthird").unwrap();
+
+        let index = fs.line_index("log.txt").unwrap();
+        assert_eq!(*index, [(0, 5), (6, 17), (19, 24)]);
+        let (start, end) = index[1];
+        assert_eq!(fs.read_range("log.txt", start, end).unwrap(), b"second line");
+        assert!(Rc::ptr_eq(&index, &fs.line_index("log.txt").unwrap()));
+
+        // Cached until the file changes.
+        fs.write_file("log.txt", b"a\nb\n").unwrap();
+        assert_eq!(*fs.line_index("log.txt").unwrap(), [(0, 1), (2, 3)]);
+        assert_eq!(fs.read_range("log.txt", 2, 100).unwrap(), b"b\n");
+    }
+
+    #[test]
+    fn line_cursor_seeks_and_reads_forward() {
+        let mut fs = MemoryFilesystem::new();
+        fs.write_file("log.txt", b"one\ntwo\r\nthree\nfour\nfive").unwrap();
+
+        let mut lines = fs.lines("/log.txt").unwrap();
+        assert_eq!(lines.line_count(), 5);
+        lines.seek_line(2);
+        assert_eq!(lines.len(), 3);
+        let rest: Vec<_> = lines.by_ref().collect();
+        assert_eq!(rest, ["three", "four", "five"]);
+        assert_eq!(lines.position(), 5);
+
+        lines.seek_line(1);
+        assert_eq!(lines.next().as_deref(), Some("two"));
+        lines.seek_line(9);
+        assert_eq!(lines.next(), None);
+        assert_eq!(fs.lines("missing.txt").unwrap_err(), FsError::NotFound("missing.txt".into()));
+    }
+
+    #[test]
+    fn revision_changes_only_on_mutation() {
+        let mut fs = MemoryFilesystem::new();
+        let r0 = fs.revision();
+        fs.write_file("a.txt", b"x").unwrap();
+        let r1 = fs.revision();
+        assert_ne!(r0, r1);
+
+        let _ = fs.read_file("a.txt");
+        let _ = fs.list_files();
+        assert!(fs.write_file("missing/b.txt", b"").is_err());
+        assert_eq!(fs.revision(), r1, "reads and failed writes keep the revision");
+
+        fs.create_dir("d").unwrap();
+        assert_ne!(fs.revision(), r1);
+        assert_ne!(MemoryFilesystem::new().revision(), fs.revision());
+    }
+
+    #[test]
+    fn list_files_ordering() {
+        let mut fs = MemoryFilesystem::new();
+        fs.create_dir_all("b/c").unwrap();
+        for path in ["b/c/deep.rs", "b/z.rs", "a.rs", "b/a.rs", "z.rs"] {
+            fs.write_file(path, b"").unwrap();
+        }
+        assert_eq!(
+            fs.list_files(),
+            vec!["a.rs", "b/a.rs", "b/c/deep.rs", "b/z.rs", "z.rs"]
+        );
+
+        let by_depth = fs.list_files_sorted_by(|a, b| {
+            a.matches('/').count().cmp(&b.matches('/').count()).then(a.cmp(b))
+        });
+        assert_eq!(by_depth, vec!["a.rs", "z.rs", "b/a.rs", "b/z.rs", "b/c/deep.rs"]);
+    }
+
     #[test]
     fn rename_file() {
         let mut fs = MemoryFilesystem::new();
@@ -557,6 +1984,64 @@ mod tests {
         assert_eq!(fs.read_file("new.txt").unwrap(), b"content");
     }
 
+    #[test]
+    fn snapshot_registry_round_trip() {
+        let mut alpha = MemoryFilesystem::new();
+        alpha.write_file("notes.txt", b"alpha").unwrap();
+        let mut beta = MemoryFilesystem::new();
+        beta.create_dir("src").unwrap();
+        beta.write_file("src/main.rs", &[0, 159, 146, 150]).unwrap();
+        beta.write_file("empty", b"").unwrap();
+
+        let mut registry = SnapshotRegistry::new();
+        registry.insert(alpha.snapshot_named("alpha", 1_700_000_000_000));
+        registry.insert(beta.snapshot_named("beta project", 1_700_000_000_500));
+
+        let stored = registry.to_storage_string();
+        assert!(stored.is_ascii());
+        let loaded = SnapshotRegistry::from_storage_string(&stored).unwrap();
+        assert_eq!(loaded, registry);
+        let names: Vec<_> = loaded.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["alpha", "beta project"]);
+
+        let picked = loaded.get("beta project").unwrap();
+        assert_eq!(picked.saved_at, 1_700_000_000_500);
+        let mut fs = MemoryFilesystem::new();
+        fs.restore(picked.entries.clone());
+        assert_eq!(fs.read_file("src/main.rs").unwrap(), [0, 159, 146, 150]);
+        assert_eq!(fs.read_file("empty").unwrap(), b"");
+
+        assert!(matches!(
+            SnapshotRegistry::from_storage_string("garbage"),
+            Err(FsError::InvalidArchive(_))
+        ));
+        let huge = format!("{REGISTRY_HEADER}\nsnapshot 0 YQ== {}\n", usize::MAX);
+        assert!(matches!(
+            SnapshotRegistry::from_storage_string(&huge),
+            Err(FsError::InvalidArchive(_))
+        ));
+    }
+
+    #[test]
+    fn from_str_map_creates_parents() {
+        let fs = MemoryFilesystem::from_str_map([
+            ("src/a.rs", "fn a() {}"),
+            ("/README.md", "# demo"),
+            ("src/nested/deep/b.rs", ""),
+        ])
+        .unwrap();
+        assert_eq!(fs.read_to_string("src/a.rs").unwrap(), "fn a() {}");
+        assert_eq!(fs.read_to_string("README.md").unwrap(), "# demo");
+        assert!(fs.is_dir("src/nested/deep"));
+        assert_eq!(fs.list_files(), ["README.md", "src/a.rs", "src/nested/deep/b.rs"]);
+
+        let bytes = MemoryFilesystem::from_str_map([("bin", vec![0u8, 255])]).unwrap();
+        assert_eq!(bytes.read_file("bin").unwrap(), [0, 255]);
+
+        let clash = MemoryFilesystem::from_str_map([("a", "file"), ("a/b", "child")]);
+        assert_eq!(clash.unwrap_err(), FsError::WrongKind("a".into()));
+    }
+
     #[test]
     fn snapshot_and_restore() {
         let mut fs = MemoryFilesystem::new();
@@ -574,6 +2059,46 @@ mod tests {
         assert!(fs2.is_dir("src"));
     }
 
+    #[test]
+    fn merge_snapshot_prefer_theirs() {
+        let mut fs =
+            MemoryFilesystem::from_str_map([("a.txt", "mine"), ("keep.txt", "k")]).unwrap();
+        let theirs = vec![
+            ("a.txt".to_string(), b"theirs".to_vec()),
+            ("new/b.txt".to_string(), b"b".to_vec()),
+            ("keep.txt".to_string(), b"k".to_vec()),
+        ];
+
+        let conflicts = fs.merge_snapshot(theirs, MergeStrategy::PreferTheirs).unwrap();
+        assert_eq!(conflicts, ["a.txt"]);
+        assert_eq!(fs.read_file("a.txt").unwrap(), b"theirs");
+        assert_eq!(fs.read_file("new/b.txt").unwrap(), b"b");
+        assert_eq!(fs.read_file("keep.txt").unwrap(), b"k");
+        assert!(fs.is_dir("new"));
+    }
+
+    #[test]
+    fn merge_snapshot_strategies_and_kind_clashes() {
+        let theirs = || vec![("a.txt".to_string(), b"theirs".to_vec())];
+        let mut fs = MemoryFilesystem::from_str_map([("a.txt", "mine"), ("d/x", "x")]).unwrap();
+        fs.merge_snapshot(theirs(), MergeStrategy::PreferMine).unwrap();
+        assert_eq!(fs.read_file("a.txt").unwrap(), b"mine");
+        fs.merge_snapshot(theirs(), MergeStrategy::NewestWins { mine: 5, theirs: 5 }).unwrap();
+        assert_eq!(fs.read_file("a.txt").unwrap(), b"mine");
+        fs.merge_snapshot(theirs(), MergeStrategy::NewestWins { mine: 5, theirs: 9 }).unwrap();
+        assert_eq!(fs.read_file("a.txt").unwrap(), b"theirs");
+
+        let clash = vec![("e".to_string(), b"e".to_vec()), ("d".to_string(), b"d".to_vec())];
+        let err = fs.merge_snapshot(clash, MergeStrategy::PreferTheirs).unwrap_err();
+        assert_eq!(err, FsError::WrongKind("d".to_string()));
+        assert!(!fs.exists("e"));
+
+        let clash = vec![("n/b".to_string(), b"b".to_vec()), ("n".to_string(), b"n".to_vec())];
+        let err = fs.merge_snapshot(clash, MergeStrategy::PreferTheirs).unwrap_err();
+        assert_eq!(err, FsError::WrongKind("n".to_string()));
+        assert!(!fs.exists("n"));
+    }
+
     #[test]
     fn remove_dir_non_empty_fails() {
         let mut fs = MemoryFilesystem::new();
@@ -582,6 +2107,196 @@ mod tests {
         assert!(fs.remove_dir("d").is_err());
     }
 
+    /// Build a single ustar header + data blocks for a test archive.
+    fn tar_entry(name: &[u8], typeflag: u8, data: &[u8]) -> Vec<u8> {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name);
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+        header[156] = typeflag;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[148..156].copy_from_slice(b"        ");
+        let sum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+        header[148..155].copy_from_slice(format!("{sum:06o}\0").as_bytes());
+
+        let mut out = header.to_vec();
+        out.extend_from_slice(data);
+        out.resize(out.len().div_ceil(512) * 512, 0);
+        out
+    }
+
+    #[test]
+    fn tar_extractor_steps_through_entries() {
+        let mut archive = tar_entry(b"src/", b'5', b"");
+        archive.extend(tar_entry(b"src/main.rs", b'0', b"fn main() {}"));
+        archive.extend(tar_entry(b"src/lib.rs", b'0', b"// lib"));
+        archive.extend(tar_entry(b"docs/readme.md", b'0', b"# hi"));
+        archive.extend(tar_entry(b"empty/", b'5', b""));
+        archive.extend([0u8; 1024]);
+
+        let mut fs = MemoryFilesystem::new();
+        let mut extractor = TarExtractor::new(&archive).unwrap();
+        let mut seen = Vec::new();
+        loop {
+            let progress = extractor.step(&mut fs, 2).unwrap();
+            seen.push((progress.done, progress.current_path.clone()));
+            if progress.is_finished() {
+                assert_eq!(progress.total, 5);
+                break;
+            }
+        }
+        assert_eq!(
+            seen,
+            [
+                (2, Some("src/main.rs".to_string())),
+                (4, Some("docs/readme.md".to_string())),
+                (5, Some("empty/".to_string())),
+            ]
+        );
+        assert_eq!(fs.read_file("src/lib.rs").unwrap(), b"// lib");
+        assert_eq!(fs.read_file("docs/readme.md").unwrap(), b"# hi");
+        assert!(fs.is_dir("empty"));
+        assert_eq!(extractor.step(&mut fs, 2).unwrap().current_path, None);
+    }
+
+    #[test]
+    fn tar_extractor_rejects_a_corrupt_final_entry() {
+        let mut archive = Vec::new();
+        for name in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+            archive.extend(tar_entry(name.as_bytes(), b'0', b"x"));
+        }
+        let mut corrupt = tar_entry(b"e.txt", b'0', b"x");
+        corrupt[124..135].copy_from_slice(b"zzzzzzzzzzz");
+        archive.extend(corrupt);
+
+        // Two steps of two would otherwise report the archive as finished
+        // without ever reaching the bad header.
+        let mut fs = MemoryFilesystem::new();
+        let result = TarExtractor::new(&archive).and_then(|mut extractor| {
+            while !extractor.step(&mut fs, 2)?.is_finished() {}
+            Ok(())
+        });
+        assert!(matches!(result, Err(FsError::InvalidArchive(_))));
+        assert!(fs.list_files().is_empty());
+    }
+
+    #[test]
+    fn import_tar_creates_files_and_dirs() {
+        let mut archive = tar_entry(b"src/", b'5', b"");
+        archive.extend(tar_entry(b"src/main.rs", b'0', b"fn main() {}"));
+        archive.extend(tar_entry(b"docs/readme.md", b'0', b"# hi"));
+        archive.extend([0u8; 1024]);
+
+        let mut fs = MemoryFilesystem::new();
+        fs.import_tar(&archive).unwrap();
+        assert_eq!(fs.read_file("src/main.rs").unwrap(), b"fn main() {}");
+        assert_eq!(fs.read_file("docs/readme.md").unwrap(), b"# hi");
+        assert!(fs.is_dir("docs"));
+    }
+
+    #[test]
+    fn zip_round_trip_keeps_nested_and_empty_dirs() {
+        let mut fs = MemoryFilesystem::from_str_map([
+            ("README.md", "hi"),
+            ("src/lib.rs", "pub mod a;"),
+            ("src/a/deep.rs", "fn x() {}"),
+        ])
+        .unwrap();
+        fs.create_dir_all("assets/empty").unwrap();
+
+        let zip = fs.to_zip().unwrap();
+        assert!(zip.starts_with(b"PK\x03\x04"));
+        let copy = MemoryFilesystem::from_zip(&zip).unwrap();
+        assert_eq!(copy.snapshot(), fs.snapshot());
+        assert!(copy.is_dir("assets/empty"));
+        assert!(copy.read_dir("assets/empty").unwrap().is_empty());
+        assert_eq!(copy.read_dir_all("").unwrap(), fs.read_dir_all("").unwrap());
+    }
+
+    #[test]
+    fn from_zip_rejects_corrupt_archives() {
+        let fs = MemoryFilesystem::from_str_map([("a.txt", "abc")]).unwrap();
+        let mut zip = fs.to_zip().unwrap();
+        let at = zip.windows(3).position(|w| w == b"abc").unwrap();
+        zip[at] = b'x';
+        assert!(matches!(MemoryFilesystem::from_zip(&zip), Err(FsError::InvalidArchive(_))));
+        assert!(matches!(MemoryFilesystem::from_zip(b"nope"), Err(FsError::InvalidArchive(_))));
+    }
+
+    #[test]
+    fn import_tar_rejects_non_utf8_names() {
+        let mut archive = tar_entry(b"ok.txt", b'0', b"fine");
+        archive.extend(tar_entry(b"bad-\xff.txt", b'0', b"data"));
+        archive.extend([0u8; 1024]);
+
+        let mut fs = MemoryFilesystem::new();
+        let err = fs.import_tar(&archive).unwrap_err();
+        assert_eq!(err, FsError::InvalidPath("bad-\u{fffd}.txt".to_string()));
+        // Nothing is imported when any entry is rejected.
+        assert!(fs.list_files().is_empty());
+    }
+
+    #[test]
+    fn import_tar_rejects_file_and_directory_clashes() {
+        let mut archive = tar_entry(b"a", b'0', b"file");
+        archive.extend(tar_entry(b"a/b", b'0', b"below"));
+        archive.extend([0u8; 1024]);
+        let mut fs = MemoryFilesystem::new();
+        assert_eq!(fs.import_tar(&archive), Err(FsError::WrongKind("a".to_string())));
+        assert!(!fs.exists("a"));
+
+        let mut archive = tar_entry(b"first.txt", b'0', b"1");
+        archive.extend(tar_entry(b"d/", b'5', b""));
+        archive.extend(tar_entry(b"d", b'0', b"d"));
+        archive.extend([0u8; 1024]);
+        let revision = fs.revision();
+        assert_eq!(fs.import_tar(&archive), Err(FsError::WrongKind("d".to_string())));
+        assert!(!fs.exists("first.txt"));
+        assert_eq!(fs.revision(), revision);
+
+        // Existing paths count too.
+        fs.write_file("x", b"x").unwrap();
+        let mut archive = tar_entry(b"x/y", b'0', b"y");
+        archive.extend([0u8; 1024]);
+        assert_eq!(fs.import_tar(&archive), Err(FsError::WrongKind("x".to_string())));
+        assert_eq!(fs.list_files(), ["x"]);
+    }
+
+    #[test]
+    fn import_tar_rejects_sizes_past_the_end() {
+        let mut archive = tar_entry(b"big.bin", b'0', b"data");
+        archive[124..135].copy_from_slice(b"37777777777");
+        archive.extend([0u8; 1024]);
+
+        let mut fs = MemoryFilesystem::new();
+        let err = fs.import_tar(&archive).unwrap_err();
+        assert!(matches!(err, FsError::InvalidArchive(_)), "{err:?}");
+    }
+
+    #[test]
+    fn root_directory_contract() {
+        let mut fs = MemoryFilesystem::new();
+        fs.write_file("top.txt", b"x").unwrap();
+
+        for root in ["", "/"] {
+            assert!(fs.exists(root));
+            assert!(fs.is_dir(root));
+            assert!(!fs.is_file(root));
+            assert_eq!(fs.metadata(root).unwrap(), Metadata { is_dir: true, len: 0 });
+            let names: Vec<_> = fs.read_dir(root).unwrap().into_iter().map(|e| e.name).collect();
+            assert_eq!(names, vec!["top.txt"]);
+        }
+
+        let root = ROOT.to_string();
+        assert_eq!(fs.create_dir("/"), Err(FsError::AlreadyExists(root.clone())));
+        assert_eq!(fs.remove_dir("/"), Err(FsError::WrongKind(root.clone())));
+        assert_eq!(fs.rename("/", "x"), Err(FsError::WrongKind(root.clone())));
+        assert_eq!(fs.write_file("/", b""), Err(FsError::WrongKind(root.clone())));
+        fs.remove_file("top.txt").unwrap();
+        assert_eq!(fs.remove_dir(""), Err(FsError::WrongKind(root)));
+        assert!(fs.exists("/"));
+    }
+
     #[test]
     fn remove_dir_empty_succeeds() {
         let mut fs = MemoryFilesystem::new();
@@ -589,4 +2304,56 @@ mod tests {
         fs.remove_dir("d").unwrap();
         assert!(!fs.exists("d"));
     }
+
+    fn overlay() -> OverlayFilesystem<MemoryFilesystem> {
+        let mut base = MemoryFilesystem::new();
+        base.create_dir_all("src/bin").unwrap();
+        base.write_file("README.md", b"base readme").unwrap();
+        base.write_file("src/lib.rs", b"base lib").unwrap();
+        base.write_file("src/bin/main.rs", b"base main").unwrap();
+        OverlayFilesystem::new(base)
+    }
+
+    #[test]
+    fn overlay_reads_through_to_base() {
+        let fs = overlay();
+        assert_eq!(fs.read_file("/src/lib.rs").unwrap(), b"base lib");
+        assert!(fs.is_dir("src/bin"));
+        assert_eq!(fs.list_files(), ["README.md", "src/bin/main.rs", "src/lib.rs"]);
+        assert!(fs.upper().list_files().is_empty());
+    }
+
+    #[test]
+    fn overlay_write_shadows_base() {
+        let mut fs = overlay();
+        fs.write_file("src/lib.rs", b"edited").unwrap();
+        fs.write_file("src/new.rs", b"new").unwrap();
+
+        assert_eq!(fs.read_file("src/lib.rs").unwrap(), b"edited");
+        assert_eq!(fs.base().read_file("src/lib.rs").unwrap(), b"base lib");
+        assert_eq!(fs.metadata("src/lib.rs").unwrap().len, 6);
+        let names: Vec<_> = fs.read_dir("src").unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["bin", "lib.rs", "new.rs"]);
+        assert_eq!(
+            fs.write_file("missing/x.rs", b""),
+            Err(FsError::ParentNotFound("missing/x.rs".into()))
+        );
+    }
+
+    #[test]
+    fn overlay_whiteout_hides_base_file() {
+        let mut fs = overlay();
+        fs.remove_file("README.md").unwrap();
+        assert!(!fs.exists("README.md"));
+        assert_eq!(fs.read_file("README.md"), Err(FsError::NotFound("README.md".into())));
+        assert!(fs.base().exists("README.md"));
+        assert_eq!(fs.whiteouts().collect::<Vec<_>>(), ["README.md"]);
+
+        // A whited-out directory stays empty when re-created.
+        fs.rename("src/bin", "tools").unwrap();
+        assert_eq!(fs.read_file("tools/main.rs").unwrap(), b"base main");
+        fs.create_dir("src/bin").unwrap();
+        assert!(fs.read_dir("src/bin").unwrap().is_empty());
+        assert_eq!(fs.list_files(), ["src/lib.rs", "tools/main.rs"]);
+    }
 }
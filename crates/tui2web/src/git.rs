@@ -8,32 +8,106 @@
 //!
 //! ## Supported operations
 //!
-//! | Operation         | Description |
-//! |-------------------|-------------|
-//! | `init`            | Initialise a new repository |
-//! | `status`          | List changed / staged / untracked files |
-//! | `diff_unstaged`   | Unified diff of unstaged working-directory changes |
-//! | `diff_staged`     | Unified diff of staged (index) changes |
-//! | `diff_commit`     | Unified diff introduced by a specific commit |
-//! | `stage_file`      | Stage a file (add to index) |
-//! | `unstage_file`    | Remove a file from the index |
-//! | `commit`          | Record a new commit with a message |
-//! | `log`             | List recent commits |
-
-use std::collections::BTreeMap;
+//! | Operation                | Description |
+//! |--------------------------|-------------|
+//! | `init`                   | Initialise a new repository |
+//! | `status`                 | List changed / staged / untracked files |
+//! | `status_short`           | Status in git's `XY path` short format |
+//! | `status_counts`          | Number of staged / modified / untracked paths |
+//! | `diff_unstaged`          | Unified diff of unstaged working-directory changes |
+//! | `diff_staged`            | Unified diff of staged (index) changes |
+//! | `snapshot_view`          | Status plus staged and unstaged diffs in one call |
+//! | `has_changes`            | Whether anything is staged or modified (also per side) |
+//! | `file_hunks`             | Staged and unstaged hunks of one file |
+//! | `diff_commit`            | Unified diff introduced by a specific commit |
+//! | `stage_file`             | Stage a file (add to index) |
+//! | `stage_files`            | Stage several files, all or nothing |
+//! | `apply_patch_to_index`   | Stage the changes in a unified diff (`git add -p`) |
+//! | `unstage_file`           | Remove a file from the index |
+//! | `commit`                 | Record a new commit with a message |
+//! | `commit_with`            | Record a commit with an explicit tree and parents |
+//! | `commit_allow_empty`     | Record a commit even if nothing is staged |
+//! | `commit_validated`       | Commit, rejecting an overlong summary line |
+//! | `amend`                  | Replace the HEAD commit, keeping or resetting its author |
+//! | `diff_range`             | Unified diff between two commits |
+//! | `changed_files`          | Paths and statuses changed between two revisions |
+//! | `status_against`         | Paths changed in the working tree since a revision |
+//! | `diff_working_vs_commit` | Unified diff from a commit to the working tree |
+//! | `resolve`                | Resolve `HEAD`, a branch, tag or SHA (with `~n`/`^`) to a SHA |
+//! | `merge_base`             | Best common ancestor of two commits |
+//! | `plan_commit`            | Preview the changes a commit would record |
+//! | `plan_merge`             | Preview a merge's changes and conflicts |
+//! | `plan_cherry_pick`       | Preview a cherry-pick's changes and conflicts |
+//! | `start_merge`            | Apply a merge, leaving conflicts marked until commit or abort |
+//! | `start_cherry_pick`      | Apply a cherry-pick the same way |
+//! | `abort_merge`            | Undo a pending merge (also `abort_cherry_pick`) |
+//! | `reorder_commits`        | Replay the newest commits in a new order |
+//! | `create_branch`          | Create a branch at HEAD |
+//! | `checkout`               | Switch to a branch or detach HEAD at a commit |
+//! | `checkout_tree`          | Replace the working tree with a commit's files, leaving HEAD |
+//! | `reset`                  | Move HEAD to a commit (soft, mixed or hard) |
+//! | `reflog`                 | Every HEAD movement, newest first |
+//! | `create_tag`             | Tag the HEAD commit |
+//! | `describe`               | Nearest tag plus distance, e.g. `v1.0-3-g0000000` |
+//! | `log`                    | List recent commits |
+//! | `commits_iter`           | Lazily iterate the commits `log` lists |
+//! | `shortlog`               | Commit counts per author |
+//!
+//! Paths are normalised as the filesystem normalises them, so `/src/a.rs`,
+//! `src//a.rs` and `src/a.rs` all name the same entry.
+//!
+//! Untracked paths matching the root `.gitignore` are left out of status and
+//! diffs.  Blank lines, `#` comments, `*`/`?`/`**` globs, a leading `/`
+//! (anchored) and a trailing `/` (directories only) are understood; `!`
+//! negations and nested `.gitignore` files are not.  An ignored directory
+//! without tracked files is skipped as a whole, so a large `node_modules` is
+//! never read.
+//!
+//! [`parse_conflicts`] and [`resolve_conflict`] help a resolver UI work with
+//! files containing merge conflict markers; [`add_trailer`] appends a
+//! `Key: value` trailer such as `Signed-off-by` to a commit message.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
+use std::io;
+use std::rc::Rc;
 
-use crate::fs::{Filesystem, MemoryFilesystem};
+use crate::fs::{normalise, Filesystem, MemoryFilesystem};
 
 // ── Error types ──────────────────────────────────────────────────────────────
 
 /// Errors produced by [`GitRepository`] operations.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum GitError {
-    /// The repository has not been initialised.
+    /// The repository has not been initialised: the operation needs a
+    /// commit, but there is none yet (see
+    /// [`InMemoryGitRepository::has_commits`]).
     NotInitialised,
     /// Nothing to commit (empty staging area).
     NothingToCommit,
+    /// The path is not present in the working tree, index or HEAD.
+    PathNotFound(String),
+    /// The revision is well-formed but names no commit in the repository.
+    CommitNotFound(String),
+    /// The operation stopped because these paths have unresolved conflicts.
+    Conflict(Vec<String>),
+    /// The revision string is not a valid commit identifier.
+    InvalidRevision(String),
+    /// A commit needs an author, but none was given and no default is set
+    /// (see [`InMemoryGitRepository::set_default_author`]).
+    MissingIdentity,
+    /// The summary (first line) of a commit message is `len` characters
+    /// long, past the `limit` set with
+    /// [`InMemoryGitRepository::set_summary_limit`].
+    SummaryTooLong { len: usize, limit: usize },
+    /// `name` is not a valid `kind` (`"branch"` or `"tag"`) name; `reason`
+    /// says which rule it breaks, e.g. ``contains `..` ``.
+    InvalidRefName {
+        kind: &'static str,
+        name: String,
+        reason: String,
+    },
     /// A general-purpose error with a human-readable message.
     Other(String),
 }
@@ -43,6 +117,19 @@ impl fmt::Display for GitError {
         match self {
             GitError::NotInitialised => write!(f, "repository not initialised"),
             GitError::NothingToCommit => write!(f, "nothing to commit"),
+            GitError::PathNotFound(path) => write!(f, "file not found: {path}"),
+            GitError::CommitNotFound(sha) => write!(f, "commit not found: {sha}"),
+            GitError::Conflict(paths) => write!(f, "unresolved conflicts: {}", paths.join(", ")),
+            GitError::InvalidRevision(rev) => write!(f, "invalid revision: {rev:?}"),
+            GitError::MissingIdentity => {
+                write!(f, "author identity unknown: pass an author or set a default")
+            }
+            GitError::SummaryTooLong { len, limit } => {
+                write!(f, "commit summary is {len} characters long; the limit is {limit}")
+            }
+            GitError::InvalidRefName { kind, name, reason } => {
+                write!(f, "invalid {kind} name {name:?}: {reason}")
+            }
             GitError::Other(msg) => write!(f, "{msg}"),
         }
     }
@@ -50,6 +137,27 @@ impl fmt::Display for GitError {
 
 impl std::error::Error for GitError {}
 
+/// Lets repository errors flow into `std::io` APIs, with the closest
+/// [`io::ErrorKind`]: missing things map to `NotFound`, bad arguments to
+/// `InvalidInput`, and refused operations to `Other`.
+impl From<GitError> for io::Error {
+    fn from(e: GitError) -> Self {
+        let kind = match e {
+            GitError::NotInitialised
+            | GitError::PathNotFound(_)
+            | GitError::CommitNotFound(_) => io::ErrorKind::NotFound,
+            GitError::InvalidRevision(_)
+            | GitError::MissingIdentity
+            | GitError::SummaryTooLong { .. }
+            | GitError::InvalidRefName { .. } => io::ErrorKind::InvalidInput,
+            GitError::NothingToCommit | GitError::Conflict(_) | GitError::Other(_) => {
+                io::ErrorKind::Other
+            }
+        };
+        io::Error::new(kind, e)
+    }
+}
+
 // ── Data types ───────────────────────────────────────────────────────────────
 
 /// The status of a file relative to HEAD and the staging area.
@@ -72,6 +180,18 @@ impl fmt::Display for FileStatus {
     }
 }
 
+impl FileStatus {
+    /// The single-letter code used by `git status --short`.
+    pub fn short_code(self) -> char {
+        match self {
+            FileStatus::Added => 'A',
+            FileStatus::Modified => 'M',
+            FileStatus::Deleted => 'D',
+            FileStatus::Untracked => '?',
+        }
+    }
+}
+
 /// An entry in the output of [`GitRepository::status`].
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StatusEntry {
@@ -82,6 +202,83 @@ pub struct StatusEntry {
     pub staged: bool,
 }
 
+/// Per-category totals returned by [`GitRepository::status_counts`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatusCounts {
+    /// Paths with staged changes.
+    pub staged: usize,
+    /// Tracked paths with unstaged changes (modified or deleted).
+    pub modified: usize,
+    /// Untracked paths.
+    pub untracked: usize,
+}
+
+/// Everything a git UI redraws each frame, returned by
+/// [`GitRepository::snapshot_view`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoView {
+    /// As returned by [`GitRepository::status`].
+    pub status: Vec<StatusEntry>,
+    /// As returned by [`GitRepository::diff_staged`].
+    pub staged: Vec<FileDiff>,
+    /// As returned by [`GitRepository::diff_unstaged`].
+    pub unstaged: Vec<FileDiff>,
+}
+
+/// The staged and unstaged hunks of one file, returned by
+/// [`GitRepository::file_hunks`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileHunks {
+    /// Hunks between HEAD and the index.
+    pub staged: Vec<DiffHunk>,
+    /// Hunks between the index and the working tree.
+    pub unstaged: Vec<DiffHunk>,
+}
+
+/// Progress of a long-running operation such as
+/// [`InMemoryGitRepository::reorder_commits_with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Steps completed so far.
+    pub done: usize,
+    /// Steps in the whole operation.
+    pub total: usize,
+}
+
+impl Progress {
+    /// Fraction completed, in `0.0..=1.0`, ready for ratatui's `Gauge::ratio`.
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f64 / self.total as f64
+        }
+    }
+}
+
+/// What HEAD points at, returned by [`InMemoryGitRepository::head_ref`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadRef {
+    /// HEAD follows a branch, which each commit advances.  The branch is
+    /// *unborn* until its first commit.
+    Branch(String),
+    /// HEAD names a commit directly ("detached HEAD").
+    Detached(String),
+}
+
+/// How much [`InMemoryGitRepository::reset`] resets besides HEAD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetMode {
+    /// Move HEAD only; the index and working tree keep their contents, so
+    /// the undone changes show up as staged.
+    Soft,
+    /// Also reset the index, leaving the undone changes unstaged.
+    Mixed,
+    /// Also overwrite the working tree, discarding tracked changes.
+    /// Untracked files are left alone.
+    Hard,
+}
+
 /// A hunk inside a unified diff.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DiffHunk {
@@ -91,6 +288,37 @@ pub struct DiffHunk {
     pub lines: Vec<String>,
 }
 
+/// What a line of a [`DiffHunk`] does, as given by its prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// Unchanged (` `).
+    Context,
+    /// Added (`+`).
+    Added,
+    /// Removed (`-`).
+    Removed,
+}
+
+impl DiffHunk {
+    /// Each line's kind and its text without the prefix or line terminator,
+    /// ready to draw with the colour the kind implies.
+    pub fn display_lines(&self) -> Vec<(LineKind, &str)> {
+        self.lines
+            .iter()
+            .map(|line| {
+                let kind = match line.as_bytes().first() {
+                    Some(b'+') => LineKind::Added,
+                    Some(b'-') => LineKind::Removed,
+                    _ => LineKind::Context,
+                };
+                let text = line.get(1..).unwrap_or("");
+                let text = text.strip_suffix('\n').unwrap_or(text);
+                (kind, text.strip_suffix('\r').unwrap_or(text))
+            })
+            .collect()
+    }
+}
+
 /// Per-file diff information returned by diff operations.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FileDiff {
@@ -99,6 +327,92 @@ pub struct FileDiff {
     pub hunks: Vec<DiffHunk>,
 }
 
+/// The only line of the single hunk standing in for a diff skipped under
+/// [`DiffOptions::max_lines`], marked with `\` like git's "No newline at
+/// end of file".
+pub const TOO_LARGE_TO_DIFF: &str = "\\ file too large to diff\n";
+
+impl FileDiff {
+    /// `true` when the hunks were skipped under [`DiffOptions::max_lines`]
+    /// and hold only the [`TOO_LARGE_TO_DIFF`] placeholder.
+    pub fn is_too_large(&self) -> bool {
+        matches!(&self.hunks[..], [hunk] if hunk.lines == [TOO_LARGE_TO_DIFF])
+    }
+}
+
+/// Options controlling how diff operations compute and order their results.
+///
+/// Set on a repository with [`InMemoryGitRepository::set_diff_options`], or
+/// applied to any `Vec<FileDiff>` with [`DiffOptions::apply`].
+#[derive(Debug, Clone, Copy)]
+pub struct DiffOptions {
+    /// Group files by status (modified, then added, then deleted) instead of
+    /// interleaving them.  Paths stay sorted within each group.
+    pub group_by_status: bool,
+    /// Line-diff algorithm used for modified files; [`LcsDiff`] by default.
+    pub algorithm: &'static dyn DiffAlgorithm,
+    /// A modified file with more lines than this on either side is not
+    /// line-diffed; its single hunk holds [`TOO_LARGE_TO_DIFF`] instead, so
+    /// two huge, unrelated versions cannot stall the UI.  Unlimited (`None`)
+    /// by default.
+    pub max_lines: Option<usize>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            group_by_status: false,
+            algorithm: &LcsDiff,
+            max_lines: None,
+        }
+    }
+}
+
+/// Algorithms compare by [`DiffAlgorithm::name`], which must therefore be
+/// unique to each algorithm; trait-object pointers are no substitute, as
+/// zero-sized algorithms can share an address and vtables can be
+/// duplicated.
+impl PartialEq for DiffOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.group_by_status == other.group_by_status
+            && self.algorithm.name() == other.algorithm.name()
+            && self.max_lines == other.max_lines
+    }
+}
+
+impl Eq for DiffOptions {}
+
+impl DiffOptions {
+    /// Reorder `diffs` (assumed sorted by path) according to these options.
+    pub fn apply(&self, diffs: &mut [FileDiff]) {
+        if self.group_by_status {
+            // Stable, so path order is kept within each group.
+            diffs.sort_by_key(|d| match d.status {
+                FileStatus::Modified => 0,
+                FileStatus::Added => 1,
+                FileStatus::Deleted => 2,
+                FileStatus::Untracked => 3,
+            });
+        }
+    }
+}
+
+/// Line-ending conversion set with [`InMemoryGitRepository::set_autocrlf`],
+/// after git's `core.autocrlf`.
+///
+/// Only text files are converted; a file containing a NUL byte counts as
+/// binary and is always stored as is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AutoCrlf {
+    /// Store and check out files unchanged.
+    #[default]
+    False,
+    /// Store CRLF line endings as LF; check files out unchanged.
+    Input,
+    /// Store CRLF line endings as LF and check files out with CRLF.
+    True,
+}
+
 /// Metadata for a commit in the log.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CommitInfo {
@@ -112,6 +426,75 @@ pub struct CommitInfo {
     pub author: String,
 }
 
+/// Options for [`InMemoryGitRepository::amend`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AmendOptions {
+    /// Record the amending author instead of keeping the original one
+    /// (`git commit --amend --reset-author`).
+    pub reset_author: bool,
+}
+
+/// Preview of a mutating operation, returned by the `plan_*` methods of
+/// [`InMemoryGitRepository`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationPlan {
+    /// Changes the operation would make to the HEAD tree.
+    pub diffs: Vec<FileDiff>,
+    /// Paths that would conflict; their HEAD contents are kept in `diffs`.
+    pub conflicts: Vec<String>,
+}
+
+/// An operation stopped by conflicts, like git's `MERGE_HEAD` and
+/// `CHERRY_PICK_HEAD`, returned by
+/// [`InMemoryGitRepository::pending_operation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PendingOperation {
+    /// A merge of this commit into HEAD.
+    Merge(String),
+    /// A cherry-pick of this commit onto HEAD.
+    CherryPick(String),
+}
+
+/// One HEAD movement, returned by [`InMemoryGitRepository::reflog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReflogEntry {
+    /// Commit HEAD pointed at before, or `None` when the branch was unborn.
+    pub old: Option<String>,
+    /// Commit HEAD pointed at afterwards.
+    pub new: String,
+    /// What moved HEAD, in git's style, e.g. `commit: Fix typo` or
+    /// `checkout: moving from main to dev`.
+    pub action: String,
+}
+
+/// Which side of a conflict to keep in [`resolve_conflict`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// Keep our version (between `<<<<<<<` and `=======`).
+    Ours,
+    /// Keep their version (between `=======` and `>>>>>>>`).
+    Theirs,
+    /// Keep both, ours first.
+    Union,
+}
+
+/// A conflicted region found by [`parse_conflicts`].
+///
+/// Lines are stored without their line terminators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictBlock {
+    /// 0-based line number of the `<<<<<<<` marker.
+    pub start_line: usize,
+    /// Our lines.
+    pub ours: Vec<String>,
+    /// Their lines.
+    pub theirs: Vec<String>,
+    /// Up to three unconflicted lines preceding the block.
+    pub context_before: Vec<String>,
+    /// Up to three unconflicted lines following the block.
+    pub context_after: Vec<String>,
+}
+
 // ── Trait ─────────────────────────────────────────────────────────────────────
 
 /// Abstraction over git operations.
@@ -121,13 +504,109 @@ pub trait GitRepository {
     /// Return the working-directory status: changed, staged, and untracked files.
     fn status(&self) -> Result<Vec<StatusEntry>, GitError>;
 
+    /// Render [`status`](Self::status) in git's short `XY path` format, one
+    /// line per path (e.g. `M  src/a.rs`, `MM both.rs`, `?? new.txt`).
+    ///
+    /// `X` is the staged change and `Y` the unstaged one; a space means
+    /// unchanged on that side.  An untracked path always gets a `??` line
+    /// of its own, so a staged deletion of a file that was then recreated
+    /// shows as both `D  a.txt` and `?? a.txt`.
+    fn status_short(&self) -> Result<String, GitError> {
+        let mut out = String::new();
+        // Keyed by path, then whether untracked, so a path's tracked line
+        // comes first.
+        let mut lines: BTreeMap<(String, bool), [char; 2]> = BTreeMap::new();
+        for entry in self.status()? {
+            let untracked = entry.status == FileStatus::Untracked;
+            let codes = lines.entry((entry.path, untracked)).or_insert([' ', ' ']);
+            match (entry.status, entry.staged) {
+                (FileStatus::Untracked, _) => *codes = ['?', '?'],
+                (status, true) => codes[0] = status.short_code(),
+                (status, false) => codes[1] = status.short_code(),
+            }
+        }
+        for ((path, _), [x, y]) in lines {
+            out.push(x);
+            out.push(y);
+            out.push(' ');
+            out.push_str(&path);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Count staged, modified and untracked paths, e.g. for a status bar.
+    ///
+    /// Derived from [`status`](Self::status) alone, so no diff hunks are
+    /// computed.  A path with both staged and unstaged changes counts in both
+    /// `staged` and `modified`.
+    fn status_counts(&self) -> Result<StatusCounts, GitError> {
+        let mut counts = StatusCounts::default();
+        for entry in self.status()? {
+            match (entry.status, entry.staged) {
+                (FileStatus::Untracked, _) => counts.untracked += 1,
+                (_, true) => counts.staged += 1,
+                (_, false) => counts.modified += 1,
+            }
+        }
+        Ok(counts)
+    }
+
     /// Produce a unified diff of *unstaged* working-directory changes
     /// (index → working tree).
+    ///
+    /// An empty list always means there are no such changes; anything that
+    /// prevents computing the diff is an error instead.
     fn diff_unstaged(&self) -> Result<Vec<FileDiff>, GitError>;
 
     /// Produce a unified diff of *staged* changes (HEAD → index).
+    ///
+    /// As with [`diff_unstaged`](Self::diff_unstaged), empty means clean.
     fn diff_staged(&self) -> Result<Vec<FileDiff>, GitError>;
 
+    /// `true` when [`diff_staged`](Self::diff_staged) is non-empty.
+    fn has_staged_changes(&self) -> Result<bool, GitError> {
+        Ok(!self.diff_staged()?.is_empty())
+    }
+
+    /// `true` when [`diff_unstaged`](Self::diff_unstaged) is non-empty.
+    fn has_unstaged_changes(&self) -> Result<bool, GitError> {
+        Ok(!self.diff_unstaged()?.is_empty())
+    }
+
+    /// `true` when there are staged or unstaged changes, i.e. the working
+    /// tree does not match HEAD.
+    fn has_changes(&self) -> Result<bool, GitError> {
+        Ok(self.has_staged_changes()? || self.has_unstaged_changes()?)
+    }
+
+    /// Return status, staged diffs and unstaged diffs together, for a UI
+    /// that redraws all three every frame.
+    ///
+    /// [`InMemoryGitRepository`] caches the working tree between
+    /// filesystem changes, so the three parts share a single read of it.
+    fn snapshot_view(&self) -> Result<RepoView, GitError> {
+        Ok(RepoView {
+            status: self.status()?,
+            staged: self.diff_staged()?,
+            unstaged: self.diff_unstaged()?,
+        })
+    }
+
+    /// Return the staged and unstaged hunks of `path`, e.g. for a hunk
+    /// selector showing both sides of one file.  Both lists are empty for
+    /// an unchanged or unknown path.
+    fn file_hunks(&self, path: &str) -> Result<FileHunks, GitError> {
+        let path = normalise(path);
+        let hunks_of = |diffs: Vec<FileDiff>| {
+            diffs.into_iter().find(|d| d.path == path).map(|d| d.hunks).unwrap_or_default()
+        };
+        Ok(FileHunks {
+            staged: hunks_of(self.diff_staged()?),
+            unstaged: hunks_of(self.diff_unstaged()?),
+        })
+    }
+
     /// Produce a unified diff introduced by a specific commit.
     fn diff_commit(&self, sha: &str) -> Result<Vec<FileDiff>, GitError>;
 
@@ -140,32 +619,85 @@ pub trait GitRepository {
     /// Create a new commit with the given message.  Returns the commit SHA.
     fn commit(&mut self, message: &str, author: &str) -> Result<String, GitError>;
 
-    /// Return the most recent commits (newest first), up to `max_count`.
+    /// Return the most recent commits reachable from HEAD (newest first), up
+    /// to `max_count`.
     fn log(&self, max_count: usize) -> Result<Vec<CommitInfo>, GitError>;
 }
 
 // ── In-memory implementation ─────────────────────────────────────────────────
 
-/// Snapshot of file contents at a point in time.
-type TreeSnapshot = BTreeMap<String, Vec<u8>>;
+/// Identifier of a blob in a repository's blob store (see
+/// [`InMemoryGitRepository::write_blob`]).
+pub type BlobId = u64;
+
+/// Snapshot of a tree at a point in time: normalised path → blob.
+pub type TreeSnapshot = BTreeMap<String, BlobId>;
+
+/// File contents read from the working tree, keyed by normalised path.
+type WorkTree = BTreeMap<String, Vec<u8>>;
+
+/// A [`WorkTree`] read at one filesystem revision, with what its ignore
+/// filtering assumed about tracked paths.
+#[derive(Debug, Clone)]
+struct WorkCache {
+    revision: u64,
+    tree: Rc<WorkTree>,
+    /// Ignored files seen, and whether each was tracked (and so kept).
+    ignored: Vec<(String, bool)>,
+    /// Ignored directories skipped because nothing under them was tracked.
+    pruned: Vec<String>,
+}
+
+/// Resolved file contents, as compared by `diff_trees`.
+type Files<'a> = BTreeMap<&'a str, &'a [u8]>;
+
+/// Branch HEAD starts out on in a new repository.
+const DEFAULT_BRANCH: &str = "main";
+
+/// Default summary-line limit for [`InMemoryGitRepository::commit_validated`].
+const DEFAULT_SUMMARY_LIMIT: usize = 72;
 
 /// An in-memory commit record.
 #[derive(Debug, Clone)]
 struct Commit {
     sha: String,
+    /// Parent SHAs; the first parent is the mainline (empty for a root commit).
+    parents: Vec<String>,
     message: String,
     author: String,
     /// Snapshot of the full tree at this commit.
     tree: TreeSnapshot,
 }
 
+/// State saved when a merge or cherry-pick starts, restored by aborting it.
+#[derive(Debug, Clone)]
+struct Pending {
+    operation: PendingOperation,
+    index: TreeSnapshot,
+    staged_deletions: BTreeSet<String>,
+    /// Pre-operation contents of every working-tree path the operation
+    /// wrote, `None` for paths that did not exist.
+    work: BTreeMap<String, Option<Vec<u8>>>,
+    /// Paths written with conflict markers and not staged since; each
+    /// blocks the concluding commit until it is.
+    conflicts: BTreeSet<String>,
+}
+
 /// A fully in-memory [`GitRepository`] that operates on a
 /// [`MemoryFilesystem`].
 ///
 /// The implementation maintains:
-/// - The **HEAD** tree (snapshot at the last commit)
-/// - The **index** (staging area)
-/// - A linear commit history
+/// - **HEAD**, either a branch or a detached commit, and its tree
+/// - The **index** (staging area), plus explicit tombstones for staged
+///   deletions
+/// - The **branches** and **tags**, each pointing at a commit
+/// - The **reflog**, recording every movement of HEAD
+/// - The **blob store**, holding each distinct file content once; the index
+///   and every commit tree refer to it by [`BlobId`]
+/// - The commit history, in creation order (each commit records its parents,
+///   so the history may form a DAG)
+///
+/// A new repository is on the unborn branch `main`.
 ///
 /// Diff generation uses a simple line-by-line comparison.
 #[derive(Debug, Clone)]
@@ -174,12 +706,37 @@ pub struct InMemoryGitRepository {
     fs: MemoryFilesystem,
     /// HEAD tree snapshot.
     head: TreeSnapshot,
-    /// Staging area (index).
+    /// Staging area (index).  Paths staged for deletion are absent here and
+    /// listed in `staged_deletions`.
     index: TreeSnapshot,
-    /// Linear commit history, newest last.
+    /// HEAD paths whose deletion has been staged.
+    staged_deletions: BTreeSet<String>,
+    /// Commit history in creation order, newest last.
     commits: Vec<Commit>,
+    /// Content-addressed blob store shared by the index and every commit.
+    blobs: BTreeMap<BlobId, Vec<u8>>,
+    /// What HEAD points at.
+    head_ref: HeadRef,
+    /// Branch name → commit SHA.  Unborn branches are absent.
+    branches: BTreeMap<String, String>,
+    /// Tag name → commit SHA.
+    tags: BTreeMap<String, String>,
+    /// HEAD movements, oldest first.
+    reflog: Vec<ReflogEntry>,
     /// Monotonic counter for generating pseudo-SHA identifiers.
     next_id: u64,
+    /// Working tree last read, reused while still valid.
+    work_cache: RefCell<Option<WorkCache>>,
+    /// Ordering applied to every diff result.
+    diff_options: DiffOptions,
+    /// Line-ending conversion between the working tree and stored blobs.
+    autocrlf: AutoCrlf,
+    /// Longest summary line, in characters, `commit_validated` accepts.
+    summary_limit: usize,
+    /// Author recorded when a commit is made with an empty author.
+    default_author: Option<String>,
+    /// Merge or cherry-pick waiting to be committed or aborted.
+    pending: Option<Pending>,
 }
 
 impl InMemoryGitRepository {
@@ -189,8 +746,20 @@ impl InMemoryGitRepository {
             fs,
             head: BTreeMap::new(),
             index: BTreeMap::new(),
+            staged_deletions: BTreeSet::new(),
             commits: Vec::new(),
+            blobs: BTreeMap::new(),
+            head_ref: HeadRef::Branch(DEFAULT_BRANCH.to_string()),
+            branches: BTreeMap::new(),
+            tags: BTreeMap::new(),
+            reflog: Vec::new(),
             next_id: 1,
+            work_cache: RefCell::new(None),
+            diff_options: DiffOptions::default(),
+            autocrlf: AutoCrlf::default(),
+            summary_limit: DEFAULT_SUMMARY_LIMIT,
+            default_author: None,
+            pending: None,
         }
     }
 
@@ -204,633 +773,3781 @@ impl InMemoryGitRepository {
         &mut self.fs
     }
 
-    // ── internal helpers ─────────────────────────────────────────────────
+    /// A number that changes whenever the working tree's filesystem is
+    /// modified, through this repository or behind its back (see
+    /// [`MemoryFilesystem::revision`]).
+    ///
+    /// A polling UI can re-run [`status`](GitRepository::status) only when
+    /// this differs from the value it last saw.
+    pub fn working_tree_revision(&self) -> u64 {
+        self.fs.revision()
+    }
 
-    /// Generate a deterministic hex-string identifier.
-    fn make_sha(&mut self) -> String {
-        let id = self.next_id;
-        self.next_id += 1;
-        format!("{id:016x}")
+    /// Set the [`DiffOptions`] used by every diff operation.
+    pub fn set_diff_options(&mut self, options: DiffOptions) {
+        self.diff_options = options;
     }
 
-    /// Build a snapshot of the current working tree from the filesystem.
-    fn working_tree(&self) -> TreeSnapshot {
-        let mut tree = BTreeMap::new();
-        for path in self.fs.list_files() {
-            if let Ok(data) = self.fs.read_file(&path) {
-                tree.insert(path, data);
-            }
-        }
-        tree
+    /// Set the line-ending conversion applied when files are staged and
+    /// checked out.
+    ///
+    /// With [`AutoCrlf::Input`] or [`AutoCrlf::True`], status and diffs also
+    /// compare the working tree with CRLF read as LF, so a file that differs
+    /// from the index only in its line endings is unchanged.
+    pub fn set_autocrlf(&mut self, mode: AutoCrlf) {
+        self.autocrlf = mode;
+        self.work_cache.borrow_mut().take();
     }
 
-    /// Compute the unified diff between two snapshots.
-    fn diff_trees(old: &TreeSnapshot, new: &TreeSnapshot) -> Vec<FileDiff> {
-        let mut diffs = Vec::new();
-        let mut all_paths: std::collections::BTreeSet<&String> = std::collections::BTreeSet::new();
-        all_paths.extend(old.keys());
-        all_paths.extend(new.keys());
+    /// Set the longest summary line, in characters, that
+    /// [`commit_validated`](Self::commit_validated) accepts (72 by default).
+    pub fn set_summary_limit(&mut self, chars: usize) {
+        self.summary_limit = chars;
+    }
 
-        for path in all_paths {
-            let old_content = old.get(path);
-            let new_content = new.get(path);
+    /// Set the author recorded by commits made with an empty author string,
+    /// like git's `user.name` / `user.email`; `None` removes it.
+    pub fn set_default_author(&mut self, author: Option<String>) {
+        self.default_author = author;
+    }
 
-            match (old_content, new_content) {
-                (None, Some(new_data)) => {
-                    // Added file.
-                    let new_str = String::from_utf8_lossy(new_data);
-                    let hunks = diff_added(&new_str);
-                    diffs.push(FileDiff {
-                        path: path.clone(),
-                        status: FileStatus::Added,
-                        hunks,
-                    });
-                }
-                (Some(old_data), None) => {
-                    // Deleted file.
-                    let old_str = String::from_utf8_lossy(old_data);
-                    let hunks = diff_deleted(&old_str);
-                    diffs.push(FileDiff {
-                        path: path.clone(),
-                        status: FileStatus::Deleted,
-                        hunks,
-                    });
-                }
-                (Some(old_data), Some(new_data)) => {
-                    if old_data != new_data {
-                        let old_str = String::from_utf8_lossy(old_data);
-                        let new_str = String::from_utf8_lossy(new_data);
-                        let hunks = diff_modified(&old_str, &new_str);
-                        diffs.push(FileDiff {
-                            path: path.clone(),
-                            status: FileStatus::Modified,
-                            hunks,
-                        });
-                    }
-                }
-                (None, None) => {}
-            }
-        }
+    /// Return the default author, or [`GitError::MissingIdentity`] when none
+    /// is set, so a UI can ask for one before the user tries to commit.
+    ///
+    /// Every commit (including [`amend`](Self::amend) with `reset_author`)
+    /// made with an empty author string falls back to this, failing the
+    /// same way.
+    pub fn require_identity(&self) -> Result<&str, GitError> {
+        self.default_author
+            .as_deref()
+            .filter(|author| !author.trim().is_empty())
+            .ok_or(GitError::MissingIdentity)
+    }
 
-        diffs
+    /// Store `data` in the blob store and return its id.  Identical contents
+    /// always share one entry, however many paths and commits refer to them.
+    pub fn write_blob(&mut self, data: &[u8]) -> BlobId {
+        let (id, stored) = self.find_blob(data);
+        if !stored {
+            self.blobs.insert(id, data.to_vec());
+        }
+        id
     }
-}
 
-impl GitRepository for InMemoryGitRepository {
-    fn status(&self) -> Result<Vec<StatusEntry>, GitError> {
-        let work = self.working_tree();
-        let mut entries = Vec::new();
+    /// Return the contents of blob `id`.
+    pub fn blob(&self, id: BlobId) -> Option<&[u8]> {
+        self.blobs.get(&id).map(Vec::as_slice)
+    }
 
-        // Gather all known paths.
-        let mut all_paths: std::collections::BTreeSet<&String> =
-            std::collections::BTreeSet::new();
-        all_paths.extend(self.head.keys());
-        all_paths.extend(self.index.keys());
-        all_paths.extend(work.keys());
+    /// Return the number of distinct blobs stored.
+    pub fn blob_count(&self) -> usize {
+        self.blobs.len()
+    }
 
-        for path in all_paths {
-            let in_head = self.head.contains_key(path);
-            let in_index = self.index.contains_key(path);
-            let in_work = work.contains_key(path);
+    /// Return what HEAD points at.
+    pub fn head_ref(&self) -> &HeadRef {
+        &self.head_ref
+    }
 
-            // Staged changes (HEAD → index).
-            match (in_head, in_index) {
-                (false, true) => entries.push(StatusEntry {
-                    path: path.clone(),
-                    status: FileStatus::Added,
-                    staged: true,
-                }),
-                (true, true) if self.head.get(path) != self.index.get(path) => {
-                    entries.push(StatusEntry {
-                        path: path.clone(),
-                        status: FileStatus::Modified,
-                        staged: true,
-                    });
-                }
-                (true, false) => entries.push(StatusEntry {
-                    path: path.clone(),
-                    status: FileStatus::Deleted,
-                    staged: true,
-                }),
-                _ => {}
-            }
+    /// Return the SHA of the commit HEAD resolves to, or `None` while the
+    /// current branch is unborn.
+    pub fn head_sha(&self) -> Option<&str> {
+        match &self.head_ref {
+            HeadRef::Branch(name) => self.branches.get(name).map(String::as_str),
+            HeadRef::Detached(sha) => Some(sha),
+        }
+    }
 
-            // Unstaged changes (index → working tree) or (HEAD → working tree
-            // for untracked).
-            let baseline = if in_index {
-                self.index.get(path)
-            } else if in_head {
-                self.head.get(path)
-            } else {
-                None
-            };
+    /// `true` once any commit has been recorded.  Before that, `log` is
+    /// empty and `diff_commit`, `reset` and `amend` fail with
+    /// [`GitError::NotInitialised`].
+    pub fn has_commits(&self) -> bool {
+        !self.commits.is_empty()
+    }
 
-            match (baseline, in_work) {
-                (None, true) if !in_head && !in_index => {
-                    entries.push(StatusEntry {
-                        path: path.clone(),
-                        status: FileStatus::Untracked,
-                        staged: false,
-                    });
-                }
-                (Some(base), true) if Some(base) != work.get(path) => {
-                    entries.push(StatusEntry {
-                        path: path.clone(),
-                        status: FileStatus::Modified,
-                        staged: false,
-                    });
-                }
-                (Some(_), false) if !entries.iter().any(|e| e.path == *path && e.staged) => {
-                    entries.push(StatusEntry {
-                        path: path.clone(),
-                        status: FileStatus::Deleted,
-                        staged: false,
-                    });
-                }
-                _ => {}
-            }
+    /// Fail with [`GitError::NotInitialised`] before the first commit.
+    fn require_commits(&self) -> Result<(), GitError> {
+        if self.has_commits() {
+            Ok(())
+        } else {
+            Err(GitError::NotInitialised)
         }
+    }
 
-        Ok(entries)
+    /// `true` when HEAD names a commit directly rather than a branch.
+    pub fn is_detached(&self) -> bool {
+        matches!(self.head_ref, HeadRef::Detached(_))
     }
 
-    fn diff_unstaged(&self) -> Result<Vec<FileDiff>, GitError> {
-        let work = self.working_tree();
-        // Base is the index if it has the file, otherwise HEAD.
-        let mut base = self.head.clone();
-        for (k, v) in &self.index {
-            base.insert(k.clone(), v.clone());
-        }
-        // Remove files that were staged as deleted.
-        for k in self.head.keys() {
-            if !self.index.contains_key(k)
-                && self
-                    .commits
-                    .last()
-                    .map_or(false, |_| !self.index.contains_key(k))
-            {
-                // If index explicitly doesn't have this file but HEAD does,
-                // it was staged as deleted – still use HEAD as the base so
-                // that working-tree additions show up.
-            }
+    /// Return the branch HEAD follows, or `None` when detached.
+    pub fn current_branch(&self) -> Option<&str> {
+        match &self.head_ref {
+            HeadRef::Branch(name) => Some(name),
+            HeadRef::Detached(_) => None,
         }
-        Ok(Self::diff_trees(&base, &work))
     }
 
-    fn diff_staged(&self) -> Result<Vec<FileDiff>, GitError> {
-        Ok(Self::diff_trees(&self.head, &self.index))
+    /// `true` when `path` is in the HEAD commit or the index, i.e. git
+    /// knows about it even if it is clean.  Cheaper than scanning
+    /// [`status`](GitRepository::status) for one path.
+    pub fn is_tracked(&self, path: &str) -> bool {
+        let path = normalise(path);
+        let path = path.as_str();
+        self.index.contains_key(path) || self.head.contains_key(path)
     }
 
-    fn diff_commit(&self, sha: &str) -> Result<Vec<FileDiff>, GitError> {
-        let commit = self
-            .commits
-            .iter()
-            .find(|c| c.sha == sha)
-            .ok_or_else(|| GitError::Other(format!("commit not found: {sha}")))?;
+    /// Create a branch pointing at the HEAD commit, without switching to it.
+    ///
+    /// This is also how work committed on a detached HEAD is kept.  Names
+    /// git would refuse, such as those containing whitespace, `~`, `^`, `:`
+    /// or `..`, or starting or ending with `/`, are rejected with
+    /// [`GitError::InvalidRefName`] saying what is wrong.
+    pub fn create_branch(&mut self, name: &str) -> Result<(), GitError> {
+        check_ref_name("branch", name)?;
+        if self.branches.contains_key(name) {
+            return Err(GitError::Other(format!("branch already exists: {name}")));
+        }
+        let sha = self
+            .head_sha()
+            .ok_or_else(|| GitError::InvalidRevision("HEAD".to_string()))?
+            .to_string();
+        self.branches.insert(name.to_string(), sha);
+        Ok(())
+    }
 
-        // Find the parent (previous commit).
-        let parent_tree: TreeSnapshot = self
-            .commits
-            .iter()
-            .zip(self.commits.iter().skip(1))
-            .find(|(_, cur)| cur.sha == sha)
-            .map(|(prev, _)| prev.tree.clone())
-            .unwrap_or_default();
+    /// Create a lightweight tag pointing at the HEAD commit.  The name is
+    /// validated as for [`create_branch`](Self::create_branch).
+    pub fn create_tag(&mut self, name: &str) -> Result<(), GitError> {
+        check_ref_name("tag", name)?;
+        if self.tags.contains_key(name) {
+            return Err(GitError::Other(format!("tag already exists: {name}")));
+        }
+        let sha = self
+            .head_sha()
+            .ok_or_else(|| GitError::InvalidRevision("HEAD".to_string()))?
+            .to_string();
+        self.tags.insert(name.to_string(), sha);
+        Ok(())
+    }
+
+    /// Describe commit `sha` relative to the nearest tag among its ancestors,
+    /// like `git describe --tags`: the tag name alone when `sha` is tagged,
+    /// otherwise `<tag>-<distance>-g<short sha>`, where the distance counts
+    /// the commits reachable from `sha` but not from the tag.  Returns the
+    /// short SHA when no tag is reachable.
+    pub fn describe(&self, sha: &str) -> Result<String, GitError> {
+        let reachable = self.ancestors(sha)?;
+        let mut nearest: Option<(usize, &str)> = None;
+        for (name, tagged) in &self.tags {
+            if !reachable.contains(tagged.as_str()) {
+                continue;
+            }
+            let distance = reachable.difference(&self.ancestors(tagged)?).count();
+            if nearest.is_none_or(|(best, _)| distance < best) {
+                nearest = Some((distance, name));
+            }
+        }
+        Ok(match nearest {
+            Some((0, name)) => name.to_string(),
+            Some((distance, name)) => format!("{name}-{distance}-g{}", short_sha(sha)),
+            None => short_sha(sha).to_string(),
+        })
+    }
+
+    /// Switch HEAD to a branch, or detach it at a commit SHA, replacing the
+    /// working tree and index with that commit's tree.
+    ///
+    /// Fails with [`GitError::Conflict`] when tracked files have staged or
+    /// unstaged changes, or when an untracked file would be overwritten,
+    /// and with [`GitError::Other`] while a merge or cherry-pick is pending.
+    pub fn checkout(&mut self, target: &str) -> Result<(), GitError> {
+        self.refuse_while_pending()?;
+        let (sha, head_ref) = match self.branches.get(target) {
+            Some(sha) => (sha.clone(), HeadRef::Branch(target.to_string())),
+            None => {
+                let sha = self.find_commit(target)?.sha.clone();
+                (sha.clone(), HeadRef::Detached(sha))
+            }
+        };
+        let tree = self.find_commit(&sha)?.tree.clone();
+        let old = self.head_sha().map(str::to_string);
+        let action = format!("checkout: moving from {} to {target}", self.head_name());
+        self.refuse_to_overwrite(&tree)?;
 
-        Ok(Self::diff_trees(&parent_tree, &commit.tree))
+        let stale: Vec<String> =
+            self.head.keys().filter(|p| !tree.contains_key(*p)).cloned().collect();
+        self.replace_work_tree(&stale, &tree)?;
+        self.head = tree.clone();
+        self.index = tree;
+        self.staged_deletions.clear();
+        self.head_ref = head_ref;
+        self.log_head(old, action);
+        Ok(())
     }
 
-    fn stage_file(&mut self, path: &str) -> Result<(), GitError> {
+    /// Fail with [`GitError::Conflict`] when replacing the working tree with
+    /// `tree` would lose work: tracked files with staged or unstaged changes,
+    /// or untracked files that `tree` would overwrite.
+    fn refuse_to_overwrite(&self, tree: &TreeSnapshot) -> Result<(), GitError> {
         let work = self.working_tree();
-        if let Some(data) = work.get(path) {
-            self.index.insert(path.to_string(), data.clone());
-        } else if self.head.contains_key(path) {
-            // File was deleted in working tree – record the deletion in the
-            // index by removing it.
-            self.index.remove(path);
+        let mut blocked: Vec<String> = Vec::new();
+        for entry in self.status()? {
+            let overwritten = entry.status != FileStatus::Untracked
+                || tree
+                    .get(&entry.path)
+                    .is_some_and(|id| work.get(&entry.path) != Some(&self.blobs[id]));
+            if overwritten && blocked.last() != Some(&entry.path) {
+                blocked.push(entry.path);
+            }
+        }
+        if blocked.is_empty() {
+            Ok(())
         } else {
-            return Err(GitError::Other(format!("file not found: {path}")));
+            Err(GitError::Conflict(blocked))
         }
-        Ok(())
     }
 
-    fn unstage_file(&mut self, path: &str) -> Result<(), GitError> {
-        if self.head.contains_key(path) {
-            // Revert index to HEAD version.
-            self.index
-                .insert(path.to_string(), self.head[path].clone());
+    /// Replace the working tree with the files of revision `rev` (anything
+    /// [`resolve`](Self::resolve) accepts), e.g. to preview an older commit.
+    ///
+    /// Tracked files missing from that commit are removed; untracked files
+    /// are left alone.  HEAD and the index do not move, so afterwards
+    /// [`status`](GitRepository::status) shows how the commit differs from
+    /// HEAD.  Like [`checkout`](Self::checkout), this fails with
+    /// [`GitError::Conflict`] rather than overwrite uncommitted changes, so
+    /// the preview itself blocks a second one; end it with a hard
+    /// [`reset`](Self::reset) to `HEAD`.
+    pub fn checkout_tree(&mut self, rev: &str) -> Result<(), GitError> {
+        let sha = self.resolve(rev)?;
+        let tree = self.find_commit(&sha)?.tree.clone();
+        self.refuse_to_overwrite(&tree)?;
+        let tracked: BTreeSet<&String> = self.head.keys().chain(self.index.keys()).collect();
+        let stale: Vec<String> =
+            tracked.into_iter().filter(|p| !tree.contains_key(*p)).cloned().collect();
+        self.replace_work_tree(&stale, &tree)
+    }
+
+    /// Move HEAD, and the branch it follows, to revision `rev` (anything
+    /// [`resolve`](Self::resolve) accepts), like `git reset`.  `mode` decides
+    /// whether the index and working tree follow.
+    ///
+    /// Passing `HEAD@{1}` undoes the previous HEAD movement, including an
+    /// earlier reset; see [`reflog`](Self::reflog).  A pending merge or
+    /// cherry-pick is forgotten, as with git.
+    pub fn reset(&mut self, rev: &str, mode: ResetMode) -> Result<(), GitError> {
+        self.require_commits()?;
+        let sha = self.resolve(rev)?;
+        let tree = self.find_commit(&sha)?.tree.clone();
+        self.pending = None;
+        let old = self.head_sha().map(str::to_string);
+        if mode == ResetMode::Hard {
+            let stale: Vec<String> = self
+                .head
+                .keys()
+                .chain(self.index.keys())
+                .filter(|p| !tree.contains_key(*p))
+                .cloned()
+                .collect();
+            self.replace_work_tree(&stale, &tree)?;
+        }
+        match &mut self.head_ref {
+            HeadRef::Branch(name) => {
+                self.branches.insert(name.clone(), sha);
+            }
+            HeadRef::Detached(head) => *head = sha,
+        }
+        self.head = tree.clone();
+        if mode == ResetMode::Soft {
+            let index = &self.index;
+            self.staged_deletions =
+                self.head.keys().filter(|p| !index.contains_key(*p)).cloned().collect();
         } else {
-            // File didn't exist in HEAD – remove from index entirely.
-            self.index.remove(path);
+            self.index = tree;
+            self.staged_deletions.clear();
         }
+        self.log_head(old, format!("reset: moving to {rev}"));
         Ok(())
     }
 
-    fn commit(&mut self, message: &str, author: &str) -> Result<String, GitError> {
-        if self.index == self.head {
-            return Err(GitError::NothingToCommit);
+    /// Return every recorded HEAD movement (commits, checkouts, resets and
+    /// commit rewrites), newest first.
+    pub fn reflog(&self) -> Vec<ReflogEntry> {
+        self.reflog.iter().rev().cloned().collect()
+    }
+
+    /// Stage every path in `paths`, or none of them: if any path is unknown
+    /// the index is left untouched and [`GitError::PathNotFound`] names the
+    /// first such path.
+    pub fn stage_files(&mut self, paths: &[&str]) -> Result<(), GitError> {
+        let work = self.working_tree();
+        let paths: Vec<String> = paths.iter().map(|p| normalise(p)).collect();
+        if let Some(missing) =
+            paths.iter().find(|p| !work.contains_key(*p) && !self.head.contains_key(*p))
+        {
+            return Err(GitError::PathNotFound(missing.to_string()));
         }
-        let sha = self.make_sha();
-        let commit = Commit {
-            sha: sha.clone(),
-            message: message.to_string(),
-            author: author.to_string(),
-            tree: self.index.clone(),
+        for path in paths {
+            self.stage_file(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Apply a unified diff to the index only, leaving the working tree
+    /// alone, e.g. to stage a hand-picked or hand-edited subset of changes
+    /// as `git add -p` does.
+    ///
+    /// `patch` is in `git diff` format: per file a `--- a/path` /
+    /// `+++ b/path` pair (`/dev/null` for an added or deleted file) followed
+    /// by `@@` hunks.  Every hunk must match the index exactly; otherwise
+    /// nothing is staged and [`GitError::Other`] names the file.
+    pub fn apply_patch_to_index(&mut self, patch: &str) -> Result<(), GitError> {
+        let mut staged: Vec<(String, Option<Vec<u8>>)> = Vec::new();
+        for file in parse_patch(patch)? {
+            let old = match &file.old_path {
+                Some(path) => {
+                    let id = self.index.get(path);
+                    let id = id.ok_or_else(|| GitError::PathNotFound(path.clone()))?;
+                    self.blobs[id].as_slice()
+                }
+                None => &[],
+            };
+            let path = file.new_path.as_ref().or(file.old_path.as_ref()).expect("parsed path");
+            if file.old_path.is_none() && self.index.contains_key(path) {
+                return Err(GitError::Other(format!("patch does not apply: {path} exists")));
+            }
+            let new = apply_hunks(old, &file.hunks)
+                .ok_or_else(|| GitError::Other(format!("patch does not apply: {path}")))?;
+            staged.push((path.clone(), file.new_path.is_some().then_some(new)));
+        }
+        for (path, content) in staged {
+            self.mark_resolved(&path);
+            match content {
+                Some(data) => {
+                    let id = self.write_blob(&data);
+                    self.index.insert(path.clone(), id);
+                    self.staged_deletions.remove(&path);
+                }
+                None => {
+                    self.index.remove(&path);
+                    if self.head.contains_key(&path) {
+                        self.staged_deletions.insert(path);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a commit with an explicit tree and parent list, bypassing the
+    /// index, and move HEAD (and the index) to it.  Returns the commit SHA.
+    ///
+    /// This is the low-level primitive behind [`GitRepository::commit`]; it
+    /// can also be used to build synthetic histories such as merge commits.
+    /// Every parent must name an existing commit and every blob must have
+    /// been stored with [`write_blob`](Self::write_blob).  The working tree
+    /// is not touched.
+    ///
+    /// On a branch, the branch moves to the new commit.  On a detached HEAD
+    /// only HEAD moves, so the commit is lost on the next checkout unless a
+    /// branch is created for it with [`create_branch`](Self::create_branch).
+    /// Fails while a merge or cherry-pick is pending.
+    pub fn commit_with(
+        &mut self,
+        tree: TreeSnapshot,
+        parents: Vec<String>,
+        message: &str,
+        author: &str,
+    ) -> Result<String, GitError> {
+        self.refuse_while_pending()?;
+        self.record_commit(tree, parents, message, author)
+    }
+
+    /// [`commit_with`](Self::commit_with) without the pending-operation
+    /// check, for concluding one.
+    fn record_commit(
+        &mut self,
+        tree: TreeSnapshot,
+        parents: Vec<String>,
+        message: &str,
+        author: &str,
+    ) -> Result<String, GitError> {
+        let tree = tree.into_iter().map(|(path, id)| (normalise(&path), id)).collect();
+        let old = self.head_sha().map(str::to_string);
+        let kind = match parents.len() {
+            0 => "commit (initial)",
+            1 => "commit",
+            _ => "commit (merge)",
         };
-        self.head = self.index.clone();
-        self.commits.push(commit);
+        let sha = self.write_commit(tree, parents, message, author)?;
+        let summary = message.lines().next().unwrap_or("");
+        self.log_head(old, format!("{kind}: {summary}"));
         Ok(sha)
     }
 
-    fn log(&self, max_count: usize) -> Result<Vec<CommitInfo>, GitError> {
-        let infos: Vec<CommitInfo> = self
+    /// Like [`GitRepository::commit`], but records the commit even when the
+    /// index matches HEAD (`git commit --allow-empty`).  This is how an empty
+    /// root commit is created.
+    ///
+    /// Either way, committing concludes a pending merge or cherry-pick; a
+    /// merge records the merged commit as the second parent.  While files
+    /// it left conflicted are not yet staged, [`GitError::Conflict`] lists
+    /// them and nothing is committed.
+    pub fn commit_allow_empty(&mut self, message: &str, author: &str) -> Result<String, GitError> {
+        self.require_resolved()?;
+        let mut parents: Vec<String> = self.head_sha().map(str::to_string).into_iter().collect();
+        if let Some(PendingOperation::Merge(theirs)) = self.pending_operation() {
+            parents.push(theirs.clone());
+        }
+        let sha = self.record_commit(self.index.clone(), parents, message, author)?;
+        self.pending = None;
+        Ok(sha)
+    }
+
+    /// Like [`GitRepository::commit`], but first checks that the summary
+    /// (first line) of `message` is no longer than the limit set with
+    /// [`set_summary_limit`](Self::set_summary_limit).
+    ///
+    /// An overlong summary is rejected with [`GitError::SummaryTooLong`] and
+    /// nothing is committed.
+    pub fn commit_validated(&mut self, message: &str, author: &str) -> Result<String, GitError> {
+        let summary = message.lines().next().unwrap_or("");
+        let len = summary.chars().count();
+        if len > self.summary_limit {
+            return Err(GitError::SummaryTooLong {
+                len,
+                limit: self.summary_limit,
+            });
+        }
+        self.commit(message, author)
+    }
+
+    /// Replace the HEAD commit with one recording the current index and
+    /// `message`, with the same parents (`git commit --amend`).  Returns the
+    /// new SHA.
+    ///
+    /// The original author is kept unless `options.reset_author` is set, in
+    /// which case `author` is recorded instead.  Fails while a merge or
+    /// cherry-pick is pending.
+    pub fn amend(
+        &mut self,
+        message: &str,
+        author: &str,
+        options: AmendOptions,
+    ) -> Result<String, GitError> {
+        self.require_commits()?;
+        self.refuse_while_pending()?;
+        let old = self
+            .head_sha()
+            .ok_or_else(|| GitError::InvalidRevision("HEAD".to_string()))?
+            .to_string();
+        let commit = self.find_commit(&old)?;
+        let parents = commit.parents.clone();
+        let author = if options.reset_author { author.to_string() } else { commit.author.clone() };
+        let sha = self.write_commit(self.index.clone(), parents, message, &author)?;
+        let summary = message.lines().next().unwrap_or("");
+        self.log_head(Some(old), format!("commit (amend): {summary}"));
+        Ok(sha)
+    }
+
+    /// Produce a unified diff between the trees of two commits
+    /// (`from` → `to`).
+    pub fn diff_range(&self, from: &str, to: &str) -> Result<Vec<FileDiff>, GitError> {
+        let old = &self.find_commit(from)?.tree;
+        let new = &self.find_commit(to)?.tree;
+        Ok(self.diff_trees(&self.tree_files(old), &self.tree_files(new)))
+    }
+
+    /// List the paths that differ between revisions `from` and `to`
+    /// (anything [`resolve`](Self::resolve) accepts) with their status, in
+    /// path order, e.g. for a PR-style summary.
+    ///
+    /// Only blob ids are compared, so no file contents are read or diffed;
+    /// use [`diff_range`](Self::diff_range) for the hunks.
+    pub fn changed_files(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<(String, FileStatus)>, GitError> {
+        let old = &self.find_commit(&self.resolve(from)?)?.tree;
+        let new = &self.find_commit(&self.resolve(to)?)?.tree;
+        let mut paths: BTreeSet<&String> = old.keys().collect();
+        paths.extend(new.keys());
+        Ok(paths
+            .into_iter()
+            .filter_map(|path| {
+                let status = match (old.get(path), new.get(path)) {
+                    (None, Some(_)) => FileStatus::Added,
+                    (Some(_), None) => FileStatus::Deleted,
+                    (Some(a), Some(b)) if a != b => FileStatus::Modified,
+                    _ => return None,
+                };
+                Some((path.clone(), status))
+            })
+            .collect())
+    }
+
+    /// List the paths whose working-tree contents differ from revision
+    /// `base` (anything [`resolve`](Self::resolve) accepts), in path order:
+    /// the net changes of a branch when `base` is its
+    /// [`merge_base`](Self::merge_base) with the parent branch, committed or
+    /// not.
+    ///
+    /// Files absent from `base` are `Added` when tracked and `Untracked`
+    /// otherwise; the index only decides which files count as tracked.
+    pub fn status_against(&self, base: &str) -> Result<Vec<(String, FileStatus)>, GitError> {
+        let tree = &self.find_commit(&self.resolve(base)?)?.tree;
+        let work = self.working_tree();
+        let mut paths: BTreeSet<&String> = tree.keys().collect();
+        paths.extend(work.keys());
+        Ok(paths
+            .into_iter()
+            .filter_map(|path| {
+                let status = match (tree.get(path), work.get(path)) {
+                    (None, Some(_)) if self.is_tracked(path) => FileStatus::Added,
+                    (None, Some(_)) => FileStatus::Untracked,
+                    (Some(_), None) => FileStatus::Deleted,
+                    (Some(id), Some(data)) if self.blobs[id] != *data => FileStatus::Modified,
+                    _ => return None,
+                };
+                Some((path.clone(), status))
+            })
+            .collect())
+    }
+
+    /// Produce a unified diff from the tree of revision `rev` (anything
+    /// [`resolve`](Self::resolve) accepts) to the working tree, e.g. "what
+    /// changed since v1.0".  The index is not consulted.
+    pub fn diff_working_vs_commit(&self, rev: &str) -> Result<Vec<FileDiff>, GitError> {
+        let tree = &self.find_commit(&self.resolve(rev)?)?.tree;
+        let work = self.working_tree();
+        Ok(self.diff_trees(&self.tree_files(tree), &work_files(&work)))
+    }
+
+    /// Resolve a revision to a commit SHA.
+    ///
+    /// Accepts `HEAD`, `HEAD@{n}` (where HEAD was `n` movements ago, per the
+    /// [`reflog`](Self::reflog)), a branch name, a tag name or a full SHA,
+    /// optionally followed by any number of `~n` (the `n`th first-parent
+    /// ancestor) and `^` (the first parent) suffixes, as in `v1.0~2` or
+    /// `HEAD^^`.
+    pub fn resolve(&self, rev: &str) -> Result<String, GitError> {
+        let invalid = || GitError::InvalidRevision(rev.to_string());
+        let (name, mut suffix) = rev.split_at(rev.find(['~', '^']).unwrap_or(rev.len()));
+        let mut sha = if name == "HEAD" {
+            self.head_sha().ok_or_else(invalid)?.to_string()
+        } else if let Some(n) = name.strip_prefix("HEAD@{").and_then(|n| n.strip_suffix('}')) {
+            let n: usize = n.parse().map_err(|_| invalid())?;
+            self.reflog.iter().rev().nth(n).ok_or_else(invalid)?.new.clone()
+        } else if let Some(sha) = self.branches.get(name).or_else(|| self.tags.get(name)) {
+            sha.clone()
+        } else {
+            self.find_commit(name)?.sha.clone()
+        };
+        while let Some(op) = suffix.chars().next() {
+            suffix = &suffix[1..];
+            let digits = suffix.find(|c: char| !c.is_ascii_digit()).unwrap_or(suffix.len());
+            let steps = match (op, &suffix[..digits]) {
+                ('~', "") => 1,
+                ('~', n) => n.parse().map_err(|_| invalid())?,
+                ('^', "") => 1,
+                _ => return Err(invalid()),
+            };
+            suffix = &suffix[digits..];
+            for _ in 0..steps {
+                sha = self.find_commit(&sha)?.parents.first().ok_or_else(invalid)?.clone();
+            }
+        }
+        Ok(sha)
+    }
+
+    /// Find the best common ancestor of commits `a` and `b`, as used for a
+    /// three-way merge base.  Returns `None` when the histories are unrelated.
+    ///
+    /// A commit counts as its own ancestor, so the base of a commit and one of
+    /// its descendants is the commit itself.  When several common ancestors
+    /// are equally good (a criss-cross history), the most recently created
+    /// one is returned.
+    pub fn merge_base(&self, a: &str, b: &str) -> Result<Option<String>, GitError> {
+        let ours = self.ancestors(a)?;
+        let theirs = self.ancestors(b)?;
+        // Parents always predate their children, so the newest common
+        // ancestor cannot be an ancestor of any other common ancestor.
+        Ok(self
             .commits
             .iter()
             .rev()
-            .take(max_count)
-            .map(|c| {
-                let short = if c.sha.len() >= 7 {
-                    c.sha[..7].to_string()
-                } else {
-                    c.sha.clone()
-                };
-                CommitInfo {
-                    sha: c.sha.clone(),
-                    short_sha: short,
-                    summary: c.message.lines().next().unwrap_or("").to_string(),
-                    author: c.author.clone(),
-                }
-            })
-            .collect();
-        Ok(infos)
+            .find(|c| ours.contains(c.sha.as_str()) && theirs.contains(c.sha.as_str()))
+            .map(|c| c.sha.clone()))
     }
-}
 
-// ── Diff helpers ─────────────────────────────────────────────────────────────
+    /// Preview [`GitRepository::commit`]: the staged changes it would
+    /// record.  Fails with [`GitError::NothingToCommit`] exactly when the
+    /// commit would.  Nothing is modified.
+    pub fn plan_commit(&self) -> Result<OperationPlan, GitError> {
+        if self.index == self.head && self.staged_deletions.is_empty() {
+            return Err(GitError::NothingToCommit);
+        }
+        Ok(OperationPlan {
+            diffs: self.diff_staged()?,
+            conflicts: Vec::new(),
+        })
+    }
 
-/// Produce hunks for a newly-added file (all lines are `+`).
-///
-/// Each line includes a trailing `\n` to match the unified-diff format
-/// expected by consumers such as *hunky*.
-fn diff_added(content: &str) -> Vec<DiffHunk> {
-    let lines: Vec<String> = content.lines().map(|l| format!("+{l}\n")).collect();
-    if lines.is_empty() {
-        return Vec::new();
+    /// Preview merging revision `rev` into HEAD: the changes made on `rev`
+    /// since the [`merge_base`](Self::merge_base) are applied to the HEAD
+    /// tree file by file, and files both sides changed differently are
+    /// reported as conflicts.  Nothing is modified.
+    pub fn plan_merge(&self, rev: &str) -> Result<OperationPlan, GitError> {
+        let (base, theirs) = self.merge_sides(&self.resolve(rev)?)?;
+        Ok(self.plan_changes(&base, theirs))
     }
-    vec![DiffHunk {
-        old_start: 0,
-        new_start: 1,
-        lines,
-    }]
-}
 
-/// Produce hunks for a deleted file (all lines are `-`).
-///
-/// Each line includes a trailing `\n` to match the unified-diff format
-/// expected by consumers such as *hunky*.
-fn diff_deleted(content: &str) -> Vec<DiffHunk> {
-    let lines: Vec<String> = content.lines().map(|l| format!("-{l}\n")).collect();
-    if lines.is_empty() {
-        return Vec::new();
+    /// Preview cherry-picking revision `rev` onto HEAD: the changes it made
+    /// relative to its first parent, applied as in
+    /// [`plan_merge`](Self::plan_merge).  Nothing is modified.
+    pub fn plan_cherry_pick(&self, rev: &str) -> Result<OperationPlan, GitError> {
+        let (before, after) = self.pick_sides(&self.resolve(rev)?)?;
+        Ok(self.plan_changes(&before, after))
     }
-    vec![DiffHunk {
-        old_start: 1,
-        new_start: 0,
-        lines,
-    }]
-}
 
-/// Produce hunks for a modified file using a simple LCS-based line diff.
-fn diff_modified(old: &str, new: &str) -> Vec<DiffHunk> {
-    let old_lines: Vec<&str> = old.lines().collect();
-    let new_lines: Vec<&str> = new.lines().collect();
+    /// Start merging revision `rev` into HEAD: the changes
+    /// [`plan_merge`](Self::plan_merge) previews are staged and written to
+    /// the working tree, and each conflicting file is written with
+    /// `<<<<<<<`/`=======`/`>>>>>>>` markers around the two whole versions
+    /// and left unstaged.  Returns the plan.
+    ///
+    /// Like git, nothing is changed and [`GitError::Conflict`] lists the
+    /// paths when the merge would overwrite uncommitted changes or an
+    /// untracked file, ignored or not.
+    ///
+    /// The merge stays [pending](Self::pending_operation) until the next
+    /// commit, which records `rev` as a second parent and is refused until
+    /// every conflicted file has been staged, or until
+    /// [`abort_merge`](Self::abort_merge) puts the index and the files it
+    /// wrote back as they were.
+    pub fn start_merge(&mut self, rev: &str) -> Result<OperationPlan, GitError> {
+        let theirs = self.resolve(rev)?;
+        let (base, after) = self.merge_sides(&theirs)?;
+        let after = after.clone();
+        self.start_operation(PendingOperation::Merge(theirs), rev, &base, &after)
+    }
 
-    let edit_script = lcs_diff(&old_lines, &new_lines);
+    /// Start cherry-picking revision `rev` onto HEAD, applying the changes
+    /// [`plan_cherry_pick`](Self::plan_cherry_pick) previews as
+    /// [`start_merge`](Self::start_merge) does.  The next commit concludes
+    /// it; [`abort_cherry_pick`](Self::abort_cherry_pick) undoes it.
+    pub fn start_cherry_pick(&mut self, rev: &str) -> Result<OperationPlan, GitError> {
+        let sha = self.resolve(rev)?;
+        let (before, after) = self.pick_sides(&sha)?;
+        let after = after.clone();
+        let label = short_sha(&sha).to_string();
+        self.start_operation(PendingOperation::CherryPick(sha), &label, &before, &after)
+    }
 
-    // Group consecutive edits into hunks with up to 3 context lines.
-    let context = 3;
-    let mut hunks: Vec<DiffHunk> = Vec::new();
-    let mut i = 0;
+    /// The merge or cherry-pick waiting to be committed or aborted, if any.
+    pub fn pending_operation(&self) -> Option<&PendingOperation> {
+        self.pending.as_ref().map(|p| &p.operation)
+    }
 
-    while i < edit_script.len() {
-        // Skip leading context lines until we hit a change.
-        if matches!(edit_script[i], Edit::Equal(_, _)) {
-            i += 1;
-            continue;
-        }
+    /// Abandon the pending merge, restoring the index and every file the
+    /// merge wrote to their state before [`start_merge`](Self::start_merge).
+    /// Fails with [`GitError::Other`] when no merge is pending.
+    pub fn abort_merge(&mut self) -> Result<(), GitError> {
+        self.abort_pending("merge", |op| matches!(op, PendingOperation::Merge(_)))
+    }
 
-        // Find the start of the change region with context.
-        let change_start = i;
+    /// Abandon the pending cherry-pick, like [`abort_merge`](Self::abort_merge).
+    pub fn abort_cherry_pick(&mut self) -> Result<(), GitError> {
+        self.abort_pending("cherry-pick", |op| matches!(op, PendingOperation::CherryPick(_)))
+    }
 
-        // Walk backwards to include up to `context` preceding Equal lines.
-        let ctx_before_start = {
-            let mut s = change_start;
-            let mut ctx = 0;
-            while s > 0 && ctx < context {
-                if matches!(edit_script[s - 1], Edit::Equal(_, _)) {
-                    s -= 1;
-                    ctx += 1;
-                } else {
-                    break;
+    /// Iterate over the commits reachable from HEAD, newest first, in the
+    /// order [`log`](GitRepository::log) returns them.
+    ///
+    /// Commits are found lazily, so a viewport can `skip` and `take` just
+    /// the rows it shows without building the whole history.
+    pub fn commits_iter(&self) -> impl Iterator<Item = CommitInfo> + '_ {
+        // Parents are always recorded before their children, so a single
+        // newest-to-oldest pass meets every wanted commit after its child.
+        let wanted: BTreeSet<&str> = self.head_sha().into_iter().collect();
+        self.commits
+            .iter()
+            .rev()
+            .scan(wanted, |wanted, c| {
+                if wanted.is_empty() {
+                    return None;
+                }
+                let reachable = wanted.remove(c.sha.as_str());
+                if reachable {
+                    wanted.extend(c.parents.iter().map(String::as_str));
                 }
+                Some(reachable.then_some(c))
+            })
+            .flatten()
+            .map(|c| CommitInfo {
+                sha: c.sha.clone(),
+                short_sha: short_sha(&c.sha).to_string(),
+                summary: c.message.lines().next().unwrap_or("").to_string(),
+                author: c.author.clone(),
+            })
+    }
+
+    /// Count the commits reachable from HEAD per author, most prolific
+    /// first (ties broken by name), like `git shortlog -sn`.
+    pub fn shortlog(&self) -> Vec<(String, usize)> {
+        let reachable = match self.head_sha() {
+            Some(sha) => self.ancestors(sha).unwrap_or_default(),
+            None => BTreeSet::new(),
+        };
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for commit in self.commits.iter().filter(|c| reachable.contains(c.sha.as_str())) {
+            *counts.entry(&commit.author).or_default() += 1;
+        }
+        let mut authors: Vec<(String, usize)> =
+            counts.into_iter().map(|(author, n)| (author.to_string(), n)).collect();
+        // Stable, so equal counts stay in name order.
+        authors.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+        authors
+    }
+
+    /// Rewrite the newest commits on HEAD's first-parent line in the order
+    /// given by `order` (oldest first), like reordering lines in
+    /// `git rebase -i`.  Returns the new HEAD SHA.
+    ///
+    /// `order` must be a permutation of the `order.len()` newest commits,
+    /// none of them a merge.  Each commit's changes are replayed file by
+    /// file; if a file it changes was changed differently by a commit now
+    /// replayed before it, nothing is rewritten and
+    /// [`GitError::Conflict`] lists the files.  Fails while a merge or
+    /// cherry-pick is pending.
+    pub fn reorder_commits(&mut self, order: &[&str]) -> Result<String, GitError> {
+        self.reorder_commits_with_progress(order, |_| {})
+    }
+
+    /// Like [`reorder_commits`](Self::reorder_commits), calling `progress`
+    /// after each commit is rewritten.
+    pub fn reorder_commits_with_progress(
+        &mut self,
+        order: &[&str],
+        mut progress: impl FnMut(Progress),
+    ) -> Result<String, GitError> {
+        self.refuse_while_pending()?;
+        // Collect the commits being rewritten, newest first.
+        let mut newest = Vec::new();
+        let mut next = self.head_sha().map(str::to_string);
+        while newest.len() < order.len() {
+            let sha = next.ok_or_else(|| GitError::Other("not enough commits".to_string()))?;
+            let commit = self.find_commit(&sha)?;
+            if commit.parents.len() > 1 {
+                return Err(GitError::Other(format!("cannot reorder merge commit {sha}")));
             }
-            s
+            next = commit.parents.first().cloned();
+            newest.push(sha);
+        }
+        for sha in order {
+            if !newest.iter().any(|n| n == sha) {
+                return Err(GitError::InvalidRevision(sha.to_string()));
+            }
+        }
+        if order.iter().collect::<BTreeSet<_>>().len() != order.len() {
+            return Err(GitError::Other("commit listed twice".to_string()));
+        }
+
+        // Replay every commit's changes onto the base, checking for clashes
+        // before anything is written.
+        let base = next;
+        let mut tree = match &base {
+            Some(sha) => self.find_commit(sha)?.tree.clone(),
+            None => TreeSnapshot::new(),
         };
+        let mut rewritten = Vec::new();
+        for sha in order {
+            let commit = self.find_commit(sha)?;
+            let before = match commit.parents.first() {
+                Some(parent) => &self.find_commit(parent)?.tree,
+                None => &TreeSnapshot::new(),
+            };
+            let conflicts = apply_tree_changes(&mut tree, before, &commit.tree);
+            if !conflicts.is_empty() {
+                return Err(GitError::Conflict(conflicts));
+            }
+            rewritten.push((tree.clone(), commit.message.clone(), commit.author.clone()));
+        }
 
-        // Find end of this change group (including bridged gaps).
-        let mut change_end = change_start;
-        while change_end < edit_script.len() {
-            if matches!(edit_script[change_end], Edit::Equal(_, _)) {
-                // Count how many equal lines follow.
-                let mut eq_count = 0;
-                let mut j = change_end;
-                while j < edit_script.len() && matches!(edit_script[j], Edit::Equal(_, _)) {
-                    eq_count += 1;
-                    j += 1;
+        // Committing resets the index, so carry any staged work across.
+        let (index, deletions) = (self.index.clone(), self.staged_deletions.clone());
+        let old = self.head_sha().map(str::to_string);
+        let total = rewritten.len();
+        let mut parent = base;
+        for (done, (tree, message, author)) in rewritten.into_iter().enumerate() {
+            let sha = self.write_commit(tree, parent.into_iter().collect(), &message, &author)?;
+            parent = Some(sha);
+            progress(Progress { done: done + 1, total });
+        }
+        self.index = index;
+        self.staged_deletions = deletions;
+        self.log_head(old, format!("rebase: reorder {total} commits"));
+        parent.ok_or_else(|| GitError::Other("no commits to reorder".to_string()))
+    }
+
+    // ── internal helpers ─────────────────────────────────────────────────
+
+    /// [`commit_with`](Self::commit_with) without the reflog entry.
+    fn write_commit(
+        &mut self,
+        tree: TreeSnapshot,
+        parents: Vec<String>,
+        message: &str,
+        author: &str,
+    ) -> Result<String, GitError> {
+        for parent in &parents {
+            self.find_commit(parent)?;
+        }
+        if let Some((path, _)) = tree.iter().find(|(_, id)| !self.blobs.contains_key(id)) {
+            return Err(GitError::Other(format!("no blob stored for {path}")));
+        }
+        let author = match author.trim() {
+            "" => self.require_identity()?.to_string(),
+            _ => author.to_string(),
+        };
+        let sha = self.make_sha();
+        self.commits.push(Commit {
+            sha: sha.clone(),
+            parents,
+            message: message.to_string(),
+            author,
+            tree: tree.clone(),
+        });
+        match &mut self.head_ref {
+            HeadRef::Branch(name) => {
+                self.branches.insert(name.clone(), sha.clone());
+            }
+            HeadRef::Detached(head) => *head = sha.clone(),
+        }
+        self.head = tree.clone();
+        self.index = tree;
+        self.staged_deletions.clear();
+        Ok(sha)
+    }
+
+    /// The trees a merge of commit `theirs` into HEAD applies the changes
+    /// between: the merge base (empty for unrelated histories) and `theirs`.
+    fn merge_sides(&self, theirs: &str) -> Result<(TreeSnapshot, &TreeSnapshot), GitError> {
+        let head = self
+            .head_sha()
+            .ok_or_else(|| GitError::InvalidRevision("HEAD".to_string()))?;
+        let base = match self.merge_base(head, theirs)? {
+            Some(sha) => self.find_commit(&sha)?.tree.clone(),
+            None => TreeSnapshot::new(),
+        };
+        Ok((base, &self.find_commit(theirs)?.tree))
+    }
+
+    /// The trees a cherry-pick of commit `sha` applies the changes between:
+    /// its first parent's (empty for a root commit) and its own.
+    fn pick_sides(&self, sha: &str) -> Result<(TreeSnapshot, &TreeSnapshot), GitError> {
+        let commit = self.find_commit(sha)?;
+        let before = match commit.parents.first() {
+            Some(parent) => self.find_commit(parent)?.tree.clone(),
+            None => TreeSnapshot::new(),
+        };
+        Ok((before, &commit.tree))
+    }
+
+    /// Apply the changes from `before` to `after` to the index and working
+    /// tree, writing conflict markers labelled `label` for clashes, and
+    /// record `operation` as pending.
+    fn start_operation(
+        &mut self,
+        operation: PendingOperation,
+        label: &str,
+        before: &TreeSnapshot,
+        after: &TreeSnapshot,
+    ) -> Result<OperationPlan, GitError> {
+        self.refuse_while_pending()?;
+        let plan = self.plan_changes(before, after);
+        let mut tree = self.head.clone();
+        apply_tree_changes(&mut tree, before, after);
+
+        // Like git, refuse to overwrite uncommitted changes or untracked
+        // (even ignored) files, which aborting could not bring back.
+        let work = self.working_tree();
+        let touched: Vec<&String> =
+            plan.diffs.iter().map(|d| &d.path).chain(&plan.conflicts).collect();
+        let blocked: Vec<String> = touched
+            .iter()
+            .filter(|path| {
+                let staged = self.index.get(**path);
+                if staged.is_none() && !self.head.contains_key(**path) {
+                    return self.fs.is_file(path);
                 }
-                // If the gap is small enough and there are more changes after,
-                // merge them into the same hunk.
-                if eq_count <= context * 2 && j < edit_script.len() {
-                    change_end = j;
-                } else {
-                    break;
+                staged != self.head.get(**path)
+                    || staged.map(|id| &self.blobs[id]) != work.get(**path)
+            })
+            .map(|path| path.to_string())
+            .collect();
+        if !blocked.is_empty() {
+            return Err(GitError::Conflict(blocked));
+        }
+
+        // Save the raw bytes, before any line-ending conversion.
+        let saved = touched
+            .into_iter()
+            .map(|path| (path.clone(), self.fs.read_file(path).ok()))
+            .collect();
+        self.pending = Some(Pending {
+            operation,
+            index: self.index.clone(),
+            staged_deletions: self.staged_deletions.clone(),
+            work: saved,
+            conflicts: plan.conflicts.iter().cloned().collect(),
+        });
+
+        let mut written = TreeSnapshot::new();
+        let mut removed = Vec::new();
+        for diff in &plan.diffs {
+            let path = &diff.path;
+            match tree.get(path) {
+                Some(&id) => {
+                    self.index.insert(path.clone(), id);
+                    self.staged_deletions.remove(path);
+                    written.insert(path.clone(), id);
+                }
+                None => {
+                    self.index.remove(path);
+                    if self.head.contains_key(path) {
+                        self.staged_deletions.insert(path.clone());
+                    }
+                    removed.push(path.clone());
                 }
-            } else {
-                change_end += 1;
             }
         }
+        self.replace_work_tree(&removed, &written)?;
 
-        // Include up to `context` trailing Equal lines.
-        let ctx_after_end = {
-            let mut e = change_end;
-            let mut ctx = 0;
-            while e < edit_script.len() && ctx < context {
-                if matches!(edit_script[e], Edit::Equal(_, _)) {
-                    e += 1;
-                    ctx += 1;
-                } else {
-                    break;
+        let fs_err = |e: crate::fs::FsError| GitError::Other(e.to_string());
+        for path in &plan.conflicts {
+            let side = |tree: &TreeSnapshot| {
+                let mut text = tree
+                    .get(path)
+                    .map(|id| String::from_utf8_lossy(&self.blobs[id]).into_owned())
+                    .unwrap_or_default();
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
                 }
+                text
+            };
+            let marked = format!(
+                "<<<<<<< HEAD\n{}=======\n{}>>>>>>> {label}\n",
+                side(&self.head),
+                side(after)
+            );
+            if let Some((parent, _)) = path.rsplit_once('/') {
+                self.fs.create_dir_all(parent).map_err(fs_err)?;
             }
-            e
+            self.fs.write_file(path, marked.as_bytes()).map_err(fs_err)?;
+        }
+        Ok(plan)
+    }
+
+    /// Fail with [`GitError::Other`] while a merge or cherry-pick is
+    /// pending, for operations that would move HEAD under it.
+    fn refuse_while_pending(&self) -> Result<(), GitError> {
+        let what = match self.pending_operation() {
+            None => return Ok(()),
+            Some(PendingOperation::Merge(_)) => "merge",
+            Some(PendingOperation::CherryPick(_)) => "cherry-pick",
         };
+        Err(GitError::Other(format!("a {what} is in progress")))
+    }
+
+    /// Fail with [`GitError::Conflict`] while the pending operation has
+    /// conflicted paths that are not staged yet.
+    fn require_resolved(&self) -> Result<(), GitError> {
+        match &self.pending {
+            Some(pending) if !pending.conflicts.is_empty() => {
+                Err(GitError::Conflict(pending.conflicts.iter().cloned().collect()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Record that `path` was staged, resolving it if the pending operation
+    /// left it conflicted.
+    fn mark_resolved(&mut self, path: &str) {
+        if let Some(pending) = &mut self.pending {
+            pending.conflicts.remove(path);
+        }
+    }
+
+    /// Undo the pending operation when `is_kind` accepts it (`kind` names
+    /// it in the error otherwise).
+    fn abort_pending(
+        &mut self,
+        kind: &str,
+        is_kind: fn(&PendingOperation) -> bool,
+    ) -> Result<(), GitError> {
+        let pending = match self.pending.take() {
+            Some(pending) if is_kind(&pending.operation) => pending,
+            other => {
+                self.pending = other;
+                return Err(GitError::Other(format!("no {kind} in progress")));
+            }
+        };
+        self.index = pending.index;
+        self.staged_deletions = pending.staged_deletions;
+        let fs_err = |e: crate::fs::FsError| GitError::Other(e.to_string());
+        for (path, data) in pending.work {
+            match data {
+                Some(data) => {
+                    if let Some((parent, _)) = path.rsplit_once('/') {
+                        self.fs.create_dir_all(parent).map_err(fs_err)?;
+                    }
+                    self.fs.write_file(&path, &data).map_err(fs_err)?;
+                }
+                None if self.fs.is_file(&path) => self.fs.remove_file(&path).map_err(fs_err)?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the changes from `before` to `after` onto the HEAD tree and
+    /// describe the result.
+    fn plan_changes(&self, before: &TreeSnapshot, after: &TreeSnapshot) -> OperationPlan {
+        let mut tree = self.head.clone();
+        let conflicts = apply_tree_changes(&mut tree, before, after);
+        OperationPlan {
+            diffs: self.diff_trees(&self.tree_files(&self.head), &self.tree_files(&tree)),
+            conflicts,
+        }
+    }
+
+    /// Record that HEAD moved from `old` to the commit it now resolves to.
+    fn log_head(&mut self, old: Option<String>, action: String) {
+        if let Some(new) = self.head_sha() {
+            let new = new.to_string();
+            self.reflog.push(ReflogEntry { old, new, action });
+        }
+    }
+
+    /// How HEAD is named in reflog messages: its branch, or its SHA when
+    /// detached.
+    fn head_name(&self) -> &str {
+        match &self.head_ref {
+            HeadRef::Branch(name) => name,
+            HeadRef::Detached(sha) => sha,
+        }
+    }
+
+    /// Delete the `stale` paths from the working tree, then write out every
+    /// file in `tree`.
+    fn replace_work_tree(&mut self, stale: &[String], tree: &TreeSnapshot) -> Result<(), GitError> {
+        let fs_err = |e: crate::fs::FsError| GitError::Other(e.to_string());
+        for path in stale {
+            if self.fs.is_file(path) {
+                self.fs.remove_file(path).map_err(fs_err)?;
+            }
+        }
+        for (path, id) in tree {
+            if let Some((parent, _)) = path.rsplit_once('/') {
+                self.fs.create_dir_all(parent).map_err(fs_err)?;
+            }
+            let data = &self.blobs[id];
+            match self.autocrlf {
+                AutoCrlf::True => self.fs.write_file(path, &lf_to_crlf(data)),
+                AutoCrlf::False | AutoCrlf::Input => self.fs.write_file(path, data),
+            }
+            .map_err(fs_err)?;
+        }
+        Ok(())
+    }
+
+    /// SHAs of `sha` and every commit reachable through its parents.
+    fn ancestors(&self, sha: &str) -> Result<BTreeSet<&str>, GitError> {
+        let mut seen = BTreeSet::new();
+        let mut pending = vec![self.find_commit(sha)?];
+        while let Some(commit) = pending.pop() {
+            if seen.insert(commit.sha.as_str()) {
+                for parent in &commit.parents {
+                    pending.push(self.find_commit(parent)?);
+                }
+            }
+        }
+        Ok(seen)
+    }
+
+    /// The id `data` has, or would get, in the blob store, and whether it is
+    /// already stored.  Ids start at the FNV-1a hash of the contents and are
+    /// probed linearly past collisions.
+    fn find_blob(&self, data: &[u8]) -> (BlobId, bool) {
+        let mut id = data.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+        loop {
+            match self.blobs.get(&id) {
+                Some(stored) if stored == data => return (id, true),
+                Some(_) => id = id.wrapping_add(1),
+                None => return (id, false),
+            }
+        }
+    }
+
+    /// Resolve `tree`'s blob ids to their contents.
+    fn tree_files<'a>(&'a self, tree: &'a TreeSnapshot) -> Files<'a> {
+        tree.iter().map(|(path, id)| (path.as_str(), self.blobs[id].as_slice())).collect()
+    }
+
+    /// Look up a commit by its full SHA.
+    fn find_commit(&self, sha: &str) -> Result<&Commit, GitError> {
+        if sha.is_empty() || !sha.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(GitError::InvalidRevision(sha.to_string()));
+        }
+        self.commits
+            .iter()
+            .find(|c| c.sha == sha)
+            .ok_or_else(|| GitError::CommitNotFound(sha.to_string()))
+    }
+
+    /// Generate a deterministic hex-string identifier.
+    fn make_sha(&mut self) -> String {
+        let id = self.next_id;
+        self.next_id += 1;
+        format!("{id:016x}")
+    }
+
+    /// Snapshot of the current working tree, with line endings normalised
+    /// according to [`AutoCrlf`].
+    ///
+    /// The snapshot is cached against [`MemoryFilesystem::revision`], so
+    /// repeated calls between filesystem mutations (e.g. `status` followed by
+    /// several diffs in one frame) read the files only once.  It is rebuilt
+    /// as well when staging or committing changes whether an ignored path
+    /// is tracked.
+    fn working_tree(&self) -> Rc<WorkTree> {
+        let revision = self.fs.revision();
+        if let Some(cache) = &*self.work_cache.borrow() {
+            let valid = cache.revision == revision
+                && cache.ignored.iter().all(|(path, tracked)| self.is_tracked(path) == *tracked)
+                && cache.pruned.iter().all(|dir| !self.tracks_under(dir));
+            if valid {
+                return Rc::clone(&cache.tree);
+            }
+        }
+
+        let rules = match self.fs.read_file(".gitignore") {
+            Ok(data) => IgnoreRules::parse(&String::from_utf8_lossy(&data)),
+            Err(_) => IgnoreRules::default(),
+        };
+        let mut tree = BTreeMap::new();
+        let mut ignored = Vec::new();
+        let pruned = self.walk_work_files(&rules, |path, data| {
+            if rules.ignored_dir(path).is_some() || rules.matches(path, false) {
+                let tracked = self.is_tracked(path);
+                ignored.push((path.to_string(), tracked));
+                if !tracked {
+                    return;
+                }
+            }
+            let data = match self.autocrlf {
+                AutoCrlf::False => data.to_vec(),
+                AutoCrlf::Input | AutoCrlf::True => crlf_to_lf(data.to_vec()),
+            };
+            tree.insert(path.to_string(), data);
+        });
+        let tree = Rc::new(tree);
+        *self.work_cache.borrow_mut() =
+            Some(WorkCache { revision, tree: Rc::clone(&tree), ignored, pruned });
+        tree
+    }
+
+    /// Call `visit` with every working-tree file in path order, except those
+    /// under an ignored directory with no tracked files, which is skipped
+    /// without listing its contents.  Returns the skipped directories.
+    fn walk_work_files(
+        &self,
+        rules: &IgnoreRules,
+        mut visit: impl FnMut(&str, &[u8]),
+    ) -> Vec<String> {
+        let mut pruned = Vec::new();
+        let mut start = String::new();
+        loop {
+            let mut resume = None;
+            for (path, data) in self.fs.files_from(&start) {
+                if let Some(dir) = rules.ignored_dir(path).filter(|dir| !self.tracks_under(dir)) {
+                    // `0` follows `/`, so this is the first path past `dir/`.
+                    resume = Some(format!("{dir}0"));
+                    pruned.push(dir.to_string());
+                    break;
+                }
+                visit(path, data);
+            }
+            match resume {
+                Some(next) => start = next,
+                None => return pruned,
+            }
+        }
+    }
+
+    /// `true` when the index or HEAD tracks a file under directory `dir`.
+    fn tracks_under(&self, dir: &str) -> bool {
+        let prefix = format!("{dir}/");
+        [&self.index, &self.head].iter().any(|tree| {
+            tree.range(prefix.clone()..).next().is_some_and(|(p, _)| p.starts_with(&prefix))
+        })
+    }
+
+    /// Compute the unified diff between two snapshots.
+    fn diff_trees(&self, old: &Files, new: &Files) -> Vec<FileDiff> {
+        let mut diffs = Vec::new();
+        let mut all_paths: std::collections::BTreeSet<&str> = std::collections::BTreeSet::new();
+        all_paths.extend(old.keys());
+        all_paths.extend(new.keys());
+
+        for path in all_paths {
+            let old_content = old.get(path);
+            let new_content = new.get(path);
+
+            match (old_content, new_content) {
+                (None, Some(new_data)) => {
+                    // Added file.
+                    let new_str = String::from_utf8_lossy(new_data);
+                    let hunks = diff_added(&new_str);
+                    diffs.push(FileDiff {
+                        path: path.to_string(),
+                        status: FileStatus::Added,
+                        hunks,
+                    });
+                }
+                (Some(old_data), None) => {
+                    // Deleted file.
+                    let old_str = String::from_utf8_lossy(old_data);
+                    let hunks = diff_deleted(&old_str);
+                    diffs.push(FileDiff {
+                        path: path.to_string(),
+                        status: FileStatus::Deleted,
+                        hunks,
+                    });
+                }
+                (Some(old_data), Some(new_data)) => {
+                    if old_data != new_data {
+                        let old_str = String::from_utf8_lossy(old_data);
+                        let new_str = String::from_utf8_lossy(new_data);
+                        let lines = old_str.lines().count().max(new_str.lines().count());
+                        let hunks = match self.diff_options.max_lines {
+                            Some(max) if lines > max => vec![DiffHunk {
+                                old_start: 1,
+                                new_start: 1,
+                                lines: vec![TOO_LARGE_TO_DIFF.to_string()],
+                            }],
+                            _ => diff_modified(&old_str, &new_str, self.diff_options.algorithm),
+                        };
+                        diffs.push(FileDiff {
+                            path: path.to_string(),
+                            status: FileStatus::Modified,
+                            hunks,
+                        });
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+
+        self.diff_options.apply(&mut diffs);
+        diffs
+    }
+}
+
+impl GitRepository for InMemoryGitRepository {
+    fn status(&self) -> Result<Vec<StatusEntry>, GitError> {
+        let work = self.working_tree();
+        let mut entries = Vec::new();
+
+        // Gather all known paths.
+        let mut all_paths: std::collections::BTreeSet<&String> =
+            std::collections::BTreeSet::new();
+        all_paths.extend(self.head.keys());
+        all_paths.extend(self.index.keys());
+        all_paths.extend(work.keys());
+
+        for path in all_paths {
+            let in_head = self.head.contains_key(path);
+            let in_index = self.index.contains_key(path);
+            let in_work = work.contains_key(path);
+            let tombstoned = self.staged_deletions.contains(path);
+
+            // Staged changes (HEAD → index).
+            match (in_head, in_index) {
+                _ if tombstoned => entries.push(StatusEntry {
+                    path: path.clone(),
+                    status: FileStatus::Deleted,
+                    staged: true,
+                }),
+                (false, true) => entries.push(StatusEntry {
+                    path: path.clone(),
+                    status: FileStatus::Added,
+                    staged: true,
+                }),
+                (true, true) if self.head.get(path) != self.index.get(path) => {
+                    entries.push(StatusEntry {
+                        path: path.clone(),
+                        status: FileStatus::Modified,
+                        staged: true,
+                    });
+                }
+                _ => {}
+            }
+
+            // Unstaged changes (index → working tree).  A path staged for
+            // deletion that still exists on disk is untracked again.
+            match (self.index.get(path), in_work) {
+                (None, true) => {
+                    entries.push(StatusEntry {
+                        path: path.clone(),
+                        status: FileStatus::Untracked,
+                        staged: false,
+                    });
+                }
+                (Some(base), true) if Some(&self.blobs[base]) != work.get(path) => {
+                    entries.push(StatusEntry {
+                        path: path.clone(),
+                        status: FileStatus::Modified,
+                        staged: false,
+                    });
+                }
+                (Some(_), false) => {
+                    entries.push(StatusEntry {
+                        path: path.clone(),
+                        status: FileStatus::Deleted,
+                        staged: false,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn diff_unstaged(&self) -> Result<Vec<FileDiff>, GitError> {
+        let work = self.working_tree();
+        Ok(self.diff_trees(&self.tree_files(&self.index), &work_files(&work)))
+    }
+
+    fn diff_staged(&self) -> Result<Vec<FileDiff>, GitError> {
+        Ok(self.diff_trees(&self.tree_files(&self.head), &self.tree_files(&self.index)))
+    }
+
+    /// Diffs only `path`'s HEAD, index and working-tree contents, rather
+    /// than every changed file.
+    fn file_hunks(&self, path: &str) -> Result<FileHunks, GitError> {
+        let path = normalise(path);
+        let work = self.working_tree();
+        let blob = |tree: &TreeSnapshot| tree.get(&path).map(|id| self.blobs[id].as_slice());
+        let hunks_of = |old: Option<&[u8]>, new: Option<&[u8]>| {
+            let old: Files = old.map(|d| (path.as_str(), d)).into_iter().collect();
+            let new: Files = new.map(|d| (path.as_str(), d)).into_iter().collect();
+            self.diff_trees(&old, &new).pop().map(|d| d.hunks).unwrap_or_default()
+        };
+        Ok(FileHunks {
+            staged: hunks_of(blob(&self.head), blob(&self.index)),
+            unstaged: hunks_of(blob(&self.index), work.get(&path).map(Vec::as_slice)),
+        })
+    }
+
+    fn diff_commit(&self, sha: &str) -> Result<Vec<FileDiff>, GitError> {
+        self.require_commits()?;
+        let commit = self.find_commit(sha)?;
+
+        // Diff against the first parent (an empty tree for a root commit).
+        let parent_files = match commit.parents.first() {
+            Some(parent) => self.tree_files(&self.find_commit(parent)?.tree),
+            None => Files::new(),
+        };
+
+        Ok(self.diff_trees(&parent_files, &self.tree_files(&commit.tree)))
+    }
+
+    fn stage_file(&mut self, path: &str) -> Result<(), GitError> {
+        let path = normalise(path);
+        let path = path.as_str();
+        let work = self.working_tree();
+        if let Some(data) = work.get(path) {
+            let id = self.write_blob(data);
+            self.index.insert(path.to_string(), id);
+            self.staged_deletions.remove(path);
+        } else if self.head.contains_key(path) {
+            // File was deleted in working tree – record a tombstone.
+            self.index.remove(path);
+            self.staged_deletions.insert(path.to_string());
+        } else {
+            return Err(GitError::PathNotFound(path.to_string()));
+        }
+        self.mark_resolved(path);
+        Ok(())
+    }
+
+    fn unstage_file(&mut self, path: &str) -> Result<(), GitError> {
+        let path = normalise(path);
+        let path = path.as_str();
+        if self.head.contains_key(path) {
+            // Revert index to HEAD version.
+            self.index.insert(path.to_string(), self.head[path]);
+        } else {
+            // File didn't exist in HEAD – remove from index entirely.
+            self.index.remove(path);
+        }
+        self.staged_deletions.remove(path);
+        Ok(())
+    }
+
+    fn commit(&mut self, message: &str, author: &str) -> Result<String, GitError> {
+        self.require_resolved()?;
+        if self.index == self.head && self.staged_deletions.is_empty() {
+            return Err(GitError::NothingToCommit);
+        }
+        self.commit_allow_empty(message, author)
+    }
+
+    fn log(&self, max_count: usize) -> Result<Vec<CommitInfo>, GitError> {
+        Ok(self.commits_iter().take(max_count).collect())
+    }
+}
+
+/// Apply the per-file changes from `before` to `after` onto `tree`.  Returns
+/// the paths `tree` had already changed differently, which are left as is; a
+/// path that already has the target contents is not a conflict.
+fn apply_tree_changes(
+    tree: &mut TreeSnapshot,
+    before: &TreeSnapshot,
+    after: &TreeSnapshot,
+) -> Vec<String> {
+    let mut paths: BTreeSet<&String> = before.keys().collect();
+    paths.extend(after.keys());
+    let mut conflicts = Vec::new();
+    for path in paths {
+        let (old, new) = (before.get(path), after.get(path));
+        if old == new || tree.get(path) == new {
+            continue;
+        }
+        if tree.get(path) != old {
+            conflicts.push(path.clone());
+        } else if let Some(&id) = new {
+            tree.insert(path.clone(), id);
+        } else {
+            tree.remove(path);
+        }
+    }
+    conflicts
+}
+
+/// `true` when `data` looks like text (has no NUL byte), so its line
+/// endings may be converted.
+fn is_text(data: &[u8]) -> bool {
+    !data.contains(&0)
+}
+
+/// Replace every CRLF in a text file with LF.
+fn crlf_to_lf(data: Vec<u8>) -> Vec<u8> {
+    if !is_text(&data) || !data.windows(2).any(|w| w == b"\r\n") {
+        return data;
+    }
+    let mut out = Vec::with_capacity(data.len());
+    for (i, &b) in data.iter().enumerate() {
+        if !(b == b'\r' && data.get(i + 1) == Some(&b'\n')) {
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// Replace every bare LF in a text file with CRLF.
+fn lf_to_crlf(data: &[u8]) -> Vec<u8> {
+    if !is_text(data) {
+        return data.to_vec();
+    }
+    let mut out = Vec::with_capacity(data.len() + data.len() / 16);
+    for (i, &b) in data.iter().enumerate() {
+        if b == b'\n' && (i == 0 || data[i - 1] != b'\r') {
+            out.push(b'\r');
+        }
+        out.push(b);
+    }
+    out
+}
+
+/// Borrow the contents of a working tree for diffing.
+fn work_files(work: &WorkTree) -> Files<'_> {
+    work.iter().map(|(path, data)| (path.as_str(), data.as_slice())).collect()
+}
+
+/// The abbreviated form of `sha` shown in logs: its first 7 characters.
+fn short_sha(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+// ── Diff helpers ─────────────────────────────────────────────────────────────
+
+/// Produce hunks for a newly-added file (all lines are `+`).
+///
+/// Each line includes a trailing `\n` to match the unified-diff format
+/// expected by consumers such as *hunky*.
+fn diff_added(content: &str) -> Vec<DiffHunk> {
+    let lines: Vec<String> = content.lines().map(|l| format!("+{l}\n")).collect();
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    vec![DiffHunk {
+        old_start: 0,
+        new_start: 1,
+        lines,
+    }]
+}
+
+/// Produce hunks for a deleted file (all lines are `-`).
+///
+/// Each line includes a trailing `\n` to match the unified-diff format
+/// expected by consumers such as *hunky*.
+fn diff_deleted(content: &str) -> Vec<DiffHunk> {
+    let lines: Vec<String> = content.lines().map(|l| format!("-{l}\n")).collect();
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    vec![DiffHunk {
+        old_start: 1,
+        new_start: 0,
+        lines,
+    }]
+}
+
+/// Produce hunks for a modified file from `algorithm`'s line diff.
+fn diff_modified(old: &str, new: &str, algorithm: &dyn DiffAlgorithm) -> Vec<DiffHunk> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let edits = algorithm.diff(&old_lines, &new_lines);
+    let edit_script = slide_edits(&edits, &old_lines, &new_lines);
+
+    // Group consecutive edits into hunks with up to 3 context lines.
+    let context = 3;
+    let mut hunks: Vec<DiffHunk> = Vec::new();
+    let mut i = 0;
+
+    while i < edit_script.len() {
+        // Skip leading context lines until we hit a change.
+        if matches!(edit_script[i], Edit::Equal(_, _)) {
+            i += 1;
+            continue;
+        }
+
+        // Find the start of the change region with context.
+        let change_start = i;
+
+        // Walk backwards to include up to `context` preceding Equal lines.
+        let ctx_before_start = {
+            let mut s = change_start;
+            let mut ctx = 0;
+            while s > 0 && ctx < context {
+                if matches!(edit_script[s - 1], Edit::Equal(_, _)) {
+                    s -= 1;
+                    ctx += 1;
+                } else {
+                    break;
+                }
+            }
+            s
+        };
+
+        // Find end of this change group (including bridged gaps).
+        let mut change_end = change_start;
+        while change_end < edit_script.len() {
+            if matches!(edit_script[change_end], Edit::Equal(_, _)) {
+                // Count how many equal lines follow.
+                let mut eq_count = 0;
+                let mut j = change_end;
+                while j < edit_script.len() && matches!(edit_script[j], Edit::Equal(_, _)) {
+                    eq_count += 1;
+                    j += 1;
+                }
+                // If the gap is small enough and there are more changes after,
+                // merge them into the same hunk.
+                if eq_count <= context * 2 && j < edit_script.len() {
+                    change_end = j;
+                } else {
+                    break;
+                }
+            } else {
+                change_end += 1;
+            }
+        }
+
+        // Include up to `context` trailing Equal lines.
+        let ctx_after_end = {
+            let mut e = change_end;
+            let mut ctx = 0;
+            while e < edit_script.len() && ctx < context {
+                if matches!(edit_script[e], Edit::Equal(_, _)) {
+                    e += 1;
+                    ctx += 1;
+                } else {
+                    break;
+                }
+            }
+            e
+        };
+
+        // Determine old_start / new_start from the first edit in the hunk.
+        let (old_start, new_start) = match &edit_script[ctx_before_start] {
+            Edit::Equal(o, n) => (*o + 1, *n + 1),
+            Edit::Insert(_, n) => (if *n > 0 { *n } else { 0 }, *n + 1),
+            Edit::Delete(o, _) => (*o + 1, if *o > 0 { *o } else { 0 }),
+        };
+
+        let mut lines = Vec::new();
+        for edit in &edit_script[ctx_before_start..ctx_after_end] {
+            match edit {
+                Edit::Equal(o, _) => lines.push(format!(" {}\n", old_lines[*o])),
+                Edit::Delete(o, _) => lines.push(format!("-{}\n", old_lines[*o])),
+                Edit::Insert(_, n) => lines.push(format!("+{}\n", new_lines[*n])),
+            }
+        }
+
+        hunks.push(DiffHunk {
+            old_start,
+            new_start,
+            lines,
+        });
+
+        i = ctx_after_end;
+    }
+
+    hunks
+}
+
+// ── Line diff algorithms ─────────────────────────────────────────────────────
+
+/// One step of a line-level edit script, as produced by a [`DiffAlgorithm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    /// `(old_idx, new_idx)`: the lines match.
+    Equal(usize, usize),
+    /// `(old_idx, new_idx)`: old line removed; `new_idx` is positional context.
+    Delete(usize, usize),
+    /// `(old_idx, new_idx)`: new line added; `old_idx` is positional context.
+    Insert(usize, usize),
+}
+
+/// A line-diff algorithm, selected with [`DiffOptions::algorithm`].
+///
+/// Any minimal script will do: equal-cost alternatives are normalised
+/// afterwards so hunk boundaries land between logical sections.
+pub trait DiffAlgorithm: fmt::Debug {
+    /// Short identifier, e.g. `"myers"`.  [`DiffOptions`] compare their
+    /// algorithms by name alone, so each algorithm (and each configuration
+    /// of one that changes its output) needs a name of its own.
+    fn name(&self) -> &'static str;
+
+    /// Return the edit script turning `old` into `new`: every line of both
+    /// sides exactly once, in order.
+    fn diff(&self, old: &[&str], new: &[&str]) -> Vec<Edit>;
+}
+
+/// The classic LCS dynamic-programming diff.  Quadratic in time and memory,
+/// which is fine for the typical diff sizes encountered in a TUI.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LcsDiff;
+
+impl DiffAlgorithm for LcsDiff {
+    fn name(&self) -> &'static str {
+        "lcs"
+    }
+
+    fn diff(&self, old: &[&str], new: &[&str]) -> Vec<Edit> {
+        lcs_diff(old, new)
+    }
+}
+
+/// Myers' O(ND) diff: time grows with the number of differences rather than
+/// the file sizes, so it suits large files with small changes.  Memory grows
+/// with the square of the number of differences.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MyersDiff;
+
+impl DiffAlgorithm for MyersDiff {
+    fn name(&self) -> &'static str {
+        "myers"
+    }
+
+    fn diff(&self, old: &[&str], new: &[&str]) -> Vec<Edit> {
+        let (n, m) = (old.len() as isize, new.len() as isize);
+        let max = old.len() + new.len();
+        let offset = max as isize + 1;
+        // `v[k + offset]` is the furthest x reached on diagonal k = x - y;
+        // `trace[d]` holds diagonals -(d + 1)..=d + 1 of `v` as it stood
+        // before round d, the only ones the walk back reads from it.
+        let mut v = vec![0isize; 2 * max + 3];
+        let mut trace = Vec::new();
+        'search: for d in 0..=max as isize {
+            trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+            for k in (-d..=d).step_by(2) {
+                let at = |k: isize| v[(k + offset) as usize];
+                let mut x = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                    at(k + 1)
+                } else {
+                    at(k - 1) + 1
+                };
+                let mut y = x - k;
+                while x < n && y < m && old[x as usize] == new[y as usize] {
+                    x += 1;
+                    y += 1;
+                }
+                v[(k + offset) as usize] = x;
+                if x >= n && y >= m {
+                    break 'search;
+                }
+            }
+        }
+
+        // Walk back from the end, one difference per round.
+        let mut edits = Vec::with_capacity(max);
+        let (mut x, mut y) = (n, m);
+        for (d, band) in trace.iter().enumerate().rev() {
+            let d = d as isize;
+            let at = |k: isize| band[(k + d + 1) as usize];
+            let k = x - y;
+            let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+            let prev_x = at(prev_k);
+            let prev_y = prev_x - prev_k;
+            while x > prev_x && y > prev_y {
+                x -= 1;
+                y -= 1;
+                edits.push(Edit::Equal(x as usize, y as usize));
+            }
+            if d > 0 {
+                if x == prev_x {
+                    y -= 1;
+                    edits.push(Edit::Insert(x as usize, y as usize));
+                } else {
+                    x -= 1;
+                    edits.push(Edit::Delete(x as usize, y as usize));
+                }
+            }
+        }
+        edits.reverse();
+        edits
+    }
+}
+
+/// Compute a line-level edit script using the classic LCS dynamic-programming
+/// algorithm.
+fn lcs_diff(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let m = old.len();
+    let n = new.len();
+
+    // Build LCS table.
+    let mut table = vec![vec![0u32; n + 1]; m + 1];
+    for i in (0..m).rev() {
+        for j in (0..n).rev() {
+            if old[i] == new[j] {
+                table[i][j] = table[i + 1][j + 1] + 1;
+            } else {
+                table[i][j] = table[i + 1][j].max(table[i][j + 1]);
+            }
+        }
+    }
+
+    // Backtrack to produce the edit script.
+    let mut edits = Vec::new();
+    let mut i = 0;
+    let mut j = 0;
+    while i < m || j < n {
+        if i < m && j < n && old[i] == new[j] {
+            edits.push(Edit::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if j < n && (i >= m || table[i][j + 1] >= table[i + 1][j]) {
+            edits.push(Edit::Insert(i, j));
+            j += 1;
+        } else {
+            edits.push(Edit::Delete(i, j));
+            i += 1;
+        }
+    }
+
+    edits
+}
+
+// ── Ignore rules ─────────────────────────────────────────────────────────────
+
+/// The patterns of a `.gitignore` file, in file order.
+#[derive(Debug, Default)]
+struct IgnoreRules {
+    patterns: Vec<IgnorePattern>,
+}
+
+/// One `.gitignore` line.
+#[derive(Debug)]
+struct IgnorePattern {
+    glob: String,
+    /// Matched against the whole path rather than just the last component.
+    anchored: bool,
+    /// Only matches directories (the line ended in `/`).
+    dir_only: bool,
+}
+
+impl IgnoreRules {
+    fn parse(text: &str) -> Self {
+        let patterns = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('!'))
+            .filter_map(|line| {
+                let dir_only = line.ends_with('/');
+                let line = line.trim_end_matches('/');
+                let anchored = line.contains('/');
+                let glob = line.strip_prefix('/').unwrap_or(line);
+                (!glob.is_empty()).then(|| IgnorePattern {
+                    glob: glob.to_string(),
+                    anchored,
+                    dir_only,
+                })
+            })
+            .collect();
+        IgnoreRules { patterns }
+    }
+
+    /// `true` when a file (or, with `is_dir`, a directory) at `path` is
+    /// ignored by a pattern of its own; ignored parents are not considered.
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        self.patterns.iter().any(|p| {
+            (is_dir || !p.dir_only) && glob_match(&p.glob, if p.anchored { path } else { name })
+        })
+    }
+
+    /// The outermost ancestor directory of `path` that is ignored, if any.
+    fn ignored_dir<'a>(&self, path: &'a str) -> Option<&'a str> {
+        if self.patterns.is_empty() {
+            return None;
+        }
+        path.match_indices('/').map(|(at, _)| &path[..at]).find(|dir| self.matches(dir, true))
+    }
+}
+
+/// Match `text` against a `.gitignore` glob: `?` and `*` stop at `/`, while
+/// `**` crosses it.
+fn glob_match(glob: &str, text: &str) -> bool {
+    match glob.as_bytes().first() {
+        None => text.is_empty(),
+        Some(b'*') if glob.starts_with("**") => {
+            let rest = glob[2..].trim_start_matches('/');
+            (0..=text.len())
+                .filter(|&at| text.is_char_boundary(at))
+                .any(|at| glob_match(rest, &text[at..]))
+        }
+        Some(b'*') => {
+            let rest = &glob[1..];
+            let limit = text.find('/').unwrap_or(text.len());
+            (0..=limit)
+                .filter(|&at| text.is_char_boundary(at))
+                .any(|at| glob_match(rest, &text[at..]))
+        }
+        Some(b'?') => text
+            .chars()
+            .next()
+            .is_some_and(|c| c != '/' && glob_match(&glob[1..], &text[c.len_utf8()..])),
+        Some(_) => {
+            let c = glob.chars().next().unwrap_or_default();
+            text.starts_with(c) && glob_match(&glob[c.len_utf8()..], &text[c.len_utf8()..])
+        }
+    }
+}
+
+// ── Patch parsing ────────────────────────────────────────────────────────────
+
+/// One file's part of a unified diff.
+#[derive(Debug)]
+struct FilePatch {
+    /// `None` for `/dev/null` (an added file).
+    old_path: Option<String>,
+    /// `None` for `/dev/null` (a deleted file).
+    new_path: Option<String>,
+    hunks: Vec<PatchHunk>,
+}
+
+/// One `@@` hunk of a [`FilePatch`].
+#[derive(Debug)]
+struct PatchHunk {
+    /// 1-based first old line (the line before the hunk when `old_len` is 0).
+    old_start: usize,
+    old_len: usize,
+    /// `(prefix, text, ends_with_newline)` per line; prefix is ` `, `-` or `+`.
+    lines: Vec<(char, String, bool)>,
+}
+
+/// Parse the files and hunks of a unified diff.  Text outside file sections
+/// (e.g. `diff --git` and `index` lines) is ignored.
+fn parse_patch(patch: &str) -> Result<Vec<FilePatch>, GitError> {
+    let invalid = |msg: &str| GitError::Other(format!("invalid patch: {msg}"));
+    let patch_path = |spec: &str, prefix: &str| {
+        let spec = spec.split('\t').next().unwrap_or("").trim_end();
+        (spec != "/dev/null").then(|| normalise(spec.strip_prefix(prefix).unwrap_or(spec)))
+    };
+
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut lines = patch.lines().peekable();
+    while let Some(line) = lines.next() {
+        if let Some(old) = line.strip_prefix("--- ") {
+            let new = lines
+                .next()
+                .and_then(|l| l.strip_prefix("+++ "))
+                .ok_or_else(|| invalid("`---` without `+++`"))?;
+            let (old_path, new_path) = (patch_path(old, "a/"), patch_path(new, "b/"));
+            if old_path.is_none() && new_path.is_none() {
+                return Err(invalid("both sides are /dev/null"));
+            }
+            files.push(FilePatch { old_path, new_path, hunks: Vec::new() });
+        } else if let Some(header) = line.strip_prefix("@@ -") {
+            let file = files.last_mut().ok_or_else(|| invalid("hunk before file header"))?;
+            let range = |spec: &str| -> Option<(usize, usize)> {
+                match spec.split_once(',') {
+                    Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+                    None => Some((spec.parse().ok()?, 1)),
+                }
+            };
+            let (old, rest) = header.split_once(" +").ok_or_else(|| invalid(line))?;
+            let new = rest.split_once(" @@").ok_or_else(|| invalid(line))?.0;
+            let ((old_start, old_len), (_, new_len)) =
+                range(old).zip(range(new)).ok_or_else(|| invalid(line))?;
+
+            let mut hunk = PatchHunk { old_start, old_len, lines: Vec::new() };
+            let (mut old_left, mut new_left) = (old_len, new_len);
+            while old_left > 0 || new_left > 0 {
+                let body = lines.next().ok_or_else(|| invalid("truncated hunk"))?;
+                // Some editors strip the space from an empty context line.
+                let (prefix, text) = match body.chars().next() {
+                    Some(c @ (' ' | '-' | '+')) => (c, &body[1..]),
+                    None => (' ', ""),
+                    Some(_) => return Err(invalid(body)),
+                };
+                if prefix != '+' {
+                    old_left = old_left.checked_sub(1).ok_or_else(|| invalid("hunk too long"))?;
+                }
+                if prefix != '-' {
+                    new_left = new_left.checked_sub(1).ok_or_else(|| invalid("hunk too long"))?;
+                }
+                hunk.lines.push((prefix, text.to_string(), true));
+                if lines.peek().is_some_and(|l| l.starts_with('\\')) {
+                    lines.next();
+                    if let Some(last) = hunk.lines.last_mut() {
+                        last.2 = false;
+                    }
+                }
+            }
+            file.hunks.push(hunk);
+        }
+    }
+    if files.is_empty() {
+        return Err(invalid("no file headers"));
+    }
+    Ok(files)
+}
+
+/// Apply `hunks` (in file order) to `old`, or `None` if any context or
+/// removed line does not match.
+fn apply_hunks(old: &[u8], hunks: &[PatchHunk]) -> Option<Vec<u8>> {
+    let old_lines: Vec<&[u8]> = old.split_inclusive(|&b| b == b'\n').collect();
+    let trim = |line: &[u8]| -> Vec<u8> {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        line.strip_suffix(b"\r").unwrap_or(line).to_vec()
+    };
+    let mut out = Vec::with_capacity(old.len());
+    let mut next = 0;
+    for hunk in hunks {
+        let start = if hunk.old_len == 0 { hunk.old_start } else { hunk.old_start.checked_sub(1)? };
+        if start < next || start > old_lines.len() {
+            return None;
+        }
+        out.extend(old_lines[next..start].concat());
+        next = start;
+        for (prefix, text, newline) in &hunk.lines {
+            if *prefix == '+' {
+                out.extend_from_slice(text.as_bytes());
+                if *newline {
+                    out.push(b'\n');
+                }
+                continue;
+            }
+            let line = old_lines.get(next)?;
+            if trim(line) != text.as_bytes() {
+                return None;
+            }
+            if *prefix == ' ' {
+                out.extend_from_slice(line);
+            }
+            next += 1;
+        }
+    }
+    out.extend(old_lines[next..].concat());
+    Some(out)
+}
+
+// ── Ref names ────────────────────────────────────────────────────────────────
+
+/// Check that `name` is usable as a branch or tag name (`kind`), following
+/// the main rules of `git check-ref-format`: no whitespace or control
+/// characters, none of `~ ^ : ? * [ \\`, no `..` or `@{`, no empty path
+/// component, and no leading `-`, leading or trailing `/`, or trailing `.`
+/// or `.lock`.
+fn check_ref_name(kind: &'static str, name: &str) -> Result<(), GitError> {
+    let reason = if name.is_empty() {
+        Some("is empty".to_string())
+    } else if let Some(c) = name.chars().find(|c| c.is_whitespace() || c.is_control()) {
+        Some(format!("contains {c:?}"))
+    } else if let Some(c) = name.chars().find(|c| "~^:?*[\\".contains(*c)) {
+        Some(format!("contains `{c}`"))
+    } else if let Some(seq) = ["..", "@{", "//"].into_iter().find(|seq| name.contains(seq)) {
+        Some(format!("contains `{seq}`"))
+    } else if name.starts_with('/') || name.ends_with('/') {
+        Some("starts or ends with `/`".to_string())
+    } else if name.starts_with('-') {
+        Some("starts with `-`".to_string())
+    } else if name.ends_with('.') || name.ends_with(".lock") {
+        Some(format!("ends with `{}`", if name.ends_with('.') { "." } else { ".lock" }))
+    } else {
+        None
+    };
+    match reason {
+        Some(reason) => Err(GitError::InvalidRefName {
+            kind,
+            name: name.to_string(),
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+// ── Commit message helpers ───────────────────────────────────────────────────
+
+/// Append a `key: value` trailer (e.g. `Signed-off-by: Ann <ann@x>`) to a
+/// commit message, as `git interpret-trailers` does.
+///
+/// The trailer joins the message's final paragraph when that is already a
+/// block of trailers, and starts a new paragraph otherwise.  A trailer
+/// identical to the last one is not repeated.  The result ends in a newline.
+pub fn add_trailer(message: &str, key: &str, value: &str) -> String {
+    let body = message.trim_end();
+    let trailer = format!("{key}: {value}");
+    let last_paragraph = body.rsplit("\n\n").next().unwrap_or("");
+    let is_trailer = |line: &str| {
+        line.split_once(": ").is_some_and(|(k, _)| {
+            !k.is_empty() && k.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+    };
+    let in_trailer_block = body.contains("\n\n") && last_paragraph.lines().all(is_trailer);
+
+    let mut out = body.to_string();
+    if in_trailer_block && last_paragraph.lines().last() == Some(trailer.as_str()) {
+        out.push('\n');
+        return out;
+    }
+    if !body.is_empty() {
+        out.push_str(if in_trailer_block { "\n" } else { "\n\n" });
+    }
+    out.push_str(&trailer);
+    out.push('\n');
+    out
+}
+
+// ── Conflict helpers ─────────────────────────────────────────────────────────
+
+/// Number of context lines kept around each [`ConflictBlock`].
+const CONFLICT_CONTEXT: usize = 3;
+
+/// Line indices of the markers delimiting one conflict.
+struct ConflictSpan {
+    start: usize,
+    /// The diff3 `|||||||` base marker, if present.
+    base: Option<usize>,
+    separator: usize,
+    end: usize,
+}
+
+/// Whether `line` (terminator allowed) is the conflict `marker`, optionally
+/// followed by a space and a label.
+fn is_marker(line: &str, marker: &str, labelled: bool) -> bool {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let Some(rest) = line.strip_prefix(marker) else {
+        return false;
+    };
+    rest.is_empty() || (labelled && rest.starts_with(' '))
+}
+
+/// Locate every complete conflict in `lines`.  Unterminated markers are
+/// treated as ordinary text.
+fn conflict_spans<S: AsRef<str>>(lines: &[S]) -> Vec<ConflictSpan> {
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if !is_marker(lines[i].as_ref(), "<<<<<<<", true) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let (mut base, mut separator) = (None, None);
+        let mut j = i + 1;
+        let mut end = None;
+        while j < lines.len() {
+            let line = lines[j].as_ref();
+            if separator.is_none() && base.is_none() && is_marker(line, "|||||||", true) {
+                base = Some(j);
+            } else if separator.is_none() && is_marker(line, "=======", false) {
+                separator = Some(j);
+            } else if separator.is_some() && is_marker(line, ">>>>>>>", true) {
+                end = Some(j);
+                break;
+            } else if is_marker(line, "<<<<<<<", true) {
+                break;
+            }
+            j += 1;
+        }
+        match (separator, end) {
+            (Some(separator), Some(end)) => {
+                spans.push(ConflictSpan {
+                    start,
+                    base,
+                    separator,
+                    end,
+                });
+                i = end + 1;
+            }
+            _ => i = start + 1,
+        }
+    }
+    spans
+}
+
+/// Parse the conflict markers left in a file by a merge.
+///
+/// Both the default (`<<<<<<<`/`=======`/`>>>>>>>`) and diff3 (with a
+/// `|||||||` base section, which is skipped) styles are understood.
+pub fn parse_conflicts(content: &str) -> Vec<ConflictBlock> {
+    let lines: Vec<&str> = content.lines().collect();
+    let spans = conflict_spans(&lines);
+    let owned = |range: std::ops::Range<usize>| -> Vec<String> {
+        lines[range].iter().map(|l| l.to_string()).collect()
+    };
+
+    spans
+        .iter()
+        .enumerate()
+        .map(|(n, span)| {
+            let prev_end = if n == 0 { 0 } else { spans[n - 1].end + 1 };
+            let next_start = spans.get(n + 1).map_or(lines.len(), |s| s.start);
+            let before_start = span.start.saturating_sub(CONFLICT_CONTEXT).max(prev_end);
+            let after_end = (span.end + 1 + CONFLICT_CONTEXT).min(next_start);
+            ConflictBlock {
+                start_line: span.start,
+                ours: owned(span.start + 1..span.base.unwrap_or(span.separator)),
+                theirs: owned(span.separator + 1..span.end),
+                context_before: owned(before_start..span.start),
+                context_after: owned(span.end + 1..after_end),
+            }
+        })
+        .collect()
+}
+
+/// Resolve every conflict in `content` by keeping the chosen [`Side`].
+///
+/// Text outside conflicts, including line terminators, is preserved exactly.
+pub fn resolve_conflict(content: &str, choice: Side) -> String {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut out = String::with_capacity(content.len());
+    let mut next = 0;
+    for span in conflict_spans(&lines) {
+        lines[next..span.start].iter().for_each(|l| out.push_str(l));
+        let ours = &lines[span.start + 1..span.base.unwrap_or(span.separator)];
+        let theirs = &lines[span.separator + 1..span.end];
+        if matches!(choice, Side::Ours | Side::Union) {
+            ours.iter().for_each(|l| out.push_str(l));
+        }
+        if matches!(choice, Side::Theirs | Side::Union) {
+            theirs.iter().for_each(|l| out.push_str(l));
+        }
+        next = span.end + 1;
+    }
+    lines[next..].iter().for_each(|l| out.push_str(l));
+    out
+}
+
+// ── Slider heuristic ─────────────────────────────────────────────────────────
+
+/// Shift ambiguous change blocks to cleaner positions, like git's
+/// "slider" post-processing.
+///
+/// When a block of added (or removed) lines is surrounded by lines equal to
+/// its own edges, the same change can be expressed at several offsets, and
+/// the LCS backtrack picks one arbitrarily (e.g. attributing a function's
+/// closing brace to the wrong side).  Each block is slid to the lowest
+/// position whose last line is blank, or else as far down as it goes, so
+/// hunk boundaries land between logical sections.
+///
+/// Deletions are emitted before insertions within each change group.
+fn slide_edits(edits: &[Edit], old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let mut old_changed = vec![false; old.len()];
+    let mut new_changed = vec![false; new.len()];
+    for edit in edits {
+        match edit {
+            Edit::Delete(o, _) => old_changed[*o] = true,
+            Edit::Insert(_, n) => new_changed[*n] = true,
+            Edit::Equal(_, _) => {}
+        }
+    }
+
+    slide_groups(&mut old_changed, old);
+    slide_groups(&mut new_changed, new);
+
+    // Rebuild the edit script from the two change maps.  The unchanged lines
+    // on each side are still identical sequences, so they pair up in order.
+    let mut out = Vec::with_capacity(edits.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && old_changed[i] {
+            out.push(Edit::Delete(i, j));
+            i += 1;
+        } else if j < new.len() && new_changed[j] {
+            out.push(Edit::Insert(i, j));
+            j += 1;
+        } else {
+            out.push(Edit::Equal(i, j));
+            i += 1;
+            j += 1;
+        }
+    }
+    out
+}
+
+/// Slide every run of changed lines in one file (see [`slide_edits`]).
+fn slide_groups(changed: &mut [bool], lines: &[&str]) {
+    let len = changed.len();
+    let mut start = 0;
+    while start < len {
+        if !changed[start] {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < len && changed[end] {
+            end += 1;
+        }
+        let size = end - start;
+        changed[start..end].iter_mut().for_each(|c| *c = false);
+        let (mut s, mut e) = (start, end);
+
+        // Slide up as far as possible without touching the previous group.
+        while s > 0 && lines[s - 1] == lines[e - 1] && (s == 1 || !changed[s - 2]) {
+            s -= 1;
+            e -= 1;
+        }
+        // Then walk down, remembering the best resting place.
+        let mut best = None;
+        loop {
+            if lines[e - 1].trim().is_empty() {
+                best = Some(s);
+            }
+            if e < len && lines[s] == lines[e] && (e + 1 == len || !changed[e + 1]) {
+                s += 1;
+                e += 1;
+            } else {
+                break;
+            }
+        }
+        let s = best.unwrap_or(s);
+        changed[s..s + size].iter_mut().for_each(|c| *c = true);
+        start = s + size;
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::Filesystem;
+
+    fn setup() -> InMemoryGitRepository {
+        let fs = MemoryFilesystem::new();
+        InMemoryGitRepository::new(fs)
+    }
+
+    /// Write and stage `files` (`(path, contents)`), then commit them.
+    fn commit_files(repo: &mut InMemoryGitRepository, files: &[(&str, &str)]) -> String {
+        for (path, data) in files {
+            repo.filesystem_mut().write_file(path, data.as_bytes()).unwrap();
+            repo.stage_file(path).unwrap();
+        }
+        repo.commit("c", "t").unwrap()
+    }
+
+    #[test]
+    fn status_empty_repo() {
+        let repo = setup();
+        let st = repo.status().unwrap();
+        assert!(st.is_empty());
+    }
+
+    #[test]
+    fn status_untracked_file() {
+        let mut repo = setup();
+        repo.filesystem_mut()
+            .write_file("hello.txt", b"world")
+            .unwrap();
+        let st = repo.status().unwrap();
+        assert_eq!(st.len(), 1);
+        assert_eq!(st[0].path, "hello.txt");
+        assert_eq!(st[0].status, FileStatus::Untracked);
+        assert!(!st[0].staged);
+    }
+
+    #[test]
+    fn status_counts_mixed_state() {
+        let mut repo = setup();
+        for path in ["a.txt", "b.txt", "c.txt"] {
+            repo.filesystem_mut().write_file(path, b"v1\n").unwrap();
+            repo.stage_file(path).unwrap();
+        }
+        repo.commit("base", "t").unwrap();
+
+        let fs = repo.filesystem_mut();
+        fs.write_file("a.txt", b"v2\n").unwrap();
+        fs.write_file("b.txt", b"v2\n").unwrap();
+        fs.remove_file("c.txt").unwrap();
+        fs.write_file("new.txt", b"staged\n").unwrap();
+        fs.write_file("x.txt", b"?\n").unwrap();
+        fs.write_file("y.txt", b"?\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.stage_file("new.txt").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"v3\n").unwrap();
+
+        let counts = repo.status_counts().unwrap();
+        assert_eq!(counts, StatusCounts { staged: 2, modified: 3, untracked: 2 });
+    }
+
+    #[test]
+    fn snapshot_view_matches_individual_calls() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"one\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.commit("init", "test").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"two\n").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"new\n").unwrap();
+        repo.stage_file("b.txt").unwrap();
+        repo.filesystem_mut().write_file("c.txt", b"loose\n").unwrap();
+
+        let view = repo.snapshot_view().unwrap();
+        assert_eq!(view.status, repo.status().unwrap());
+        assert_eq!(view.staged, repo.diff_staged().unwrap());
+        assert_eq!(view.unstaged, repo.diff_unstaged().unwrap());
+        assert_eq!(view.status.len(), 3);
+    }
+
+    #[test]
+    fn file_hunks_splits_staged_and_unstaged() {
+        let mut repo = setup();
+        let lines: Vec<String> = (1..=12).map(|n| format!("line {n}\n")).collect();
+        repo.filesystem_mut().write_file("f.txt", lines.concat().as_bytes()).unwrap();
+        repo.stage_file("f.txt").unwrap();
+        repo.commit("init", "t").unwrap();
+
+        let mut edited = lines.clone();
+        edited[0] = "staged\n".to_string();
+        repo.filesystem_mut().write_file("f.txt", edited.concat().as_bytes()).unwrap();
+        repo.stage_file("f.txt").unwrap();
+        edited[11] = "unstaged\n".to_string();
+        repo.filesystem_mut().write_file("f.txt", edited.concat().as_bytes()).unwrap();
+
+        let hunks = repo.file_hunks("f.txt").unwrap();
+        assert_eq!(hunks.staged.len(), 1);
+        assert!(hunks.staged[0].lines.iter().any(|l| l.starts_with("+staged")));
+        assert_eq!(hunks.unstaged.len(), 1);
+        assert!(hunks.unstaged[0].lines.iter().any(|l| l.starts_with("+unstaged")));
+        assert_eq!(hunks.staged, repo.diff_staged().unwrap()[0].hunks);
+        assert_eq!(hunks.unstaged, repo.diff_unstaged().unwrap()[0].hunks);
+        assert_eq!(repo.file_hunks("other.txt").unwrap(), FileHunks::default());
+    }
+
+    #[test]
+    fn autocrlf_input_commits_lf() {
+        let mut repo = setup();
+        repo.set_autocrlf(AutoCrlf::Input);
+        repo.filesystem_mut().write_file("a.txt", b"one\r\ntwo\r\n").unwrap();
+        repo.filesystem_mut().write_file("bin", b"\0\r\n").unwrap();
+        repo.stage_files(&["a.txt", "bin"]).unwrap();
+        let sha = repo.commit("crlf", "t").unwrap();
+
+        let tree = &repo.find_commit(&sha).unwrap().tree;
+        assert_eq!(repo.blob(tree["a.txt"]), Some(&b"one\ntwo\n"[..]));
+        assert_eq!(repo.blob(tree["bin"]), Some(&b"\0\r\n"[..]));
+        // The CRLF working copy matches the LF blob.
+        assert!(repo.status().unwrap().is_empty());
+        repo.set_autocrlf(AutoCrlf::False);
+        assert_eq!(repo.status_short().unwrap(), " M a.txt\n");
+    }
+
+    #[test]
+    fn autocrlf_true_checks_out_crlf() {
+        let mut repo = setup();
+        repo.set_autocrlf(AutoCrlf::True);
+        repo.filesystem_mut().write_file("a.txt", b"one\ntwo\r\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.commit("mixed", "t").unwrap();
+
+        repo.reset("HEAD", ResetMode::Hard).unwrap();
+        assert_eq!(repo.filesystem().read_file("a.txt").unwrap(), b"one\r\ntwo\r\n");
+        assert!(repo.status().unwrap().is_empty());
+    }
+
+    #[test]
+    fn apply_patch_stages_one_hunk() {
+        let mut repo = setup();
+        let lines: Vec<String> = (1..=12).map(|n| format!("line {n}\n")).collect();
+        repo.filesystem_mut().write_file("f.txt", lines.concat().as_bytes()).unwrap();
+        repo.stage_file("f.txt").unwrap();
+        repo.commit("init", "t").unwrap();
+        let mut edited = lines.clone();
+        edited[0] = "first\n".to_string();
+        edited[11] = "last\n".to_string();
+        repo.filesystem_mut().write_file("f.txt", edited.concat().as_bytes()).unwrap();
+
+        let patch = "diff --git a/f.txt b/f.txt\n--- a/f.txt\n+++ b/f.txt\n\
+                     @@ -1,4 +1,4 @@\n-line 1\n+first\n line 2\n line 3\n line 4\n";
+        repo.apply_patch_to_index(patch).unwrap();
+
+        let hunks = repo.file_hunks("f.txt").unwrap();
+        assert_eq!(hunks.staged.len(), 1);
+        assert_eq!(hunks.staged[0].lines[..2], ["-line 1\n", "+first\n"]);
+        assert_eq!(hunks.unstaged.len(), 1);
+        assert!(hunks.unstaged[0].lines.contains(&"+last\n".to_string()));
+        assert_eq!(repo.filesystem().read_file("f.txt").unwrap(), edited.concat().as_bytes());
+    }
+
+    #[test]
+    fn apply_patch_adds_files_and_rejects_stale_context() {
+        let mut repo = setup();
+        let add = "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n\
+                   +a\n+b\n\\ No newline at end of file\n";
+        repo.apply_patch_to_index(add).unwrap();
+        let id = repo.index["new.txt"];
+        assert_eq!(repo.blob(id), Some(&b"a\nb"[..]));
+        assert_eq!(repo.status_short().unwrap(), "AD new.txt\n");
+
+        let stale = "--- a/new.txt\n+++ b/new.txt\n@@ -1 +1 @@\n-x\n+y\n";
+        assert!(matches!(repo.apply_patch_to_index(stale), Err(GitError::Other(_))));
+        assert_eq!(repo.index["new.txt"], id);
+        assert!(repo.apply_patch_to_index("not a patch").is_err());
+    }
+
+    #[test]
+    fn add_trailer_starts_or_extends_a_trailer_block() {
+        let signed = add_trailer("Fix parser\n\nHandles tabs.\n", "Signed-off-by", "Ann <a@x>");
+        assert_eq!(signed, "Fix parser\n\nHandles tabs.\n\nSigned-off-by: Ann <a@x>\n");
+
+        let both = add_trailer(&signed, "Reviewed-by", "Bo <b@x>");
+        assert!(both.ends_with("\n\nSigned-off-by: Ann <a@x>\nReviewed-by: Bo <b@x>\n"));
+        assert_eq!(add_trailer(&both, "Reviewed-by", "Bo <b@x>"), both);
+        assert_eq!(add_trailer("Summary: only", "Acked-by", "C"), "Summary: only\n\nAcked-by: C\n");
+    }
+
+    #[test]
+    fn commit_validated_rejects_an_overlong_summary() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a").unwrap();
+        repo.stage_file("a.txt").unwrap();
+
+        let long = format!("{}\n\nBody lines may be longer.", "x".repeat(73));
+        let err = repo.commit_validated(&long, "t").unwrap_err();
+        assert_eq!(err, GitError::SummaryTooLong { len: 73, limit: 72 });
+        assert!(repo.log(1).unwrap().is_empty());
+
+        repo.set_summary_limit(80);
+        repo.commit_validated(&long, "t").unwrap();
+        assert_eq!(repo.log(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn stage_and_commit() {
+        let mut repo = setup();
+        repo.filesystem_mut()
+            .write_file("a.txt", b"hello")
+            .unwrap();
+        repo.stage_file("a.txt").unwrap();
+
+        // Should show as staged Added.
+        let st = repo.status().unwrap();
+        let staged: Vec<_> = st.iter().filter(|e| e.staged).collect();
+        assert_eq!(staged.len(), 1);
+        assert_eq!(staged[0].status, FileStatus::Added);
+
+        let sha = repo.commit("initial", "test").unwrap();
+        assert!(!sha.is_empty());
+
+        // After commit, status should be clean.
+        let st = repo.status().unwrap();
+        let relevant: Vec<_> = st
+            .iter()
+            .filter(|e| e.status != FileStatus::Untracked)
+            .collect();
+        assert!(relevant.is_empty(), "expected clean status after commit");
+    }
+
+    #[test]
+    fn nothing_to_commit() {
+        let mut repo = setup();
+        let err = repo.commit("empty", "test").unwrap_err();
+        assert_eq!(err, GitError::NothingToCommit);
+    }
+
+    #[test]
+    fn errors_convert_to_io_errors() {
+        use io::ErrorKind;
+        let kind = |e: GitError| io::Error::from(e).kind();
+        assert_eq!(kind(GitError::NotInitialised), ErrorKind::NotFound);
+        assert_eq!(kind(GitError::NothingToCommit), ErrorKind::Other);
+        assert_eq!(kind(GitError::PathNotFound("a".into())), ErrorKind::NotFound);
+        assert_eq!(kind(GitError::CommitNotFound("ff".into())), ErrorKind::NotFound);
+        assert_eq!(kind(GitError::Conflict(vec!["a".into()])), ErrorKind::Other);
+        assert_eq!(kind(GitError::InvalidRevision("x".into())), ErrorKind::InvalidInput);
+        assert_eq!(kind(GitError::MissingIdentity), ErrorKind::InvalidInput);
+        let too_long = GitError::SummaryTooLong { len: 80, limit: 72 };
+        assert_eq!(kind(too_long), ErrorKind::InvalidInput);
+        let bad_ref = GitError::InvalidRefName {
+            kind: "tag",
+            name: "a b".into(),
+            reason: "contains ' '".into(),
+        };
+        assert_eq!(kind(bad_ref), ErrorKind::InvalidInput);
+        assert_eq!(kind(GitError::Other("oops".into())), ErrorKind::Other);
+
+        let e = io::Error::from(GitError::PathNotFound("a.txt".into()));
+        assert_eq!(e.to_string(), "file not found: a.txt");
+    }
+
+    #[test]
+    fn errors_use_specific_variants() {
+        let mut repo = setup();
+        assert_eq!(
+            repo.stage_file("missing.txt").unwrap_err(),
+            GitError::PathNotFound("missing.txt".into())
+        );
+        // An empty repository fails earlier, with `NotInitialised`.
+        repo.commit_allow_empty("root", "test").unwrap();
+        assert_eq!(
+            repo.diff_commit("00000000000000ff").unwrap_err(),
+            GitError::CommitNotFound("00000000000000ff".into())
+        );
+        assert_eq!(
+            repo.diff_commit("HEAD~1").unwrap_err(),
+            GitError::InvalidRevision("HEAD~1".into())
+        );
+    }
+
+    #[test]
+    fn changed_files_lists_paths_between_revisions() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.filesystem_mut().write_file("keep.txt", b"k\n").unwrap();
+        repo.stage_files(&["a.txt", "keep.txt"]).unwrap();
+        let first = repo.commit("first", "test").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"a2\n").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"b\n").unwrap();
+        repo.stage_files(&["a.txt", "b.txt"]).unwrap();
+        repo.commit("second", "test").unwrap();
+
+        let changed = repo.changed_files(&first, "HEAD").unwrap();
+        assert_eq!(
+            changed,
+            [("a.txt".to_string(), FileStatus::Modified), ("b.txt".to_string(), FileStatus::Added)]
+        );
+        let from_diff: Vec<_> = repo
+            .diff_range(&first, &repo.resolve("HEAD").unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.path, d.status))
+            .collect();
+        assert_eq!(changed, from_diff);
+        assert!(repo.changed_files("HEAD", "HEAD").unwrap().is_empty());
+    }
+
+    #[test]
+    fn status_against_merge_base_lists_branch_changes() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"b\n").unwrap();
+        repo.stage_files(&["a.txt", "b.txt"]).unwrap();
+        repo.commit("base", "test").unwrap();
+        repo.create_branch("topic").unwrap();
+        repo.checkout("topic").unwrap();
+
+        // Two commits: one adds and later reverts a change to b.txt.
+        repo.filesystem_mut().write_file("a.txt", b"a2\n").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"b2\n").unwrap();
+        repo.filesystem_mut().write_file("c.txt", b"c\n").unwrap();
+        repo.stage_files(&["a.txt", "b.txt", "c.txt"]).unwrap();
+        repo.commit("one", "test").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"b\n").unwrap();
+        repo.stage_file("b.txt").unwrap();
+        repo.commit("two", "test").unwrap();
+        repo.filesystem_mut().write_file("scratch.txt", b"s\n").unwrap();
+
+        let (main, topic) = (repo.resolve("main").unwrap(), repo.resolve("topic").unwrap());
+        let base = repo.merge_base(&main, &topic).unwrap().unwrap();
+        assert_eq!(
+            repo.status_against(&base).unwrap(),
+            [
+                ("a.txt".to_string(), FileStatus::Modified),
+                ("c.txt".to_string(), FileStatus::Added),
+                ("scratch.txt".to_string(), FileStatus::Untracked),
+            ]
+        );
+    }
+
+    #[test]
+    fn commit_allow_empty_records_unchanged_tree() {
+        let mut repo = setup();
+        let root = repo.commit_allow_empty("root", "test").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        let first = repo.commit("add a", "test").unwrap();
+        let empty = repo.commit_allow_empty("checkpoint", "test").unwrap();
+
+        let log = repo.log(10).unwrap();
+        let shas: Vec<_> = log.iter().map(|c| c.sha.as_str()).collect();
+        assert_eq!(shas, [empty.as_str(), first.as_str(), root.as_str()]);
+        assert_eq!(log[0].summary, "checkpoint");
+        assert!(repo.diff_range(&first, &empty).unwrap().is_empty());
+        assert!(repo.diff_commit(&root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn diff_staged_shows_additions() {
+        let mut repo = setup();
+        repo.filesystem_mut()
+            .write_file("f.txt", b"line1\nline2\n")
+            .unwrap();
+        repo.stage_file("f.txt").unwrap();
+
+        let diffs = repo.diff_staged().unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].status, FileStatus::Added);
+        assert!(!diffs[0].hunks.is_empty());
+        assert!(diffs[0].hunks[0].lines.iter().all(|l| l.starts_with('+')));
+    }
+
+    #[test]
+    fn diff_unstaged_shows_modifications() {
+        let mut repo = setup();
+        repo.filesystem_mut()
+            .write_file("f.txt", b"line1\n")
+            .unwrap();
+        repo.stage_file("f.txt").unwrap();
+        repo.commit("init", "test").unwrap();
+
+        // Modify the file in the working tree.
+        repo.filesystem_mut()
+            .write_file("f.txt", b"line1\nline2\n")
+            .unwrap();
+
+        let diffs = repo.diff_unstaged().unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].status, FileStatus::Modified);
+        assert!(!diffs[0].hunks.is_empty());
+    }
+
+    #[test]
+    fn log_returns_commits_newest_first() {
+        let mut repo = setup();
+        repo.filesystem_mut()
+            .write_file("a.txt", b"v1")
+            .unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.commit("first", "alice").unwrap();
+
+        repo.filesystem_mut()
+            .write_file("a.txt", b"v2")
+            .unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.commit("second", "bob").unwrap();
+
+        let log = repo.log(10).unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].summary, "second");
+        assert_eq!(log[0].author, "bob");
+        assert_eq!(log[1].summary, "first");
+        assert_eq!(log[1].author, "alice");
+    }
+
+    #[test]
+    fn shortlog_counts_commits_per_author() {
+        let mut repo = setup();
+        repo.commit_allow_empty("one", "bob").unwrap();
+        repo.commit_allow_empty("two", "alice").unwrap();
+        repo.commit_allow_empty("three", "alice").unwrap();
+        assert_eq!(repo.shortlog(), [("alice".to_string(), 2), ("bob".to_string(), 1)]);
+    }
+
+    #[test]
+    fn unstage_reverts_to_head() {
+        let mut repo = setup();
+        repo.filesystem_mut()
+            .write_file("f.txt", b"original")
+            .unwrap();
+        repo.stage_file("f.txt").unwrap();
+        repo.commit("init", "test").unwrap();
+
+        // Modify and stage.
+        repo.filesystem_mut()
+            .write_file("f.txt", b"changed")
+            .unwrap();
+        repo.stage_file("f.txt").unwrap();
+
+        // Staged diff should show a change.
+        assert!(!repo.diff_staged().unwrap().is_empty());
+
+        // Unstage should revert index to HEAD.
+        repo.unstage_file("f.txt").unwrap();
+        assert!(repo.diff_staged().unwrap().is_empty());
+    }
+
+    #[test]
+    fn staged_deletion_is_committed() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"b").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.stage_file("b.txt").unwrap();
+        repo.commit("init", "test").unwrap();
+
+        repo.filesystem_mut().remove_file("a.txt").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        let st = repo.status().unwrap();
+        assert_eq!(st.len(), 1);
+        assert_eq!((st[0].status, st[0].staged), (FileStatus::Deleted, true));
+        assert!(repo.diff_unstaged().unwrap().is_empty());
+
+        let sha = repo.commit("remove a", "test").unwrap();
+        let tree = &repo.find_commit(&sha).unwrap().tree;
+        assert!(!tree.contains_key("a.txt") && tree.contains_key("b.txt"));
+        assert!(repo.status().unwrap().is_empty());
+    }
+
+    #[test]
+    fn stage_files_is_all_or_nothing() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"b").unwrap();
+
+        let err = repo.stage_files(&["a.txt", "missing.txt", "b.txt"]).unwrap_err();
+        assert_eq!(err, GitError::PathNotFound("missing.txt".to_string()));
+        assert!(repo.status().unwrap().iter().all(|e| !e.staged));
+
+        repo.stage_files(&["a.txt", "b.txt"]).unwrap();
+        assert!(repo.status().unwrap().iter().all(|e| e.staged));
+    }
+
+    #[test]
+    fn unstaging_a_deletion_restores_the_index() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.commit("init", "test").unwrap();
+
+        repo.filesystem_mut().remove_file("a.txt").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.unstage_file("a.txt").unwrap();
+
+        let st = repo.status().unwrap();
+        assert_eq!(st.len(), 1);
+        assert_eq!((st[0].status, st[0].staged), (FileStatus::Deleted, false));
+        assert!(repo.diff_staged().unwrap().is_empty());
+        assert_eq!(repo.commit("noop", "test"), Err(GitError::NothingToCommit));
+    }
+
+    #[test]
+    fn diff_options_group_by_status() {
+        let mut repo = setup();
+        for path in ["a.txt", "c.txt", "e.txt"] {
+            repo.filesystem_mut().write_file(path, b"old\n").unwrap();
+            repo.stage_file(path).unwrap();
+        }
+        repo.commit("base", "test").unwrap();
+
+        let fs = repo.filesystem_mut();
+        fs.write_file("b.txt", b"new\n").unwrap();
+        fs.write_file("c.txt", b"changed\n").unwrap();
+        fs.remove_file("a.txt").unwrap();
+        fs.write_file("d.txt", b"new\n").unwrap();
+        fs.remove_file("e.txt").unwrap();
+        for path in ["a.txt", "b.txt", "c.txt", "d.txt", "e.txt"] {
+            repo.stage_file(path).unwrap();
+        }
+
+        let order = |repo: &InMemoryGitRepository| -> Vec<(String, FileStatus)> {
+            let diffs = repo.diff_staged().unwrap();
+            diffs.into_iter().map(|d| (d.path, d.status)).collect()
+        };
+        let by_path: Vec<_> = order(&repo).into_iter().map(|(p, _)| p).collect();
+        assert_eq!(by_path, ["a.txt", "b.txt", "c.txt", "d.txt", "e.txt"]);
+
+        repo.set_diff_options(DiffOptions { group_by_status: true, ..DiffOptions::default() });
+        assert_eq!(
+            order(&repo),
+            [
+                ("c.txt".to_string(), FileStatus::Modified),
+                ("b.txt".to_string(), FileStatus::Added),
+                ("d.txt".to_string(), FileStatus::Added),
+                ("a.txt".to_string(), FileStatus::Deleted),
+                ("e.txt".to_string(), FileStatus::Deleted),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_repository_reports_no_commits() {
+        let mut repo = setup();
+        assert!(!repo.has_commits());
+        assert!(repo.log(10).unwrap().is_empty());
+        assert_eq!(repo.diff_commit("0000000"), Err(GitError::NotInitialised));
+        assert_eq!(repo.reset("HEAD", ResetMode::Hard), Err(GitError::NotInitialised));
+        let amended = repo.amend("msg", "test", AmendOptions::default());
+        assert_eq!(amended, Err(GitError::NotInitialised));
+
+        repo.commit_allow_empty("root", "test").unwrap();
+        assert!(repo.has_commits());
+        let reset = repo.reset("HEAD~1", ResetMode::Soft);
+        assert_eq!(reset, Err(GitError::InvalidRevision("HEAD~1".into())));
+    }
+
+    #[test]
+    fn diff_commit_shows_changes() {
+        let mut repo = setup();
+        repo.filesystem_mut()
+            .write_file("f.txt", b"v1\n")
+            .unwrap();
+        repo.stage_file("f.txt").unwrap();
+        let sha1 = repo.commit("first", "test").unwrap();
+
+        repo.filesystem_mut()
+            .write_file("f.txt", b"v2\n")
+            .unwrap();
+        repo.stage_file("f.txt").unwrap();
+        let sha2 = repo.commit("second", "test").unwrap();
+
+        // First commit should show added file.
+        let d1 = repo.diff_commit(&sha1).unwrap();
+        assert_eq!(d1.len(), 1);
+        assert_eq!(d1[0].status, FileStatus::Added);
+
+        // Second commit should show modification.
+        let d2 = repo.diff_commit(&sha2).unwrap();
+        assert_eq!(d2.len(), 1);
+        assert_eq!(d2[0].status, FileStatus::Modified);
+    }
+
+    #[test]
+    fn checkout_tree_previews_an_older_commit() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"v1").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        let first = repo.commit("first", "t").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"v2").unwrap();
+        repo.filesystem_mut().create_dir_all("src").unwrap();
+        repo.filesystem_mut().write_file("src/later.txt", b"new").unwrap();
+        repo.stage_files(&["a.txt", "src/later.txt"]).unwrap();
+        let second = repo.commit("second", "t").unwrap();
+        repo.filesystem_mut().write_file("notes.txt", b"mine").unwrap();
+
+        repo.checkout_tree(&first).unwrap();
+        let fs = repo.filesystem();
+        assert_eq!(fs.read_file("a.txt").unwrap(), b"v1");
+        assert!(!fs.is_file("src/later.txt"));
+        assert!(fs.is_file("notes.txt"));
+        assert_eq!(repo.head_sha(), Some(second.as_str()));
+
+        assert!(matches!(repo.checkout_tree(&second), Err(GitError::Conflict(_))));
+        repo.reset("HEAD", ResetMode::Hard).unwrap();
+        assert_eq!(repo.filesystem().read_file("src/later.txt").unwrap(), b"new");
+        assert!(repo.filesystem().is_file("notes.txt"));
+    }
+
+    #[test]
+    fn checkout_tree_refuses_to_overwrite_uncommitted_changes() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"v1").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        let first = repo.commit("first", "t").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"edited").unwrap();
+        repo.filesystem_mut().write_file("new.txt", b"staged").unwrap();
+        repo.stage_file("new.txt").unwrap();
+
+        let err = repo.checkout_tree(&first).unwrap_err();
+        assert_eq!(err, GitError::Conflict(vec!["a.txt".to_string(), "new.txt".to_string()]));
+        assert_eq!(repo.filesystem().read_file("a.txt").unwrap(), b"edited");
+        assert!(repo.filesystem().is_file("new.txt"));
+    }
+
+    #[test]
+    fn checkout_past_commit_detaches_head() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("f.txt", b"one\n").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        let first = repo.commit("one", "t").unwrap();
+        repo.filesystem_mut().write_file("f.txt", b"two\n").unwrap();
+        repo.filesystem_mut().write_file("g.txt", b"new\n").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        repo.stage_file("g.txt").unwrap();
+        let second = repo.commit("two", "t").unwrap();
+        assert_eq!(repo.current_branch(), Some("main"));
+
+        repo.checkout(&first).unwrap();
+        assert!(repo.is_detached());
+        assert_eq!(repo.current_branch(), None);
+        assert_eq!(repo.head_sha(), Some(first.as_str()));
+        assert_eq!(repo.filesystem().read_file("f.txt").unwrap(), b"one\n");
+        assert!(!repo.filesystem().exists("g.txt"));
+        assert_eq!(repo.log(10).unwrap().len(), 1);
+
+        // A detached commit moves only HEAD; a branch keeps it.
+        repo.filesystem_mut().write_file("f.txt", b"side\n").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        let side = repo.commit("side", "t").unwrap();
+        assert_eq!(repo.head_ref(), &HeadRef::Detached(side.clone()));
+        repo.create_branch("side").unwrap();
+
+        repo.filesystem_mut().write_file("f.txt", b"dirty\n").unwrap();
+        assert_eq!(repo.checkout("main"), Err(GitError::Conflict(vec!["f.txt".into()])));
+        repo.filesystem_mut().write_file("f.txt", b"side\n").unwrap();
+        repo.checkout("main").unwrap();
+        assert!(!repo.is_detached());
+        assert_eq!(repo.head_sha(), Some(second.as_str()));
+        assert_eq!(repo.filesystem().read_file("g.txt").unwrap(), b"new\n");
+    }
+
+    #[test]
+    fn reflog_records_commit_and_reset() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("f.txt", b"one\n").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        let first = repo.commit("one", "t").unwrap();
+        repo.filesystem_mut().write_file("f.txt", b"two\n").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        let second = repo.commit("two", "t").unwrap();
+
+        repo.reset("HEAD~1", ResetMode::Mixed).unwrap();
+        let log = repo.reflog();
+        assert_eq!(log.len(), 3);
+        assert_eq!((log[0].old.as_deref(), log[0].new.as_str()), (Some(&*second), &*first));
+        assert_eq!(log[0].action, "reset: moving to HEAD~1");
+        assert_eq!((log[1].old.as_deref(), log[1].new.as_str()), (Some(&*first), &*second));
+        assert_eq!(log[1].action, "commit: two");
+        assert_eq!(log[2].old, None);
+        assert_eq!(repo.status_short().unwrap(), " M f.txt\n");
+
+        // Undo the reset through the reflog.
+        repo.reset("HEAD@{1}", ResetMode::Hard).unwrap();
+        assert_eq!(repo.head_sha(), Some(second.as_str()));
+        assert_eq!(repo.filesystem().read_file("f.txt").unwrap(), b"two\n");
+        assert!(repo.status().unwrap().is_empty());
+    }
+
+    #[test]
+    fn soft_and_hard_reset() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        let first = repo.commit("a", "t").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"b\n").unwrap();
+        repo.stage_file("b.txt").unwrap();
+        repo.commit("b", "t").unwrap();
+
+        repo.reset(&first, ResetMode::Soft).unwrap();
+        assert_eq!(repo.status_short().unwrap(), "A  b.txt\n");
+        repo.filesystem_mut().write_file("u.txt", b"u\n").unwrap();
+        repo.reset("HEAD", ResetMode::Hard).unwrap();
+        assert!(!repo.filesystem().exists("b.txt"));
+        assert_eq!(repo.status_short().unwrap(), "?? u.txt\n");
+    }
+
+    #[test]
+    fn plan_commit_leaves_history_untouched() {
+        let mut repo = setup();
+        assert_eq!(repo.plan_commit(), Err(GitError::NothingToCommit));
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+
+        let plan = repo.plan_commit().unwrap();
+        assert_eq!(plan.diffs, repo.diff_staged().unwrap());
+        assert_eq!(plan.diffs[0].path, "a.txt");
+        assert!(plan.conflicts.is_empty());
+        assert_eq!(repo.head_sha(), None);
+        assert!(repo.log(10).unwrap().is_empty());
+        assert_eq!(repo.status_short().unwrap(), "A  a.txt\n");
+    }
+
+    #[test]
+    fn plan_merge_and_cherry_pick_report_conflicts() {
+        let mut repo = setup();
+        commit_files(&mut repo, &[("a.txt", "a\n"), ("b.txt", "b\n")]);
+        repo.create_branch("dev").unwrap();
+        commit_files(&mut repo, &[("a.txt", "main\n")]);
+        repo.checkout("dev").unwrap();
+        let new_file = commit_files(&mut repo, &[("c.txt", "c\n")]);
+        commit_files(&mut repo, &[("a.txt", "dev\n"), ("b.txt", "dev\n")]);
+        repo.checkout("main").unwrap();
+        let head = repo.head_sha().map(str::to_string);
+
+        let merge = repo.plan_merge("dev").unwrap();
+        assert_eq!(merge.conflicts, ["a.txt"]);
+        let paths: Vec<&str> = merge.diffs.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, ["b.txt", "c.txt"]);
+
+        let pick = repo.plan_cherry_pick(&new_file).unwrap();
+        assert!(pick.conflicts.is_empty());
+        assert_eq!(pick.diffs[0].status, FileStatus::Added);
+        assert_eq!(repo.head_sha().map(str::to_string), head);
+        assert!(!repo.filesystem().exists("c.txt"));
+    }
+
+    #[test]
+    fn aborting_a_conflicting_merge_restores_head() {
+        let mut repo = setup();
+        commit_files(&mut repo, &[("a.txt", "a\n"), ("b.txt", "b\n")]);
+        repo.create_branch("dev").unwrap();
+        commit_files(&mut repo, &[("a.txt", "main\n")]);
+        repo.checkout("dev").unwrap();
+        commit_files(&mut repo, &[("a.txt", "dev\n"), ("c.txt", "c\n")]);
+        repo.checkout("main").unwrap();
+        let head = repo.head_sha().map(str::to_string);
+
+        let plan = repo.start_merge("dev").unwrap();
+        assert_eq!(plan.conflicts, ["a.txt"]);
+        let fs = repo.filesystem();
+        assert_eq!(
+            fs.read_to_string("a.txt").unwrap(),
+            "<<<<<<< HEAD\nmain\n=======\ndev\n>>>>>>> dev\n"
+        );
+        assert_eq!(fs.read_to_string("c.txt").unwrap(), "c\n");
+        assert!(matches!(repo.pending_operation(), Some(PendingOperation::Merge(_))));
+        assert!(repo.abort_cherry_pick().is_err());
+
+        repo.abort_merge().unwrap();
+        assert_eq!(repo.pending_operation(), None);
+        assert!(repo.status().unwrap().is_empty());
+        assert_eq!(repo.filesystem().read_to_string("a.txt").unwrap(), "main\n");
+        assert!(!repo.filesystem().exists("c.txt"));
+        assert_eq!(repo.head_sha().map(str::to_string), head);
+        assert!(repo.abort_merge().is_err());
+    }
+
+    #[test]
+    fn conflicted_merge_commits_only_once_resolved() {
+        let mut repo = setup();
+        commit_files(&mut repo, &[("a.txt", "a\n"), ("b.txt", "b\n")]);
+        repo.create_branch("dev").unwrap();
+        commit_files(&mut repo, &[("a.txt", "main\n")]);
+        repo.checkout("dev").unwrap();
+        let theirs = commit_files(&mut repo, &[("a.txt", "dev\n"), ("b.txt", "dev\n")]);
+        repo.checkout("main").unwrap();
+        let ours = repo.head_sha().unwrap().to_string();
+
+        repo.start_merge("dev").unwrap();
+        let conflict = Err(GitError::Conflict(vec!["a.txt".to_string()]));
+        assert_eq!(repo.commit("merge", "t"), conflict);
+        assert_eq!(repo.commit_allow_empty("merge", "t"), conflict);
+        // Nothing may move HEAD under the pending merge.
+        assert!(repo.checkout("dev").is_err());
+        assert!(repo.amend("x", "t", AmendOptions::default()).is_err());
+        assert!(repo.commit_with(TreeSnapshot::new(), vec![], "x", "t").is_err());
+        assert!(repo.reorder_commits(&[&ours]).is_err());
+        assert_eq!(repo.head_sha(), Some(ours.as_str()));
+
+        repo.filesystem_mut().write_file("a.txt", b"both\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        let sha = repo.commit("merge", "t").unwrap();
+        assert_eq!(repo.find_commit(&sha).unwrap().parents, [ours, theirs]);
+        assert_eq!(repo.pending_operation(), None);
+        assert!(repo.status().unwrap().is_empty());
+    }
+
+    #[test]
+    fn aborted_merge_restores_raw_working_files() {
+        let mut repo = setup();
+        repo.set_autocrlf(AutoCrlf::Input);
+        repo.filesystem_mut().write_file("a.txt", b"x\r\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.commit("base", "t").unwrap();
+        repo.create_branch("dev").unwrap();
+        repo.checkout("dev").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"y\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.commit("dev", "t").unwrap();
+        repo.checkout("main").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"x\r\n").unwrap();
+        assert!(repo.status().unwrap().is_empty());
+
+        repo.start_merge("dev").unwrap();
+        assert_eq!(repo.filesystem().read_file("a.txt").unwrap(), b"y\n");
+        repo.abort_merge().unwrap();
+        assert_eq!(repo.filesystem().read_file("a.txt").unwrap(), b"x\r\n");
+    }
+
+    #[test]
+    fn merge_refuses_to_overwrite_local_files() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file(".gitignore", b"*.log\n").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.stage_files(&[".gitignore", "a.txt"]).unwrap();
+        repo.commit("base", "t").unwrap();
+        repo.create_branch("dev").unwrap();
+        repo.checkout("dev").unwrap();
+        // The branch tracks a file the ignore rules match.
+        repo.filesystem_mut().write_file("a.txt", b"dev\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.filesystem_mut().write_file("out.log", b"theirs\n").unwrap();
+        let mut tree = repo.index.clone();
+        tree.insert("out.log".to_string(), repo.write_blob(b"theirs\n"));
+        let head = repo.head_sha().unwrap().to_string();
+        repo.commit_with(tree, vec![head], "dev", "t").unwrap();
+        repo.checkout("main").unwrap();
+
+        // An ignored, untracked file in the way.
+        repo.filesystem_mut().write_file("out.log", b"mine\n").unwrap();
+        let err = repo.start_merge("dev").unwrap_err();
+        assert_eq!(err, GitError::Conflict(vec!["out.log".into()]));
+        assert_eq!(repo.filesystem().read_file("out.log").unwrap(), b"mine\n");
+        assert_eq!(repo.pending_operation(), None);
+
+        // An uncommitted edit to a tracked file.
+        repo.filesystem_mut().remove_file("out.log").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"edited\n").unwrap();
+        let err = repo.start_merge("dev").unwrap_err();
+        assert_eq!(err, GitError::Conflict(vec!["a.txt".into()]));
+        assert_eq!(repo.filesystem().read_file("a.txt").unwrap(), b"edited\n");
+    }
+
+    #[test]
+    fn resolved_merge_commits_with_two_parents() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        let base = repo.commit("base", "t").unwrap();
+        repo.create_branch("dev").unwrap();
+        repo.checkout("dev").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"b\n").unwrap();
+        repo.stage_file("b.txt").unwrap();
+        let dev = repo.commit("dev", "t").unwrap();
+        repo.checkout("main").unwrap();
+
+        // A cherry-pick is aborted; a later merge runs to completion.
+        repo.start_cherry_pick(&dev).unwrap();
+        assert!(repo.start_merge("dev").is_err());
+        repo.abort_cherry_pick().unwrap();
+        assert!(!repo.filesystem().exists("b.txt"));
+
+        assert!(repo.start_merge("dev").unwrap().conflicts.is_empty());
+        let merge = repo.commit("merge dev", "t").unwrap();
+        assert_eq!(repo.find_commit(&merge).unwrap().parents, [base, dev]);
+        assert_eq!(repo.pending_operation(), None);
+    }
+
+    #[test]
+    fn amend_keeps_author_by_default() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.commit("first", "alice").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"b\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+
+        repo.amend("first, fixed", "bob", AmendOptions::default()).unwrap();
+        let log = repo.log(10).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!((log[0].summary.as_str(), log[0].author.as_str()), ("first, fixed", "alice"));
+        assert!(repo.status().unwrap().is_empty());
+        assert_eq!(repo.reflog()[0].action, "commit (amend): first, fixed");
+    }
+
+    #[test]
+    fn amend_can_reset_author() {
+        let mut repo = setup();
+        assert!(repo.amend("x", "bob", AmendOptions::default()).is_err());
+        repo.commit_allow_empty("root", "alice").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        let original = repo.commit("second", "alice").unwrap();
+
+        let amended = repo.amend("second", "bob", AmendOptions { reset_author: true }).unwrap();
+        assert_ne!(amended, original);
+        let log = repo.log(10).unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].author, "bob");
+        assert_eq!(repo.diff_commit(&amended).unwrap()[0].path, "a.txt");
+    }
+
+    #[test]
+    fn merge_base_finds_lowest_common_ancestor() {
+        let mut repo = setup();
+        let commit = |repo: &mut InMemoryGitRepository, s: &str, parents: Vec<String>| {
+            let tree = TreeSnapshot::from([("f.txt".into(), repo.write_blob(s.as_bytes()))]);
+            repo.commit_with(tree, parents, s, "t").unwrap()
+        };
+        let root = commit(&mut repo, "root", vec![]);
+        let fork = commit(&mut repo, "fork", vec![root.clone()]);
+        let left = commit(&mut repo, "l1", vec![fork.clone()]);
+        let left = commit(&mut repo, "l2", vec![left]);
+        let right = commit(&mut repo, "r1", vec![fork.clone()]);
+        let merge = commit(&mut repo, "merge", vec![right.clone(), left.clone()]);
+        let lonely = commit(&mut repo, "unrelated", vec![]);
+
+        assert_eq!(repo.merge_base(&left, &right).unwrap(), Some(fork.clone()));
+        assert_eq!(repo.merge_base(&right, &left).unwrap(), Some(fork));
+        assert_eq!(repo.merge_base(&merge, &left).unwrap(), Some(left));
+        assert_eq!(repo.merge_base(&root, &merge).unwrap(), Some(root));
+        assert_eq!(repo.merge_base(&lonely, &merge).unwrap(), None);
+    }
+
+    #[test]
+    fn identical_contents_share_one_blob() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"same").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"same").unwrap();
+        repo.stage_files(&["a.txt", "b.txt"]).unwrap();
+        repo.commit("twins", "test").unwrap();
+        repo.filesystem_mut().write_file("c.txt", b"same").unwrap();
+        repo.stage_file("c.txt").unwrap();
+        let sha = repo.commit("triplets", "test").unwrap();
+
+        assert_eq!(repo.blob_count(), 1);
+        let tree = &repo.find_commit(&sha).unwrap().tree;
+        assert_eq!(repo.blob(tree["a.txt"]), Some(&b"same"[..]));
+        assert!(tree.values().all(|id| *id == tree["c.txt"]));
+    }
+
+    #[test]
+    fn reorder_reports_progress_per_commit() {
+        let mut repo = setup();
+        let mut shas = Vec::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            repo.filesystem_mut().write_file(name, name.as_bytes()).unwrap();
+            repo.stage_file(name).unwrap();
+            shas.push(repo.commit(name, "test").unwrap());
+        }
+        let head_tree = repo.find_commit(&shas[2]).unwrap().tree.clone();
+
+        let mut reports = Vec::new();
+        let order = [shas[2].as_str(), shas[0].as_str(), shas[1].as_str()];
+        let head = repo.reorder_commits_with_progress(&order, |p| reports.push(p)).unwrap();
+
+        let steps: Vec<_> = reports.iter().map(|p| (p.done, p.total)).collect();
+        assert_eq!(steps, [(1, 3), (2, 3), (3, 3)]);
+        assert_eq!(reports[2].ratio(), 1.0);
+        let summaries: Vec<_> = repo.log(10).unwrap().into_iter().map(|c| c.summary).collect();
+        assert_eq!(summaries, ["b.txt", "a.txt", "c.txt"]);
+        assert_eq!(repo.find_commit(&head).unwrap().tree, head_tree);
+        assert!(repo.status().unwrap().is_empty());
+    }
+
+    #[test]
+    fn diff_working_tree_against_older_commit() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"v1\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        let first = repo.commit("first", "test").unwrap();
+        repo.create_tag("v1").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"b\n").unwrap();
+        repo.stage_file("b.txt").unwrap();
+        repo.commit("second", "test").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"v2\n").unwrap();
 
-        // Determine old_start / new_start from the first edit in the hunk.
-        let (old_start, new_start) = match &edit_script[ctx_before_start] {
-            Edit::Equal(o, n) => (*o + 1, *n + 1),
-            Edit::Insert(_, n) => (if *n > 0 { *n } else { 0 }, *n + 1),
-            Edit::Delete(o, _) => (*o + 1, if *o > 0 { *o } else { 0 }),
-        };
+        let diffs = repo.diff_working_vs_commit("v1").unwrap();
+        let summary: Vec<_> = diffs.iter().map(|d| (d.path.as_str(), d.status)).collect();
+        assert_eq!(summary, [("a.txt", FileStatus::Modified), ("b.txt", FileStatus::Added)]);
+        assert_eq!(repo.diff_working_vs_commit("HEAD~1").unwrap(), diffs);
+        assert_eq!(repo.resolve("main^").unwrap(), first);
+        assert_eq!(repo.resolve("HEAD~2"), Err(GitError::InvalidRevision("HEAD~2".into())));
+    }
 
-        let mut lines = Vec::new();
-        for edit in &edit_script[ctx_before_start..ctx_after_end] {
-            match edit {
-                Edit::Equal(o, _) => lines.push(format!(" {}\n", old_lines[*o])),
-                Edit::Delete(o, _) => lines.push(format!("-{}\n", old_lines[*o])),
-                Edit::Insert(_, n) => lines.push(format!("+{}\n", new_lines[*n])),
-            }
+    #[test]
+    fn invalid_ref_names_are_rejected() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.commit("first", "test").unwrap();
+
+        for name in ["my branch", "a~1", "a^", "a:b", "a..b", "/a", "a/", ""] {
+            assert!(repo.create_branch(name).is_err(), "{name:?}");
+            assert!(repo.create_tag(name).is_err(), "{name:?}");
         }
+        let err = repo.create_branch("fix..it").unwrap_err();
+        assert_eq!(
+            err,
+            GitError::InvalidRefName {
+                kind: "branch",
+                name: "fix..it".into(),
+                reason: "contains `..`".into(),
+            }
+        );
+        assert_eq!(err.to_string(), "invalid branch name \"fix..it\": contains `..`");
+        assert!(repo.branches.keys().eq(["main"]));
+    }
 
-        hunks.push(DiffHunk {
-            old_start,
-            new_start,
-            lines,
-        });
+    #[test]
+    fn valid_ref_names_are_accepted() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("a.txt", b"a\n").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        repo.commit("first", "test").unwrap();
 
-        i = ctx_after_end;
+        repo.create_branch("feature/login-form").unwrap();
+        repo.create_tag("v1.2.3").unwrap();
+        assert!(repo.branches.keys().eq(["feature/login-form", "main"]));
+        assert!(repo.tags.contains_key("v1.2.3"));
     }
 
-    hunks
-}
+    #[test]
+    fn describe_counts_commits_since_tag() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("f.txt", b"1").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        let first = repo.commit("one", "test").unwrap();
+        assert_eq!(repo.describe(&first).unwrap(), short_sha(&first));
 
-// ── Minimal LCS diff ─────────────────────────────────────────────────────────
+        repo.create_tag("v1.0").unwrap();
+        assert_eq!(repo.describe(&first).unwrap(), "v1.0");
+        repo.commit_allow_empty("two", "test").unwrap();
+        let third = repo.commit_allow_empty("three", "test").unwrap();
+        assert_eq!(
+            repo.describe(&third).unwrap(),
+            format!("v1.0-2-g{}", short_sha(&third))
+        );
+    }
 
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-enum Edit {
-    Equal(usize, usize),  // (old_idx, new_idx)
-    Delete(usize, usize), // (old_idx, new_idx – positional context)
-    Insert(usize, usize), // (old_idx – positional context, new_idx)
-}
+    #[test]
+    fn commit_with_builds_merge_commit() {
+        let mut repo = setup();
+        let base_blob = repo.write_blob(b"base\n");
+        let ours_blob = repo.write_blob(b"ours\n");
+        let theirs_blob = repo.write_blob(b"theirs\n");
+        let base_tree = TreeSnapshot::from([("f.txt".into(), base_blob)]);
+        let base = repo.commit_with(base_tree, vec![], "base", "t").unwrap();
+        let ours = repo
+            .commit_with(
+                TreeSnapshot::from([("f.txt".into(), ours_blob)]),
+                vec![base.clone()],
+                "ours",
+                "t",
+            )
+            .unwrap();
+        let theirs = repo
+            .commit_with(
+                TreeSnapshot::from([("f.txt".into(), base_blob), ("g.txt".into(), theirs_blob)]),
+                vec![base],
+                "theirs",
+                "t",
+            )
+            .unwrap();
+        let merged =
+            TreeSnapshot::from([("f.txt".into(), ours_blob), ("g.txt".into(), theirs_blob)]);
+        let merge = repo
+            .commit_with(merged, vec![ours.clone(), theirs.clone()], "merge", "t")
+            .unwrap();
 
-/// Compute a line-level edit script using the classic LCS dynamic-programming
-/// algorithm.  Good enough for the typical diff sizes encountered in a TUI.
-fn lcs_diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit> {
-    let m = old.len();
-    let n = new.len();
+        // `diff_commit` compares against the first parent: only g.txt arrives.
+        let d = repo.diff_commit(&merge).unwrap();
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].path, "g.txt");
+        assert_eq!(d[0].status, FileStatus::Added);
 
-    // Build LCS table.
-    let mut table = vec![vec![0u32; n + 1]; m + 1];
-    for i in (0..m).rev() {
-        for j in (0..n).rev() {
-            if old[i] == new[j] {
-                table[i][j] = table[i + 1][j + 1] + 1;
-            } else {
-                table[i][j] = table[i + 1][j].max(table[i][j + 1]);
-            }
-        }
+        // Against the second parent only f.txt differs.
+        let d = repo.diff_range(&theirs, &merge).unwrap();
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].path, "f.txt");
+        assert_eq!(d[0].status, FileStatus::Modified);
+
+        // HEAD moved to the merge, so nothing is staged.
+        assert!(repo.diff_staged().unwrap().is_empty());
+        assert!(repo.commit_with(TreeSnapshot::new(), vec!["nope".into()], "x", "t").is_err());
+        let dangling = TreeSnapshot::from([("f.txt".into(), base_blob.wrapping_add(1))]);
+        assert!(repo.commit_with(dangling, vec![], "x", "t").is_err());
     }
 
-    // Backtrack to produce the edit script.
-    let mut edits = Vec::new();
-    let mut i = 0;
-    let mut j = 0;
-    while i < m || j < n {
-        if i < m && j < n && old[i] == new[j] {
-            edits.push(Edit::Equal(i, j));
-            i += 1;
-            j += 1;
-        } else if j < n && (i >= m || table[i][j + 1] >= table[i + 1][j]) {
-            edits.push(Edit::Insert(i, j));
-            j += 1;
-        } else {
-            edits.push(Edit::Delete(i, j));
-            i += 1;
+    #[test]
+    fn diff_modified_produces_correct_hunks() {
+        let hunks = diff_modified("a\nb\nc\n", "a\nB\nc\n", &LcsDiff);
+        assert_eq!(hunks.len(), 1);
+        let lines = &hunks[0].lines;
+        assert!(lines.iter().any(|l| l.starts_with("-b")));
+        assert!(lines.iter().any(|l| l.starts_with("+B")));
+    }
+
+    #[test]
+    fn myers_and_lcs_agree() {
+        let old = "a\nb\nc\nd\ne\nf\n";
+        let new = "a\nc\nd\nX\ne\nf\ng\n";
+        assert_eq!(diff_modified(old, new, &LcsDiff), diff_modified(old, new, &MyersDiff));
+
+        // Both scripts are minimal and rebuild the new side.
+        let old: Vec<&str> = "x a b c a b b a y".split(' ').collect();
+        let new: Vec<&str> = "c b a b a c z".split(' ').collect();
+        let equal = |edits: &[Edit]| edits.iter().filter(|e| matches!(e, Edit::Equal(..))).count();
+        let myers = MyersDiff.diff(&old, &new);
+        assert_eq!(equal(&myers), equal(&LcsDiff.diff(&old, &new)));
+        let rebuilt: Vec<&str> = myers
+            .iter()
+            .filter_map(|e| match *e {
+                Edit::Equal(o, _) => Some(old[o]),
+                Edit::Insert(_, n) => Some(new[n]),
+                Edit::Delete(..) => None,
+            })
+            .collect();
+        assert_eq!(rebuilt, new);
+        assert!(MyersDiff.diff(&[], &[]).is_empty());
+
+        // Pseudo-random pairs over a small alphabet reach every diagonal.
+        let mut seed = 7u32;
+        let mut lines = |len: usize| -> Vec<&str> {
+            (0..len)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    ["a", "b", "c"][(seed >> 16) as usize % 3]
+                })
+                .collect()
+        };
+        for len in 0..40 {
+            let (old, new) = (lines(len), lines(40 - len));
+            assert_eq!(equal(&MyersDiff.diff(&old, &new)), equal(&LcsDiff.diff(&old, &new)));
         }
     }
 
-    edits
-}
+    #[test]
+    fn algorithm_is_selected_through_diff_options() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("f.txt", b"a\nb\nc\n").unwrap();
+        repo.stage_file("f.txt").unwrap();
+        repo.commit("init", "t").unwrap();
+        repo.filesystem_mut().write_file("f.txt", b"a\nB\nc\n").unwrap();
+        let lcs = repo.diff_unstaged().unwrap();
+        repo.set_diff_options(DiffOptions { algorithm: &MyersDiff, ..DiffOptions::default() });
+        assert_eq!(repo.diff_unstaged().unwrap(), lcs);
+        assert_ne!(DiffOptions::default().algorithm.name(), "myers");
+    }
 
-// ── Tests ────────────────────────────────────────────────────────────────────
+    #[test]
+    fn max_lines_replaces_oversized_diffs_with_a_placeholder() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("big.txt", b"1\n2\n3\n4\n").unwrap();
+        repo.filesystem_mut().write_file("small.txt", b"a\n").unwrap();
+        repo.stage_files(&["big.txt", "small.txt"]).unwrap();
+        repo.commit("init", "t").unwrap();
+        repo.filesystem_mut().write_file("big.txt", b"5\n6\n7\n8\n").unwrap();
+        repo.filesystem_mut().write_file("small.txt", b"b\n").unwrap();
+        repo.set_diff_options(DiffOptions { max_lines: Some(3), ..DiffOptions::default() });
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::fs::Filesystem;
+        let diffs = repo.diff_unstaged().unwrap();
+        assert_eq!(diffs[0].path, "big.txt");
+        assert!(diffs[0].is_too_large());
+        assert_eq!(diffs[0].hunks[0].lines, [TOO_LARGE_TO_DIFF]);
+        assert_eq!(diffs[1].path, "small.txt");
+        assert!(!diffs[1].is_too_large());
+        assert_eq!(diffs[1].hunks[0].lines, ["-a\n", "+b\n"]);
+    }
 
-    fn setup() -> InMemoryGitRepository {
-        let fs = MemoryFilesystem::new();
-        InMemoryGitRepository::new(fs)
+    #[test]
+    fn inserted_function_hunk_ends_on_blank_line() {
+        let old = "fn a() {\n}\n\nfn c() {\n}\n";
+        let new = "fn a() {\n}\n\nfn b() {\n}\n\nfn c() {\n}\n";
+        let hunks = diff_modified(old, new, &LcsDiff);
+        assert_eq!(hunks.len(), 1);
+        let added: Vec<_> = hunks[0]
+            .lines
+            .iter()
+            .filter(|l| l.starts_with('+'))
+            .map(String::as_str)
+            .collect();
+        assert_eq!(added, vec!["+fn b() {\n", "+}\n", "+\n"]);
     }
 
+    const TWO_CONFLICTS: &str = "\
+header
+<<<<<<< ours
+a1
+=======
+a2
+>>>>>>> theirs
+middle
+<<<<<<< HEAD
+b1
+||||||| base
+b0
+=======
+b2
+b3
+>>>>>>> feature
+footer
+";
+
     #[test]
-    fn status_empty_repo() {
-        let repo = setup();
-        let st = repo.status().unwrap();
-        assert!(st.is_empty());
+    fn parse_conflicts_finds_both_blocks() {
+        let blocks = parse_conflicts(TWO_CONFLICTS);
+        assert_eq!(blocks.len(), 2);
+
+        assert_eq!(blocks[0].start_line, 1);
+        assert_eq!(blocks[0].ours, vec!["a1"]);
+        assert_eq!(blocks[0].theirs, vec!["a2"]);
+        assert_eq!(blocks[0].context_before, vec!["header"]);
+        assert_eq!(blocks[0].context_after, vec!["middle"]);
+
+        // The diff3 base section is not part of either side.
+        assert_eq!(blocks[1].ours, vec!["b1"]);
+        assert_eq!(blocks[1].theirs, vec!["b2", "b3"]);
+        assert_eq!(blocks[1].context_before, vec!["middle"]);
+        assert_eq!(blocks[1].context_after, vec!["footer"]);
     }
 
     #[test]
-    fn status_untracked_file() {
+    fn resolve_conflict_three_ways() {
+        assert_eq!(
+            resolve_conflict(TWO_CONFLICTS, Side::Ours),
+            "header\na1\nmiddle\nb1\nfooter\n"
+        );
+        assert_eq!(
+            resolve_conflict(TWO_CONFLICTS, Side::Theirs),
+            "header\na2\nmiddle\nb2\nb3\nfooter\n"
+        );
+        assert_eq!(
+            resolve_conflict(TWO_CONFLICTS, Side::Union),
+            "header\na1\na2\nmiddle\nb1\nb2\nb3\nfooter\n"
+        );
+        // Files without (complete) conflicts pass through untouched.
+        let clean = "x\n<<<<<<< dangling\ny";
+        assert_eq!(resolve_conflict(clean, Side::Theirs), clean);
+        assert!(parse_conflicts(clean).is_empty());
+    }
+
+    #[test]
+    fn status_short_codes() {
         let mut repo = setup();
-        repo.filesystem_mut()
-            .write_file("hello.txt", b"world")
-            .unwrap();
-        let st = repo.status().unwrap();
-        assert_eq!(st.len(), 1);
-        assert_eq!(st[0].path, "hello.txt");
-        assert_eq!(st[0].status, FileStatus::Untracked);
-        assert!(!st[0].staged);
+        repo.filesystem_mut().write_file("both.rs", b"v1").unwrap();
+        repo.filesystem_mut().write_file("gone.rs", b"x").unwrap();
+        repo.stage_file("both.rs").unwrap();
+        repo.stage_file("gone.rs").unwrap();
+        repo.commit("init", "test").unwrap();
+
+        repo.filesystem_mut().write_file("both.rs", b"v2").unwrap();
+        repo.stage_file("both.rs").unwrap();
+        repo.filesystem_mut().write_file("both.rs", b"v3").unwrap();
+        repo.filesystem_mut().remove_file("gone.rs").unwrap();
+        repo.filesystem_mut().write_file("new.txt", b"hi").unwrap();
+
+        assert_eq!(repo.status_short().unwrap(), "MM both.rs\n D gone.rs\n?? new.txt\n");
     }
 
     #[test]
-    fn stage_and_commit() {
+    fn status_short_keeps_a_staged_deletion_beside_the_recreated_file() {
         let mut repo = setup();
-        repo.filesystem_mut()
-            .write_file("a.txt", b"hello")
-            .unwrap();
+        commit_files(&mut repo, &[("a.txt", "old"), ("b.txt", "b")]);
+        repo.filesystem_mut().remove_file("a.txt").unwrap();
         repo.stage_file("a.txt").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"new").unwrap();
+        repo.filesystem_mut().write_file("b.txt", b"changed").unwrap();
 
-        // Should show as staged Added.
-        let st = repo.status().unwrap();
-        let staged: Vec<_> = st.iter().filter(|e| e.staged).collect();
-        assert_eq!(staged.len(), 1);
-        assert_eq!(staged[0].status, FileStatus::Added);
+        assert_eq!(repo.status_short().unwrap(), "D  a.txt\n?? a.txt\n M b.txt\n");
+    }
 
-        let sha = repo.commit("initial", "test").unwrap();
-        assert!(!sha.is_empty());
+    #[test]
+    fn working_tree_cache_invalidates_on_write() {
+        let mut repo = setup();
+        repo.filesystem_mut().write_file("f.txt", b"one").unwrap();
+        let first = repo.working_tree();
+        assert!(Rc::ptr_eq(&first, &repo.working_tree()), "expected a cache hit");
 
-        // After commit, status should be clean.
-        let st = repo.status().unwrap();
-        let relevant: Vec<_> = st
-            .iter()
-            .filter(|e| e.status != FileStatus::Untracked)
-            .collect();
-        assert!(relevant.is_empty(), "expected clean status after commit");
+        repo.filesystem_mut().write_file("f.txt", b"two").unwrap();
+        let second = repo.working_tree();
+        assert!(!Rc::ptr_eq(&first, &second));
+        assert_eq!(second["f.txt"], b"two");
+        assert_eq!(repo.status().unwrap()[0].status, FileStatus::Untracked);
     }
 
     #[test]
-    fn nothing_to_commit() {
+    fn ignored_directories_are_never_scanned() {
         let mut repo = setup();
-        let err = repo.commit("empty", "test").unwrap_err();
-        assert_eq!(err, GitError::NothingToCommit);
+        let fs = repo.filesystem_mut();
+        fs.write_file(".gitignore", b"# deps\nnode_modules/\n*.log\n/build\n").unwrap();
+        for n in 0..200 {
+            let dir = format!("node_modules/pkg{n}/lib");
+            fs.create_dir_all(&dir).unwrap();
+            fs.write_file(&format!("{dir}/index.js"), b"x").unwrap();
+        }
+        fs.create_dir_all("src/build").unwrap();
+        fs.write_file("src/build/keep.rs", b"x").unwrap();
+        fs.write_file("src/debug.log", b"x").unwrap();
+        fs.write_file("src/main.rs", b"x").unwrap();
+
+        let work = repo.working_tree();
+        let scanned: Vec<&String> = work.keys().collect();
+        assert_eq!(scanned, [".gitignore", "src/build/keep.rs", "src/main.rs"]);
+        assert_eq!(repo.status_counts().unwrap().untracked, 3);
+
+        let rules = IgnoreRules::parse("node_modules/\n");
+        let mut visited = Vec::new();
+        let pruned = repo.walk_work_files(&rules, |path, _| visited.push(path.to_string()));
+        assert_eq!(pruned, ["node_modules"]);
+        assert_eq!(visited, [".gitignore", "src/build/keep.rs", "src/debug.log", "src/main.rs"]);
     }
 
     #[test]
-    fn diff_staged_shows_additions() {
+    fn committing_an_ignored_file_refreshes_the_working_tree() {
         let mut repo = setup();
-        repo.filesystem_mut()
-            .write_file("f.txt", b"line1\nline2\n")
-            .unwrap();
-        repo.stage_file("f.txt").unwrap();
+        repo.filesystem_mut().write_file(".gitignore", b"*.log\n").unwrap();
+        repo.filesystem_mut().write_file("d.log", b"d").unwrap();
+        let id = repo.write_blob(b"d");
+        let tree = TreeSnapshot::from([("d.log".to_string(), id)]);
+        repo.commit_with(tree, Vec::new(), "force-add log", "t").unwrap();
 
-        let diffs = repo.diff_staged().unwrap();
-        assert_eq!(diffs.len(), 1);
-        assert_eq!(diffs[0].status, FileStatus::Added);
-        assert!(!diffs[0].hunks.is_empty());
-        assert!(diffs[0].hunks[0].lines.iter().all(|l| l.starts_with('+')));
+        assert_eq!(repo.status_short().unwrap(), "?? .gitignore\n");
+        repo.filesystem_mut().write_file("d.log", b"e").unwrap();
+        assert_eq!(repo.status_short().unwrap(), "?? .gitignore\n M d.log\n");
     }
 
     #[test]
-    fn diff_unstaged_shows_modifications() {
+    fn tracked_files_in_ignored_directories_are_still_reported() {
         let mut repo = setup();
-        repo.filesystem_mut()
-            .write_file("f.txt", b"line1\n")
-            .unwrap();
-        repo.stage_file("f.txt").unwrap();
-        repo.commit("init", "test").unwrap();
-
-        // Modify the file in the working tree.
-        repo.filesystem_mut()
-            .write_file("f.txt", b"line1\nline2\n")
-            .unwrap();
+        repo.filesystem_mut().create_dir_all("vendor/lib").unwrap();
+        repo.filesystem_mut().write_file("vendor/lib/a.c", b"1").unwrap();
+        repo.stage_file("vendor/lib/a.c").unwrap();
+        repo.commit("vendor", "t").unwrap();
 
-        let diffs = repo.diff_unstaged().unwrap();
-        assert_eq!(diffs.len(), 1);
-        assert_eq!(diffs[0].status, FileStatus::Modified);
-        assert!(!diffs[0].hunks.is_empty());
+        repo.filesystem_mut().write_file(".gitignore", b"vendor/\n").unwrap();
+        repo.filesystem_mut().write_file("vendor/lib/a.c", b"2").unwrap();
+        repo.filesystem_mut().write_file("vendor/lib/new.c", b"x").unwrap();
+        assert_eq!(repo.status_short().unwrap(), "?? .gitignore\n M vendor/lib/a.c\n");
+        assert!(glob_match("a/**/b*.rs", "a/x/y/bc.rs"));
+        assert!(!glob_match("*.rs", "src/a.rs"));
     }
 
     #[test]
-    fn log_returns_commits_newest_first() {
+    fn is_tracked_covers_head_and_index() {
         let mut repo = setup();
-        repo.filesystem_mut()
-            .write_file("a.txt", b"v1")
-            .unwrap();
-        repo.stage_file("a.txt").unwrap();
-        repo.commit("first", "alice").unwrap();
+        repo.filesystem_mut().write_file("committed.txt", b"c").unwrap();
+        repo.stage_file("committed.txt").unwrap();
+        repo.commit("init", "t").unwrap();
+        repo.filesystem_mut().write_file("staged.txt", b"s").unwrap();
+        repo.stage_file("staged.txt").unwrap();
+        repo.filesystem_mut().write_file("untracked.txt", b"u").unwrap();
 
-        repo.filesystem_mut()
-            .write_file("a.txt", b"v2")
-            .unwrap();
-        repo.stage_file("a.txt").unwrap();
-        repo.commit("second", "bob").unwrap();
+        assert!(repo.is_tracked("committed.txt"));
+        assert!(repo.is_tracked("staged.txt"));
+        assert!(!repo.is_tracked("untracked.txt"));
+        assert!(!repo.is_tracked("missing.txt"));
+    }
 
-        let log = repo.log(10).unwrap();
-        assert_eq!(log.len(), 2);
-        assert_eq!(log[0].summary, "second");
-        assert_eq!(log[0].author, "bob");
-        assert_eq!(log[1].summary, "first");
-        assert_eq!(log[1].author, "alice");
+    #[test]
+    fn display_lines_strip_prefixes_and_newlines() {
+        let hunk = DiffHunk {
+            old_start: 1,
+            new_start: 1,
+            lines: vec![" keep\n".into(), "-old\n".into(), "+new\n".into(), "+ indented".into()],
+        };
+        assert_eq!(
+            hunk.display_lines(),
+            [
+                (LineKind::Context, "keep"),
+                (LineKind::Removed, "old"),
+                (LineKind::Added, "new"),
+                (LineKind::Added, " indented"),
+            ]
+        );
     }
 
     #[test]
-    fn unstage_reverts_to_head() {
+    fn has_changes_is_false_exactly_when_clean() {
         let mut repo = setup();
-        repo.filesystem_mut()
-            .write_file("f.txt", b"original")
-            .unwrap();
-        repo.stage_file("f.txt").unwrap();
-        repo.commit("init", "test").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"1").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        assert_eq!((repo.has_staged_changes(), repo.has_unstaged_changes()), (Ok(true), Ok(false)));
+        repo.commit("init", "t").unwrap();
+        assert!(repo.diff_staged().unwrap().is_empty() && repo.diff_unstaged().unwrap().is_empty());
+        assert_eq!(repo.has_changes(), Ok(false));
 
-        // Modify and stage.
-        repo.filesystem_mut()
-            .write_file("f.txt", b"changed")
-            .unwrap();
-        repo.stage_file("f.txt").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"2").unwrap();
+        assert_eq!((repo.has_staged_changes(), repo.has_unstaged_changes()), (Ok(false), Ok(true)));
+        assert_eq!(repo.has_changes(), Ok(true));
+    }
 
-        // Staged diff should show a change.
-        assert!(!repo.diff_staged().unwrap().is_empty());
+    #[test]
+    fn working_tree_revision_tracks_filesystem_writes() {
+        let mut repo = setup();
+        let before = repo.working_tree_revision();
+        repo.status().unwrap();
+        assert_eq!(repo.working_tree_revision(), before);
 
-        // Unstage should revert index to HEAD.
-        repo.unstage_file("f.txt").unwrap();
-        assert!(repo.diff_staged().unwrap().is_empty());
+        repo.filesystem_mut().write_file("a.txt", b"x").unwrap();
+        let after = repo.working_tree_revision();
+        assert_ne!(after, before);
+        repo.stage_file("a.txt").unwrap();
+        assert_eq!(repo.working_tree_revision(), after);
     }
 
     #[test]
-    fn diff_commit_shows_changes() {
+    fn commits_without_an_identity_are_rejected() {
         let mut repo = setup();
-        repo.filesystem_mut()
-            .write_file("f.txt", b"v1\n")
-            .unwrap();
-        repo.stage_file("f.txt").unwrap();
-        let sha1 = repo.commit("first", "test").unwrap();
+        repo.filesystem_mut().write_file("a.txt", b"a").unwrap();
+        repo.stage_file("a.txt").unwrap();
+        assert_eq!(repo.require_identity(), Err(GitError::MissingIdentity));
+        assert_eq!(repo.commit("init", ""), Err(GitError::MissingIdentity));
+        assert!(repo.log(1).unwrap().is_empty());
 
-        repo.filesystem_mut()
-            .write_file("f.txt", b"v2\n")
-            .unwrap();
-        repo.stage_file("f.txt").unwrap();
-        let sha2 = repo.commit("second", "test").unwrap();
+        repo.set_default_author(Some("Ann <ann@example.com>".to_string()));
+        repo.commit("init", " ").unwrap();
+        assert_eq!(repo.log(1).unwrap()[0].author, "Ann <ann@example.com>");
+        repo.commit_allow_empty("explicit", "Bo").unwrap();
+        assert_eq!(repo.log(1).unwrap()[0].author, "Bo");
+    }
 
-        // First commit should show added file.
-        let d1 = repo.diff_commit(&sha1).unwrap();
-        assert_eq!(d1.len(), 1);
-        assert_eq!(d1[0].status, FileStatus::Added);
+    #[test]
+    fn absolute_and_relative_paths_name_the_same_entry() {
+        let mut repo = setup();
+        repo.filesystem_mut().create_dir_all("/src").unwrap();
+        repo.filesystem_mut().write_file("/src/a.rs", b"fn a() {}").unwrap();
+        repo.stage_file("/src/a.rs").unwrap();
+        repo.stage_files(&["//src/a.rs"]).unwrap();
+        repo.commit("add", "t").unwrap();
 
-        // Second commit should show modification.
-        let d2 = repo.diff_commit(&sha2).unwrap();
-        assert_eq!(d2.len(), 1);
-        assert_eq!(d2[0].status, FileStatus::Modified);
+        assert!(repo.status().unwrap().is_empty());
+        assert!(repo.is_tracked("src/a.rs") && repo.is_tracked("/src/a.rs"));
+
+        repo.filesystem_mut().write_file("src/a.rs", b"fn b() {}").unwrap();
+        repo.stage_file("src/a.rs").unwrap();
+        repo.unstage_file("/src/a.rs").unwrap();
+        assert_eq!(repo.status_short().unwrap(), " M src/a.rs\n");
+        assert_eq!(repo.file_hunks("/src/a.rs").unwrap().unstaged.len(), 1);
     }
 
     #[test]
-    fn diff_modified_produces_correct_hunks() {
-        let hunks = diff_modified("a\nb\nc\n", "a\nB\nc\n");
-        assert_eq!(hunks.len(), 1);
-        let lines = &hunks[0].lines;
-        assert!(lines.iter().any(|l| l.starts_with("-b")));
-        assert!(lines.iter().any(|l| l.starts_with("+B")));
+    fn commits_iter_is_lazy_and_matches_log() {
+        let mut repo = setup();
+        for n in 0..5 {
+            repo.commit_allow_empty(&format!("c{n}"), "t").unwrap();
+        }
+        let first_two: Vec<CommitInfo> = repo.commits_iter().take(2).collect();
+        assert_eq!(first_two, repo.log(2).unwrap());
+        assert_eq!(first_two[0].summary, "c4");
+
+        // Commits on another branch are not reachable from HEAD.
+        repo.create_branch("side").unwrap();
+        repo.checkout("side").unwrap();
+        repo.commit_allow_empty("side only", "t").unwrap();
+        repo.checkout("main").unwrap();
+        let summaries: Vec<String> = repo.commits_iter().map(|c| c.summary).collect();
+        assert_eq!(summaries, ["c4", "c3", "c2", "c1", "c0"]);
     }
 
     #[test]
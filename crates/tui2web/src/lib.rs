@@ -1,5 +1,15 @@
 mod backend;
+mod base64;
 pub mod fs;
 pub mod git;
+mod recorder;
+mod tar;
+mod width;
+mod zip;
 
-pub use backend::WebBackend;
+pub use backend::{
+    diff_cells, modifier_bits, BackendError, CellChange, CellRun, ControlChars, CursorStyle,
+    FrameSnapshot, WebBackend, MAX_CELLS,
+};
+pub use recorder::{FrameRecorder, RecordedFrame};
+pub use width::{display_width, shorten_path};
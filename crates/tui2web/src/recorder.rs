@@ -0,0 +1,123 @@
+//! Recording of flushed frames for replay or export as an
+//! [asciicast v2](https://docs.asciinema.org/manual/asciicast/v2/) file.
+
+use std::fmt::Write as _;
+
+/// One recorded frame and how long it stayed on screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedFrame {
+    /// When the frame was first shown, in milliseconds since recording began.
+    pub start_ms: f64,
+    /// How long the frame was shown: until the next different frame, or 0
+    /// for the latest one.
+    pub duration_ms: f64,
+    /// The ANSI output, as returned by
+    /// [`WebBackend::get_ansi_output`](crate::WebBackend::get_ansi_output).
+    pub frame: String,
+}
+
+/// Collects frames with their timestamps.
+///
+/// A frame byte-identical to the one before it adds no event; the previous
+/// event's duration is extended instead, so an idle app records nothing
+/// however often it flushes.
+#[derive(Debug, Clone, Default)]
+pub struct FrameRecorder {
+    events: Vec<RecordedFrame>,
+    /// Timestamp of the first [`record`](Self::record) call.
+    origin_ms: Option<f64>,
+}
+
+impl FrameRecorder {
+    /// Create an empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `frame` as shown at `now_ms` (any monotonic clock, e.g.
+    /// `performance.now()`).  Times are stored relative to the first call.
+    pub fn record(&mut self, now_ms: f64, frame: &str) {
+        let origin = *self.origin_ms.get_or_insert(now_ms);
+        let at = (now_ms - origin).max(0.0);
+        if let Some(last) = self.events.last_mut() {
+            last.duration_ms = (at - last.start_ms).max(0.0);
+            if last.frame == frame {
+                return;
+            }
+        }
+        self.events.push(RecordedFrame {
+            start_ms: at,
+            duration_ms: 0.0,
+            frame: frame.to_string(),
+        });
+    }
+
+    /// The recorded events, oldest first.
+    pub fn events(&self) -> &[RecordedFrame] {
+        &self.events
+    }
+
+    /// Render the recording as an asciicast v2 document for a `width` ×
+    /// `height` terminal: a JSON header line followed by one output event
+    /// per recorded frame.
+    pub fn to_asciicast(&self, width: u16, height: u16) -> String {
+        let mut out = format!("{{\"version\": 2, \"width\": {width}, \"height\": {height}}}\n");
+        for event in &self.events {
+            let _ = write!(out, "[{:.3}, \"o\", ", event.start_ms / 1000.0);
+            push_json_string(&mut out, &event.frame);
+            out.push_str("]\n");
+        }
+        out
+    }
+}
+
+/// Append `s` as a JSON string literal.
+fn push_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_frames_extend_the_previous_event() {
+        let mut recorder = FrameRecorder::new();
+        recorder.record(1000.0, "a");
+        recorder.record(1100.0, "a");
+        recorder.record(1250.0, "b");
+
+        let events = recorder.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].start_ms, events[0].duration_ms), (0.0, 250.0));
+        assert_eq!((events[1].start_ms, events[1].duration_ms), (250.0, 0.0));
+        assert_eq!(events[1].frame, "b");
+    }
+
+    #[test]
+    fn asciicast_escapes_control_sequences() {
+        let mut recorder = FrameRecorder::new();
+        recorder.record(0.0, "\x1b[1;1Hhi \"there\"\n");
+        recorder.record(1500.0, "\x07");
+
+        let cast = recorder.to_asciicast(80, 24);
+        let lines: Vec<&str> = cast.lines().collect();
+        assert_eq!(lines[0], "{\"version\": 2, \"width\": 80, \"height\": 24}");
+        assert_eq!(lines[1], "[0.000, \"o\", \"\\u001b[1;1Hhi \\\"there\\\"\\n\"]");
+        assert_eq!(lines[2], "[1.500, \"o\", \"\\u0007\"]");
+    }
+}
@@ -0,0 +1,221 @@
+//! Minimal reader for POSIX `ustar` archives.
+//!
+//! Only the subset needed to seed a [`MemoryFilesystem`](crate::fs::MemoryFilesystem)
+//! is supported: regular files and directories.  Other entry types (links,
+//! devices, PAX headers, …) are skipped.
+
+use crate::fs::{Filesystem, FsError};
+
+const BLOCK: usize = 512;
+
+/// The kind of a tar entry we care about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EntryKind {
+    File,
+    Dir,
+}
+
+/// A single decoded archive entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry<'a> {
+    /// Full path of the entry (`prefix/name`), guaranteed to be valid UTF-8.
+    pub path: String,
+    pub kind: EntryKind,
+    /// File contents (empty for directories).
+    pub data: &'a [u8],
+}
+
+/// Iterator over the entries of a tar archive held in memory.
+pub(crate) struct Entries<'a> {
+    data: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+/// Iterate over the file and directory entries of `data`.
+pub(crate) fn entries(data: &[u8]) -> Entries<'_> {
+    Entries {
+        data,
+        offset: 0,
+        done: false,
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<Entry<'a>, FsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let Some(header) = self.data.get(self.offset..self.offset + BLOCK) else {
+                self.done = true;
+                return None;
+            };
+            // A zero block marks the end of the archive.
+            if header.iter().all(|&b| b == 0) {
+                self.done = true;
+                return None;
+            }
+
+            let size = match parse_octal(&header[124..136]) {
+                Some(size) => size,
+                None => {
+                    self.done = true;
+                    return Some(Err(FsError::InvalidArchive(format!(
+                        "corrupt tar header at offset {}",
+                        self.offset
+                    ))));
+                }
+            };
+            let data_start = self.offset + BLOCK;
+            // The size comes from the archive, so the sum can overflow on
+            // 32-bit targets.
+            let Some(data_end) =
+                data_start.checked_add(size).filter(|&end| end <= self.data.len())
+            else {
+                self.done = true;
+                return Some(Err(FsError::InvalidArchive(format!(
+                    "truncated tar entry at offset {}",
+                    self.offset
+                ))));
+            };
+            self.offset = data_start + size.div_ceil(BLOCK) * BLOCK;
+
+            let kind = match header[156] {
+                b'0' | b'\0' | b'7' => EntryKind::File,
+                b'5' => EntryKind::Dir,
+                _ => continue,
+            };
+
+            let path = match entry_path(header) {
+                Ok(path) => path,
+                Err(e) => return Some(Err(e)),
+            };
+
+            return Some(Ok(Entry {
+                path,
+                kind,
+                data: &self.data[data_start..data_end],
+            }));
+        }
+        None
+    }
+}
+
+/// Unpacks a tar archive into a [`Filesystem`] a bounded number of entries at
+/// a time, so a large archive can be loaded across several frames while the
+/// UI shows progress.
+///
+/// Every header is checked by [`new`](Self::new), so a corrupt archive is
+/// rejected before anything is written.  Unlike
+/// [`MemoryFilesystem::import_tar`](crate::fs::MemoryFilesystem::import_tar),
+/// extraction is still not atomic: entries written by earlier steps stay in
+/// place if a later one cannot be written to the filesystem.
+pub struct TarExtractor<'a> {
+    entries: Entries<'a>,
+    done: usize,
+    total: usize,
+}
+
+/// Progress reported by [`TarExtractor::step`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractProgress {
+    /// Entries extracted so far.
+    pub done: usize,
+    /// Entries in the archive.
+    pub total: usize,
+    /// Path of the last entry extracted by this step, if any.
+    pub current_path: Option<String>,
+}
+
+impl ExtractProgress {
+    /// `true` once every entry has been extracted.
+    pub fn is_finished(&self) -> bool {
+        self.done == self.total
+    }
+}
+
+impl<'a> TarExtractor<'a> {
+    /// Prepare to extract `data`.  Only the headers are scanned up front, to
+    /// count the entries; a corrupt or truncated one yields
+    /// [`FsError::InvalidArchive`] (or [`FsError::InvalidPath`] for a
+    /// non-UTF-8 name) here rather than part-way through extraction.
+    pub fn new(data: &'a [u8]) -> Result<Self, FsError> {
+        let total = entries(data).try_fold(0, |n, entry| entry.map(|_| n + 1))?;
+        Ok(TarExtractor {
+            entries: entries(data),
+            done: 0,
+            total,
+        })
+    }
+
+    /// Extract up to `max_entries` more entries into `fs`, creating parent
+    /// directories as needed and overwriting existing files.
+    pub fn step<F: Filesystem + ?Sized>(
+        &mut self,
+        fs: &mut F,
+        max_entries: usize,
+    ) -> Result<ExtractProgress, FsError> {
+        let mut current_path = None;
+        for entry in self.entries.by_ref().take(max_entries) {
+            let entry = entry?;
+            let path = entry.path.trim_matches('/');
+            match entry.kind {
+                _ if path.is_empty() => {}
+                EntryKind::Dir => fs.create_dir_all(path)?,
+                EntryKind::File => {
+                    if let Some((parent, _)) = path.rsplit_once('/') {
+                        fs.create_dir_all(parent)?;
+                    }
+                    fs.write_file(path, entry.data)?;
+                }
+            }
+            self.done += 1;
+            current_path = Some(entry.path);
+        }
+        Ok(ExtractProgress {
+            done: self.done,
+            total: self.total,
+            current_path,
+        })
+    }
+}
+
+/// Decode the entry name (joined with the ustar `prefix` field, if any).
+///
+/// Names that are not valid UTF-8 are rejected with [`FsError::InvalidPath`]
+/// rather than being lossily converted, so that an imported path always
+/// round-trips unchanged.
+fn entry_path(header: &[u8]) -> Result<String, FsError> {
+    let name = until_nul(&header[0..100]);
+    let prefix = if &header[257..262] == b"ustar" {
+        until_nul(&header[345..500])
+    } else {
+        &[]
+    };
+
+    let mut raw = Vec::with_capacity(prefix.len() + 1 + name.len());
+    if !prefix.is_empty() {
+        raw.extend_from_slice(prefix);
+        raw.push(b'/');
+    }
+    raw.extend_from_slice(name);
+
+    String::from_utf8(raw).map_err(|e| {
+        FsError::InvalidPath(String::from_utf8_lossy(e.as_bytes()).into_owned())
+    })
+}
+
+fn until_nul(field: &[u8]) -> &[u8] {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    &field[..end]
+}
+
+/// Parse a NUL/space-terminated octal number field.
+fn parse_octal(field: &[u8]) -> Option<usize> {
+    let digits = until_nul(field);
+    let digits = std::str::from_utf8(digits).ok()?.trim();
+    if digits.is_empty() {
+        return Some(0);
+    }
+    usize::from_str_radix(digits, 8).ok()
+}
@@ -0,0 +1,72 @@
+//! Column-width rules shared by the ANSI serializer and app-side layout code.
+
+use unicode_width::UnicodeWidthChar;
+
+/// Number of terminal columns `s` occupies when rendered by
+/// [`WebBackend`](crate::WebBackend), starting at column 0.
+///
+/// Characters are measured with `unicode-width` (so CJK ideographs and most
+/// emoji count as two columns and combining marks as zero).  A `\t` counts
+/// as one column: ratatui gives it a single cell, which the serializer
+/// writes as a space.
+pub fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if c == '\t' { 1 } else { c.width().unwrap_or(0) }).sum()
+}
+
+/// Shorten a `/`-separated path to at most `max_width` columns (as measured
+/// by [`display_width`]) by replacing middle directories with `...`, e.g.
+/// `src/.../file.rs`, for a status pane in a narrow terminal.
+///
+/// The first directory and as many trailing components as fit are kept.
+/// The file name is never cut, so a name wider than `max_width` is
+/// returned on its own and still overflows.
+pub fn shorten_path(path: &str, max_width: usize) -> String {
+    if display_width(path) <= max_width {
+        return path.to_string();
+    }
+    let parts: Vec<&str> = path.split('/').collect();
+    let name = parts[parts.len() - 1];
+    let with_first = (1..parts.len().saturating_sub(1))
+        .rev()
+        .map(|keep| format!("{}/.../{}", parts[0], parts[parts.len() - keep..].join("/")));
+    with_first
+        .chain([format!(".../{name}")])
+        .find(|candidate| display_width(candidate) <= max_width)
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Columns covered by a cell holding `symbol`; never less than one.
+pub(crate) fn symbol_width(symbol: &str) -> usize {
+    display_width(symbol).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cjk_and_emoji_are_double_width() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("a😀b"), 4);
+        assert_eq!(display_width("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn shorten_path_elides_middle_directories() {
+        let path = "src/widgets/list/state/file.rs";
+        assert_eq!(shorten_path(path, 40), path);
+        assert_eq!(shorten_path(path, 30), path);
+        assert_eq!(shorten_path(path, 25), "src/.../state/file.rs");
+        assert_eq!(shorten_path(path, 15), "src/.../file.rs");
+        assert_eq!(shorten_path(path, 11), ".../file.rs");
+        assert_eq!(shorten_path(path, 4), "file.rs");
+    }
+
+    #[test]
+    fn tabs_are_one_column() {
+        assert_eq!(display_width("\t"), 1);
+        assert_eq!(display_width("ab\tc"), 4);
+        assert_eq!(display_width("日\t"), 3);
+    }
+}
@@ -0,0 +1,212 @@
+//! Minimal reader and writer for ZIP archives.
+//!
+//! Only the STORED (uncompressed) method is supported, which keeps the crate
+//! free of a compression dependency while still producing archives every
+//! browser and OS can open.  Deflated entries, ZIP64 and multi-disk archives
+//! are rejected with [`FsError::InvalidArchive`].
+
+use crate::fs::FsError;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIR: u32 = 0x0605_4b50;
+/// Format version 2.0, the first to support directories.
+const VERSION: u16 = 20;
+/// General-purpose flag bit 11: names are UTF-8.
+const UTF8_NAMES: u16 = 1 << 11;
+/// MS-DOS date of 1980-01-01, the earliest a ZIP can record.
+const DOS_EPOCH: u16 = (1 << 5) | 1;
+/// MS-DOS directory attribute.
+const DOS_DIR: u32 = 0x10;
+
+/// A decoded archive entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry<'a> {
+    /// Path without the trailing `/` of a directory entry, guaranteed to be
+    /// valid UTF-8.
+    pub path: String,
+    /// File contents, or `None` for a directory.
+    pub data: Option<&'a [u8]>,
+}
+
+/// Write `entries` (`(path, contents)`, with `None` contents for a
+/// directory) as a ZIP archive, in the order given.
+///
+/// Without ZIP64 an archive holds at most 65535 entries and 4 GiB, and a
+/// name at most 65535 bytes; anything larger is refused with
+/// [`FsError::InvalidArchive`] rather than written corrupt.
+pub(crate) fn write<'a>(
+    entries: impl IntoIterator<Item = (&'a str, Option<&'a [u8]>)>,
+) -> Result<Vec<u8>, FsError> {
+    let too_large = |what: &str| FsError::InvalidArchive(format!("{what} needs ZIP64"));
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    let mut count: u16 = 0;
+    for (path, data) in entries {
+        let name = match data {
+            Some(_) => path.to_string(),
+            None => format!("{path}/"),
+        };
+        let body = data.unwrap_or_default();
+        let offset = u32::try_from(out.len()).map_err(|_| too_large("archive size"))?;
+        if u16::try_from(name.len()).is_err() {
+            return Err(too_large(&format!("name of {path}")));
+        }
+        if u32::try_from(body.len()).is_err() {
+            return Err(too_large(&format!("size of {path}")));
+        }
+        count = count.checked_add(1).ok_or_else(|| too_large("entry count"))?;
+
+        put_u32(&mut out, LOCAL_HEADER);
+        put_entry_fields(&mut out, body, &name);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(body);
+
+        put_u32(&mut central, CENTRAL_HEADER);
+        put_u16(&mut central, VERSION); // made by
+        put_entry_fields(&mut central, body, &name);
+        put_u16(&mut central, 0); // comment length
+        put_u16(&mut central, 0); // disk number
+        put_u16(&mut central, 0); // internal attributes
+        put_u32(&mut central, if data.is_none() { DOS_DIR } else { 0 });
+        put_u32(&mut central, offset);
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = u32::try_from(out.len()).map_err(|_| too_large("archive size"))?;
+    let central_len = u32::try_from(central.len()).map_err(|_| too_large("archive size"))?;
+    out.extend_from_slice(&central);
+    put_u32(&mut out, END_OF_CENTRAL_DIR);
+    put_u16(&mut out, 0); // this disk
+    put_u16(&mut out, 0); // disk with the central directory
+    put_u16(&mut out, count);
+    put_u16(&mut out, count);
+    put_u32(&mut out, central_len);
+    put_u32(&mut out, central_offset);
+    put_u16(&mut out, 0); // comment length
+    Ok(out)
+}
+
+/// Decode every entry listed in the central directory of `data`.
+///
+/// Entry names that are not valid UTF-8 are rejected with
+/// [`FsError::InvalidPath`], as for tar archives.
+pub(crate) fn read(data: &[u8]) -> Result<Vec<Entry<'_>>, FsError> {
+    let truncated = || FsError::InvalidArchive("truncated zip archive".to_string());
+    let past = |at: usize, len: usize| at.checked_add(len).ok_or_else(truncated);
+    // The end record is 22 bytes plus a comment of up to 64 KiB.
+    let lowest = data.len().saturating_sub(22 + 0xffff);
+    let end = (lowest..data.len().saturating_sub(21))
+        .rev()
+        .find(|&at| u32_at(data, at) == Some(END_OF_CENTRAL_DIR))
+        .ok_or_else(|| FsError::InvalidArchive("not a zip archive".to_string()))?;
+    let count = u16_at(data, end + 10).ok_or_else(truncated)?;
+    let mut at = u32_at(data, end + 16).ok_or_else(truncated)? as usize;
+
+    let mut entries = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        if u32_at(data, at) != Some(CENTRAL_HEADER) {
+            return Err(FsError::InvalidArchive(format!("corrupt zip entry at offset {at}")));
+        }
+        let field = |offset: usize| u16_at(data, at + offset).map(usize::from);
+        let (method, name_len) = (field(10), field(28));
+        let (extra_len, comment_len) = (field(30), field(32));
+        let crc = u32_at(data, at + 16);
+        let (size, original_size) = (u32_at(data, at + 20), u32_at(data, at + 24));
+        let local = u32_at(data, at + 42).ok_or_else(truncated)? as usize;
+        let name_len = name_len.ok_or_else(truncated)?;
+        let name_start = past(at, 46)?;
+        let raw_name = data.get(name_start..past(name_start, name_len)?).ok_or_else(truncated)?;
+        let trailer = extra_len.ok_or_else(truncated)? + comment_len.ok_or_else(truncated)?;
+        at = past(name_start, name_len + trailer)?;
+
+        let name = String::from_utf8(raw_name.to_vec()).map_err(|e| {
+            FsError::InvalidPath(String::from_utf8_lossy(e.as_bytes()).into_owned())
+        })?;
+        if method != Some(0) || size != original_size {
+            return Err(FsError::InvalidArchive(format!("{name} is compressed")));
+        }
+
+        if u32_at(data, local) != Some(LOCAL_HEADER) {
+            return Err(FsError::InvalidArchive(format!("corrupt zip entry for {name}")));
+        }
+        let local_name = u16_at(data, past(local, 26)?).ok_or_else(truncated)?;
+        let local_extra = u16_at(data, past(local, 28)?).ok_or_else(truncated)?;
+        let start = past(local, 30 + usize::from(local_name) + usize::from(local_extra))?;
+        let size = size.ok_or_else(truncated)? as usize;
+        let body = data.get(start..past(start, size)?).ok_or_else(truncated)?;
+        if crc != Some(crc32(body)) {
+            return Err(FsError::InvalidArchive(format!("checksum mismatch for {name}")));
+        }
+
+        entries.push(match name.strip_suffix('/') {
+            Some(dir) => Entry { path: dir.to_string(), data: None },
+            None => Entry { path: name, data: Some(body) },
+        });
+    }
+    Ok(entries)
+}
+
+/// Append the header fields shared by local and central records, from
+/// "version needed" through "extra field length".
+fn put_entry_fields(out: &mut Vec<u8>, body: &[u8], name: &str) {
+    put_u16(out, VERSION);
+    put_u16(out, UTF8_NAMES);
+    put_u16(out, 0); // STORED
+    put_u16(out, 0); // time
+    put_u16(out, DOS_EPOCH);
+    put_u32(out, crc32(body));
+    put_u32(out, body.len() as u32);
+    put_u32(out, body.len() as u32);
+    put_u16(out, name.len() as u16);
+    put_u16(out, 0); // extra field length
+}
+
+fn put_u16(out: &mut Vec<u8>, n: u16) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, n: u32) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// The CRC-32 (IEEE) checksum ZIP records for each entry.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| {
+        (0..8).fold(crc ^ u32::from(b), |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_the_standard_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn too_many_entries_is_an_error() {
+        let names: Vec<String> = (0..=u16::MAX as usize).map(|i| i.to_string()).collect();
+        let fits = write(names[1..].iter().map(|name| (name.as_str(), None)));
+        assert_eq!(read(&fits.unwrap()).unwrap().len(), usize::from(u16::MAX));
+        let overflow = write(names.iter().map(|name| (name.as_str(), None)));
+        assert!(matches!(overflow, Err(FsError::InvalidArchive(_))));
+    }
+}
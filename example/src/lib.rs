@@ -3,7 +3,7 @@
 //! Demonstrates a simple counter TUI with keyboard navigation.
 //! Build with: `wasm-pack build --target web --out-dir ../web/pkg`
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 
 use ratatui::{
     layout::{Constraint, Direction, Layout},
@@ -12,8 +12,59 @@ use ratatui::{
     widgets::{Block, Borders, Gauge, Paragraph},
     Terminal,
 };
+use js_sys::{Array, Object, Uint8Array};
+use tui2web::fs::{Filesystem, MemoryFilesystem};
+use tui2web::git::{GitRepository, InMemoryGitRepository, Progress};
 use tui2web::WebBackend;
 use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// Time constant (ms) of the exponential ease applied to the gauge.
+const GAUGE_EASE_MS: f64 = 120.0;
+
+/// Period (ms) of the status-bar colour pulse.
+const PULSE_PERIOD_MS: f64 = 2000.0;
+
+/// Page colours, matching the xterm.js theme in `web/main.js`.
+const THEME_BACKGROUND: &str = "#1e1e2e";
+const THEME_FOREGROUND: &str = "#cdd6f4";
+
+/// Counter step for Shift+J / Shift+K.
+const BIG_STEP: i32 = 10;
+
+/// A key press normalised from a DOM `KeyboardEvent`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeyEvent {
+    key: String,
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+}
+
+impl KeyEvent {
+    /// Build an event from `KeyboardEvent.key` and its modifier flags.
+    ///
+    /// Browsers report Shift+J as `"J"`, so single upper-case letters are
+    /// folded to lower case with `shift` set; bindings then only need to
+    /// match the lower-case key.
+    fn new(key: &str, ctrl: bool, alt: bool, shift: bool) -> Self {
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_uppercase() => KeyEvent {
+                key: c.to_ascii_lowercase().to_string(),
+                ctrl,
+                alt,
+                shift: true,
+            },
+            _ => KeyEvent {
+                key: key.to_string(),
+                ctrl,
+                alt,
+                shift,
+            },
+        }
+    }
+}
 
 /// The WebAssembly-exported application struct.
 ///
@@ -25,8 +76,16 @@ use wasm_bindgen::prelude::*;
 /// app.tick();                 // initial render
 /// term.write(app.get_frame()); // write to xterm.js
 ///
+/// // Animated: drive the clock from requestAnimationFrame.
+/// requestAnimationFrame(function loop(now) {
+///     app.tick_with_time(now);
+///     term.write(app.get_frame());
+///     requestAnimationFrame(loop);
+/// });
+///
 /// term.onKey(({ domEvent }) => {
-///     app.push_key(domEvent.key);
+///     const { key, ctrlKey, altKey, shiftKey } = domEvent;
+///     app.push_key_mods(key, ctrlKey, altKey, shiftKey);
 ///     app.tick();
 ///     term.write(app.get_frame());
 /// });
@@ -34,25 +93,40 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
 pub struct App {
     terminal: Terminal<WebBackend>,
-    key_queue: VecDeque<String>,
+    key_queue: VecDeque<KeyEvent>,
     counter: i32,
     max_value: i32,
     should_quit: bool,
     status_message: String,
+    /// Set by Ctrl+R until the hard reset is confirmed or cancelled.
+    confirm_reset: bool,
+    /// Value currently shown by the gauge; eases toward `counter` over time.
+    displayed_value: f64,
+    /// Timestamp (ms) of the most recent [`App::tick_with_time`] call.
+    now_ms: Option<f64>,
+    /// Files created with [`App::create_file`], listed with their git
+    /// status in the Files pane.
+    repo: InMemoryGitRepository,
+    /// Progress of the last [`App::reorder_commits`], shown by the gauge in
+    /// place of the counter until the next key press.
+    rebase_progress: Option<Progress>,
 }
 
 #[wasm_bindgen]
 impl App {
     /// Create a new application with the given terminal dimensions (columns × rows).
+    ///
+    /// Throws in JavaScript if the terminal is too large to allocate.
     #[wasm_bindgen(constructor)]
-    pub fn new(width: u16, height: u16) -> App {
+    pub fn new(width: u16, height: u16) -> Result<App, JsValue> {
         // Redirect Rust panics to the browser console.
         console_error_panic_hook::set_once();
 
-        let backend = WebBackend::new(width, height);
+        let backend =
+            WebBackend::try_new(width, height).map_err(|e| JsValue::from_str(&e.to_string()))?;
         let terminal = Terminal::new(backend).unwrap();
 
-        App {
+        Ok(App {
             terminal,
             key_queue: VecDeque::new(),
             counter: 0,
@@ -61,23 +135,51 @@ impl App {
             status_message: String::from(
                 "Press j/↓ to increment · k/↑ to decrement · r to reset · q to quit",
             ),
-        }
+            confirm_reset: false,
+            displayed_value: 0.0,
+            now_ms: None,
+            repo: InMemoryGitRepository::new(MemoryFilesystem::new()),
+            rebase_progress: None,
+        })
     }
 
     /// Enqueue a keyboard event from JavaScript.
     ///
     /// Pass the value of `KeyboardEvent.key` (e.g. `"j"`, `"ArrowUp"`, `"Escape"`).
     pub fn push_key(&mut self, key: String) {
-        self.key_queue.push_back(key);
+        self.push_key_mods(key, false, false, false);
+    }
+
+    /// Like [`App::push_key`], but with the state of the Ctrl, Alt and Shift
+    /// modifiers (`KeyboardEvent.ctrlKey`, `.altKey`, `.shiftKey`).
+    pub fn push_key_mods(&mut self, key: String, ctrl: bool, alt: bool, shift: bool) {
+        self.key_queue.push_back(KeyEvent::new(&key, ctrl, alt, shift));
     }
 
     /// Process all pending key events, re-render the frame, and return `true`
     /// while the application is still running.
+    ///
+    /// Equivalent to [`App::tick_with_time`] without advancing the clock, so
+    /// animations hold still until a timestamp is supplied.
     pub fn tick(&mut self) -> bool {
+        let now_ms = self.now_ms.unwrap_or(0.0);
+        self.tick_with_time(now_ms)
+    }
+
+    /// Like [`App::tick`], but advances the animation clock to `now_ms`
+    /// (e.g. the `DOMHighResTimeStamp` passed to `requestAnimationFrame`).
+    ///
+    /// Animations are driven by elapsed time rather than frame count, so they
+    /// run at the same speed regardless of the display's refresh rate.
+    pub fn tick_with_time(&mut self, now_ms: f64) -> bool {
         while let Some(key) = self.key_queue.pop_front() {
             self.handle_input(&key);
         }
 
+        let elapsed_ms = self.now_ms.map_or(0.0, |prev| (now_ms - prev).max(0.0));
+        self.now_ms = Some(now_ms);
+        self.advance_animation(elapsed_ms);
+
         if !self.should_quit {
             self.render();
         }
@@ -85,6 +187,27 @@ impl App {
         !self.should_quit
     }
 
+    /// [`App::tick`] and [`App::get_frame`] in one call, for input handlers
+    /// that run often enough for the extra boundary crossing to matter:
+    /// ```js
+    /// term.write(app.tick_and_frame());
+    /// ```
+    ///
+    /// Returns an empty string once the application has quit.
+    pub fn tick_and_frame(&mut self) -> String {
+        if self.tick() {
+            self.get_frame()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Like [`App::tick_and_frame`], but returns the frame as UTF-8 bytes
+    /// (a `Uint8Array`), which xterm.js also accepts in `term.write`.
+    pub fn tick_and_frame_bytes(&mut self) -> Vec<u8> {
+        self.tick_and_frame().into_bytes()
+    }
+
     /// Return the latest ANSI-encoded terminal frame as a JavaScript string.
     ///
     /// Call this after [`tick`] and write the result to xterm.js:
@@ -95,53 +218,273 @@ impl App {
         self.terminal.backend().get_ansi_output().to_string()
     }
 
+    /// Return the `length` the string from [`get_frame`] will have in
+    /// JavaScript (UTF-16 code units), for preallocating buffers.
+    pub fn frame_utf16_len(&self) -> usize {
+        self.terminal.backend().frame_utf16_len()
+    }
+
+    /// Return a hash of the string [`get_frame`] would return, so an
+    /// unchanged frame can be skipped without crossing into JavaScript:
+    /// ```js
+    /// if (app.frame_hash() !== lastHash) term.write(app.get_frame());
+    /// ```
+    pub fn frame_hash(&self) -> u64 {
+        self.terminal.backend().frame_hash()
+    }
+
+    /// Return the current screen as a self-contained HTML fragment, e.g. for
+    /// a "share" button:
+    /// ```js
+    /// preview.innerHTML = app.snapshot_html();
+    /// ```
+    pub fn snapshot_html(&self) -> String {
+        format!(
+            "<pre class=\"tui2web-snapshot\" style=\"margin:0;padding:8px;\
+             font-family:monospace;line-height:1.1;\
+             background-color:{bg};color:{fg};--tui2web-bg:{bg};--tui2web-fg:{fg}\">\
+             {}</pre>",
+            self.terminal.backend().to_html(),
+            bg = THEME_BACKGROUND,
+            fg = THEME_FOREGROUND,
+        )
+    }
+
     /// Notify the application that the terminal has been resized.
-    pub fn resize(&mut self, width: u16, height: u16) {
-        self.terminal.backend_mut().resize(width, height);
+    ///
+    /// The current state is laid out again at the new size straight away,
+    /// so [`App::get_frame`] is ready without waiting for the next tick;
+    /// nothing is reset or reloaded.  A size over the backend's cell limit
+    /// is refused and the current size kept.
+    pub fn resize(&mut self, width: u16, height: u16) -> Result<(), JsValue> {
+        self.terminal
+            .backend_mut()
+            .try_resize(width, height)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
         let _ = self.terminal
             .resize(ratatui::layout::Rect::new(0, 0, width, height));
+        if !self.should_quit {
+            self.render();
+        }
+        Ok(())
     }
 
     /// Return `true` when the user has requested to quit.
     pub fn should_quit(&self) -> bool {
         self.should_quit
     }
+
+    /// Return the message currently shown in the status bar, e.g. to mirror
+    /// it elsewhere on the page.
+    pub fn status(&self) -> String {
+        self.status_message.clone()
+    }
+
+    /// Replace the status-bar message.  It is drawn by the next
+    /// [`App::tick`] and stays until a key press sets another one.
+    pub fn set_status(&mut self, msg: String) {
+        self.status_message = msg;
+    }
+
+    /// Create (or overwrite) the file at `path`, creating parent
+    /// directories as needed.  It appears in the Files pane, as untracked,
+    /// on the next [`App::tick`].
+    ///
+    /// Problems such as an empty path or a `..` component are reported in
+    /// the status bar rather than thrown.
+    pub fn create_file(&mut self, path: String, contents: String) {
+        self.status_message = match self.write_file(&path, contents.as_bytes()) {
+            Ok(()) => format!("Created {path}"),
+            Err(msg) => format!("Cannot create {path:?}: {msg}"),
+        };
+    }
+
+    /// Rewrite the newest commits in the order given (oldest first, as full
+    /// SHAs), like `git rebase -i`.  The gauge follows the rewrite, commit
+    /// by commit, on the next [`App::tick`].
+    ///
+    /// Problems such as a conflict are reported in the status bar rather
+    /// than thrown.
+    pub fn reorder_commits(&mut self, order: Vec<String>) {
+        let order: Vec<&str> = order.iter().map(String::as_str).collect();
+        let progress = &mut self.rebase_progress;
+        let result = self.repo.reorder_commits_with_progress(&order, |p| *progress = Some(p));
+        self.status_message = match result {
+            Ok(head) => {
+                let short = head.get(..7).unwrap_or(&head);
+                format!("Reordered {} commits, HEAD is now {short}", order.len())
+            }
+            Err(e) => format!("Cannot reorder commits: {e}"),
+        };
+    }
+}
+
+/// An in-memory filesystem that JavaScript can seed in one call.
+///
+/// ```js
+/// const fs = new WebFilesystem();
+/// fs.load_object({ "src/a.rs": "fn a() {}", "logo.png": pngBytes });
+/// ```
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WebFilesystem {
+    fs: MemoryFilesystem,
+}
+
+#[wasm_bindgen]
+impl WebFilesystem {
+    /// Create an empty filesystem.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WebFilesystem {
+        WebFilesystem::default()
+    }
+
+    /// Replace the contents with the files of a flat `{ path: contents }`
+    /// object.  Values may be strings or `Uint8Array`s; parent directories
+    /// are created automatically.
+    pub fn load_object(&mut self, obj: JsValue) -> Result<(), JsValue> {
+        let obj = obj
+            .dyn_into::<Object>()
+            .map_err(|_| JsValue::from_str("expected an object of path → contents"))?;
+        let mut files = Vec::new();
+        for entry in Object::entries(&obj).iter() {
+            let entry: Array = entry.unchecked_into();
+            let path = entry.get(0).as_string().unwrap_or_default();
+            let value = entry.get(1);
+            let bytes = if let Some(text) = value.as_string() {
+                text.into_bytes()
+            } else if let Some(array) = value.dyn_ref::<Uint8Array>() {
+                array.to_vec()
+            } else {
+                return Err(JsValue::from_str(&format!(
+                    "{path}: expected a string or Uint8Array"
+                )));
+            };
+            files.push((path, bytes));
+        }
+        self.fs = MemoryFilesystem::from_str_map(files)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(())
+    }
+
+    /// Every file path, in lexicographic order.
+    pub fn list_files(&self) -> Vec<String> {
+        self.fs.list_files()
+    }
+
+    /// Read a file as UTF-8 text.
+    pub fn read_to_string(&self, path: &str) -> Result<String, JsValue> {
+        self.fs
+            .read_to_string(path)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
 }
 
 // ── Private helpers ───────────────────────────────────────────────────────────
 
 impl App {
-    fn handle_input(&mut self, key: &str) {
-        match key {
-            "q" | "Escape" => {
-                self.should_quit = true;
-            }
-            "j" | "ArrowDown" => {
-                if self.counter < self.max_value {
-                    self.counter += 1;
-                }
-                self.status_message =
-                    format!("Counter: {}/{}", self.counter, self.max_value);
+    /// Validate `path` and write `contents` to it in the repository's
+    /// working tree.
+    fn write_file(&mut self, path: &str, contents: &[u8]) -> Result<(), String> {
+        if path.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+            return Err("expected a relative path without empty, `.` or `..` parts".to_string());
+        }
+
+        let fs = self.repo.filesystem_mut();
+        if let Some((parent, _)) = path.rsplit_once('/') {
+            fs.create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs.write_file(path, contents).map_err(|e| e.to_string())
+    }
+
+    /// One `XY path` line per file for the Files pane, using git's
+    /// short-status codes and blanks for unchanged files.
+    fn file_lines(&self) -> Vec<String> {
+        let status = self.repo.status_short().unwrap_or_default();
+        let codes: BTreeMap<&str, &str> = status
+            .lines()
+            .filter_map(|line| Some((line.get(3..)?, line.get(..2)?)))
+            .collect();
+        self.repo
+            .filesystem()
+            .list_files()
+            .into_iter()
+            .map(|path| format!("{} {path}", codes.get(path.as_str()).unwrap_or(&"  ")))
+            .collect()
+    }
+
+    fn handle_input(&mut self, event: &KeyEvent) {
+        self.rebase_progress = None;
+        if self.confirm_reset {
+            self.confirm_reset = false;
+            if event.key == "y" && !event.ctrl && !event.alt {
+                // A hard reset skips the gauge animation.
+                self.counter = 0;
+                self.displayed_value = 0.0;
+                self.status_message = String::from("Hard reset");
+            } else {
+                self.status_message = String::from("Reset cancelled");
             }
-            "k" | "ArrowUp" => {
-                if self.counter > 0 {
-                    self.counter -= 1;
-                }
-                self.status_message =
-                    format!("Counter: {}/{}", self.counter, self.max_value);
+            return;
+        }
+
+        let step = if event.shift { BIG_STEP } else { 1 };
+        match (event.key.as_str(), event.ctrl, event.alt) {
+            ("q" | "Escape", false, false) => {
+                self.should_quit = true;
             }
-            "r" => {
+            ("j" | "ArrowDown", false, false) => self.step_counter(step),
+            ("k" | "ArrowUp", false, false) => self.step_counter(-step),
+            ("r", false, false) => {
                 self.counter = 0;
                 self.status_message = String::from("Counter reset to 0");
             }
+            ("r", true, false) => {
+                self.confirm_reset = true;
+                self.status_message =
+                    String::from("Hard reset? Press y to confirm, any other key to cancel");
+            }
             _ => {}
         }
     }
 
+    /// Move the counter by `delta`, clamped to `0..=max_value`.  Rings the
+    /// bell when the step runs into either end.
+    fn step_counter(&mut self, delta: i32) {
+        let next = (self.counter + delta).clamp(0, self.max_value);
+        if next != self.counter + delta {
+            self.terminal.backend_mut().bell();
+        }
+        self.counter = next;
+        self.status_message = format!("Counter: {}/{}", self.counter, self.max_value);
+    }
+
+    /// Ease the gauge toward the counter by `elapsed_ms` worth of motion.
+    fn advance_animation(&mut self, elapsed_ms: f64) {
+        let target = f64::from(self.counter);
+        let blend = 1.0 - (-elapsed_ms / GAUGE_EASE_MS).exp();
+        self.displayed_value += (target - self.displayed_value) * blend;
+        if (target - self.displayed_value).abs() < 0.01 {
+            self.displayed_value = target;
+        }
+    }
+
+    /// Status-bar colour, pulsing smoothly between two greys.
+    fn status_color(&self) -> Color {
+        let now_ms = self.now_ms.unwrap_or(0.0);
+        let phase = (now_ms / PULSE_PERIOD_MS * std::f64::consts::TAU).sin() * 0.5 + 0.5;
+        let level = 150 + (phase * 80.0) as u8;
+        Color::Rgb(level, level, level)
+    }
+
     fn render(&mut self) {
         let counter = self.counter;
+        let displayed = self.displayed_value;
         let max_value = self.max_value;
         let status = self.status_message.clone();
+        let status_color = self.status_color();
+        let files = self.file_lines();
+        let rebase_progress = self.rebase_progress;
 
         self.terminal
             .draw(|frame| {
@@ -168,10 +511,16 @@ impl App {
                 frame.render_widget(title, chunks[0]);
 
                 // ── Progress gauge ───────────────────────────────────────────
-                let percent = if max_value > 0 {
-                    (counter * 100 / max_value).clamp(0, 100) as u16
-                } else {
-                    0
+                let (ratio, label) = match rebase_progress {
+                    Some(progress) => (
+                        progress.ratio(),
+                        format!("{}/{} commits rewritten", progress.done, progress.total),
+                    ),
+                    None if max_value > 0 => (
+                        (displayed / f64::from(max_value)).clamp(0.0, 1.0),
+                        format!("{}/{}", displayed.round(), max_value),
+                    ),
+                    None => (0.0, format!("{}/{}", displayed.round(), max_value)),
                 };
                 let gauge = Gauge::default()
                     .block(Block::default().borders(Borders::ALL).title(" Progress "))
@@ -180,8 +529,8 @@ impl App {
                             .fg(Color::Green)
                             .bg(Color::Black),
                     )
-                    .percent(percent)
-                    .label(format!("{}/{}", counter, max_value));
+                    .ratio(ratio)
+                    .label(label);
                 frame.render_widget(gauge, chunks[1]);
 
                 // ── Counter and key bindings ─────────────────────────────────
@@ -214,6 +563,15 @@ impl App {
                         ),
                         Span::raw("  Decrement"),
                     ]),
+                    Line::from(vec![
+                        Span::styled(
+                            "  J / K",
+                            Style::default()
+                                .fg(Color::Green)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw("  Step by 10"),
+                    ]),
                     Line::from(vec![
                         Span::styled(
                             "  r    ",
@@ -223,6 +581,15 @@ impl App {
                         ),
                         Span::raw("  Reset to 0"),
                     ]),
+                    Line::from(vec![
+                        Span::styled(
+                            "  ^R   ",
+                            Style::default()
+                                .fg(Color::Blue)
+                                .add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw("  Hard reset (asks to confirm)"),
+                    ]),
                     Line::from(vec![
                         Span::styled(
                             "  q    ",
@@ -235,12 +602,25 @@ impl App {
                 ];
                 let content = Paragraph::new(lines)
                     .block(Block::default().borders(Borders::ALL).title(" Counter "));
-                frame.render_widget(content, chunks[2]);
+                if files.is_empty() {
+                    frame.render_widget(content, chunks[2]);
+                } else {
+                    // ── Files created from JavaScript, with git status ───────
+                    let panes = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+                        .split(chunks[2]);
+                    frame.render_widget(content, panes[0]);
+                    let lines: Vec<Line> = files.into_iter().map(Line::from).collect();
+                    let file_pane = Paragraph::new(lines)
+                        .block(Block::default().borders(Borders::ALL).title(" Files "));
+                    frame.render_widget(file_pane, panes[1]);
+                }
 
                 // ── Status bar ───────────────────────────────────────────────
                 let status_widget = Paragraph::new(Span::styled(
                     format!(" {}", status),
-                    Style::default().fg(Color::Gray),
+                    Style::default().fg(status_color),
                 ))
                 .block(Block::default().borders(Borders::ALL));
                 frame.render_widget(status_widget, chunks[3]);
@@ -248,3 +628,172 @@ impl App {
             .unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gauge_eases_toward_counter_over_time() {
+        let mut app = App::new(60, 20).unwrap();
+        app.tick_with_time(0.0);
+        for _ in 0..10 {
+            app.push_key("j".to_string());
+        }
+
+        let mut previous = app.displayed_value;
+        for step in 1..=5 {
+            app.tick_with_time(f64::from(step) * 50.0);
+            let shown = app.displayed_value;
+            assert!(shown > previous, "gauge should move toward the counter");
+            assert!(shown < 10.0, "gauge should not jump straight to the target");
+            previous = shown;
+        }
+
+        app.tick_with_time(5000.0);
+        assert_eq!(app.displayed_value, 10.0);
+        assert!(app.get_frame().contains("10/100"));
+    }
+
+    #[test]
+    fn snapshot_html_contains_styled_counter() {
+        let mut app = App::new(60, 20).unwrap();
+        for _ in 0..7 {
+            app.push_key("j".to_string());
+        }
+        app.tick();
+
+        let html = app.snapshot_html();
+        assert!(html.starts_with("<pre class=\"tui2web-snapshot\""));
+        assert!(html.contains("background-color:#1e1e2e"));
+        assert!(html.contains("Current value: <span style=\"color:#cdcd00;font-weight:bold\">7<"));
+    }
+
+    #[test]
+    fn resize_redraws_without_resetting_state() {
+        let mut app = App::new(60, 20).unwrap();
+        for _ in 0..3 {
+            app.push_key("j".to_string());
+        }
+        app.tick();
+        app.set_status("kept".to_string());
+
+        app.resize(80, 30).unwrap();
+        assert_eq!((app.counter, app.status()), (3, "kept".to_string()));
+        let frame = app.get_frame();
+        assert!(frame.contains("\x1b[30;1H"), "frame should cover 30 rows");
+        assert!(!frame.contains("\x1b[31;1H"));
+        assert!(frame.contains("kept"));
+        assert_eq!(app.terminal.size().unwrap(), ratatui::layout::Rect::new(0, 0, 80, 30));
+    }
+
+    #[test]
+    fn created_files_show_as_untracked() {
+        let mut app = App::new(80, 20).unwrap();
+        app.create_file("notes/todo.txt".to_string(), "buy milk".to_string());
+        app.tick();
+        assert_eq!(app.status(), "Created notes/todo.txt");
+        assert!(app.terminal.backend().to_plain_text().contains("?? notes/todo.txt"));
+
+        app.create_file("../escape.txt".to_string(), String::new());
+        assert!(app.status().starts_with("Cannot create \"../escape.txt\""));
+        assert_eq!(app.repo.filesystem().list_files(), ["notes/todo.txt"]);
+    }
+
+    #[test]
+    fn reordering_commits_drives_the_gauge() {
+        let mut app = App::new(80, 20).unwrap();
+        let mut shas = Vec::new();
+        for name in ["a", "b", "c"] {
+            app.create_file(format!("{name}.txt"), name.to_string());
+            app.repo.stage_file(&format!("{name}.txt")).unwrap();
+            shas.push(app.repo.commit(name, "t").unwrap());
+        }
+
+        shas.swap(1, 2);
+        app.reorder_commits(shas);
+        app.tick();
+        assert!(app.status().starts_with("Reordered 3 commits"), "{}", app.status());
+        assert!(app.terminal.backend().to_plain_text().contains("3/3 commits rewritten"));
+
+        app.push_key("j".to_string());
+        app.tick();
+        assert!(!app.terminal.backend().to_plain_text().contains("commits rewritten"));
+    }
+
+    #[test]
+    fn tick_and_frame_matches_separate_calls() {
+        let mut batched = App::new(60, 20).unwrap();
+        let mut separate = App::new(60, 20).unwrap();
+        for app in [&mut batched, &mut separate] {
+            app.push_key("j".to_string());
+        }
+        separate.tick();
+        assert_eq!(batched.tick_and_frame(), separate.get_frame());
+
+        batched.push_key("q".to_string());
+        assert_eq!(batched.tick_and_frame_bytes(), b"");
+    }
+
+    #[test]
+    fn tick_does_not_advance_the_clock() {
+        let mut app = App::new(60, 20).unwrap();
+        app.tick_with_time(100.0);
+        app.push_key("j".to_string());
+        app.tick();
+        assert_eq!(app.displayed_value, 0.0);
+    }
+
+    #[test]
+    fn shift_j_increments_by_ten() {
+        let mut app = App::new(60, 20).unwrap();
+        app.push_key_mods("J".to_string(), false, false, true);
+        app.push_key("J".to_string());
+        app.push_key("j".to_string());
+        app.tick();
+        assert_eq!(app.counter, 21);
+    }
+
+    #[test]
+    fn ctrl_r_hard_resets_after_confirmation() {
+        let mut app = App::new(60, 20).unwrap();
+        app.push_key_mods("J".to_string(), false, false, true);
+        app.push_key_mods("r".to_string(), true, false, false);
+        app.push_key("n".to_string());
+        app.tick();
+        assert_eq!(app.counter, 10);
+
+        app.push_key_mods("r".to_string(), true, false, false);
+        app.push_key("y".to_string());
+        app.tick();
+        assert_eq!(app.counter, 0);
+        assert_eq!(app.displayed_value, 0.0);
+    }
+
+    #[test]
+    fn injected_status_is_rendered() {
+        let mut app = App::new(60, 20).unwrap();
+        app.set_status("Saved to localStorage".to_string());
+        assert_eq!(app.status(), "Saved to localStorage");
+        app.tick();
+        assert!(app.get_frame().contains("Saved to localStorage"));
+
+        app.push_key("j".to_string());
+        app.tick();
+        assert_eq!(app.status(), "Counter: 1/100");
+    }
+
+    #[test]
+    fn stepping_past_a_boundary_rings_the_bell() {
+        let mut app = App::new(60, 20).unwrap();
+        app.push_key("j".to_string());
+        app.tick();
+        assert!(!app.get_frame().contains('\x07'));
+
+        app.push_key("k".to_string());
+        app.push_key("k".to_string());
+        app.tick();
+        assert_eq!(app.counter, 0);
+        assert!(app.get_frame().contains('\x07'));
+    }
+}
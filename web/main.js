@@ -17,6 +17,7 @@ const ROWS = 24;
 // load call `Tui2webFs.load()` and feed the entries into `restore()`.
 
 const FS_STORAGE_KEY = 'tui2web_fs';
+const PROJECTS_STORAGE_KEY = 'tui2web_projects';
 
 const Tui2webFs = {
   /**
@@ -49,6 +50,26 @@ const Tui2webFs = {
   clear() {
     localStorage.removeItem(FS_STORAGE_KEY);
   },
+
+  /**
+   * Persist a serialised `SnapshotRegistry` (several named projects).
+   * @param {string} registry output of `SnapshotRegistry::to_storage_string`
+   */
+  saveProjects(registry) {
+    try {
+      localStorage.setItem(PROJECTS_STORAGE_KEY, registry);
+    } catch (e) {
+      console.warn('tui2web: failed to persist projects to localStorage', e);
+    }
+  },
+
+  /**
+   * Load the serialised `SnapshotRegistry`, for `from_storage_string`.
+   * @returns {string|null} registry text or null if nothing saved.
+   */
+  loadProjects() {
+    return localStorage.getItem(PROJECTS_STORAGE_KEY);
+  },
 };
 
 // Expose on window so WASM can call via js_sys / web_sys if needed.
@@ -91,31 +112,50 @@ async function run() {
   fitAddon.fit();
 
   // ── Create the Rust/WASM application ──────────────────────────────────────
-  const app = new App(term.cols, term.rows);
+  let app;
+  try {
+    app = new App(term.cols, term.rows);
+  } catch (err) {
+    statusEl.textContent = `Failed to start the app: ${err}`;
+    console.error(err);
+    return;
+  }
+
+  // Write the latest frame unless it matches the one already on screen; the
+  // hash is compared first so an idle app never copies the frame into JS.
+  let lastHash;
+  function writeFrame() {
+    const hash = app.frame_hash();
+    if (hash !== lastHash) {
+      lastHash = hash;
+      term.write(app.get_frame());
+    }
+  }
 
   // ── Keyboard forwarding ────────────────────────────────────────────────────
   // xterm.js fires onKey with the DOM event; we forward KeyboardEvent.key
-  // (e.g. "j", "ArrowUp", "Escape") to the Rust app.
+  // (e.g. "j", "ArrowUp", "Escape") and the modifier state to the Rust app.
   term.onKey(({ domEvent }) => {
     if (!app.should_quit()) {
-      app.push_key(domEvent.key);
+      const { key, ctrlKey, altKey, shiftKey } = domEvent;
+      app.push_key_mods(key, ctrlKey, altKey, shiftKey);
       // Render synchronously on input for immediate feedback.
       app.tick();
-      term.write(app.get_frame());
+      writeFrame();
     }
     domEvent.preventDefault();
   });
 
   // ── Initial render ─────────────────────────────────────────────────────────
   app.tick();
-  term.write(app.get_frame());
+  writeFrame();
 
   statusEl.textContent = 'Click the terminal and use the keyboard to interact.';
 
   // ── Animation loop ─────────────────────────────────────────────────────────
   // Keeps the app ticking at ~60 fps so timer-based TUI apps work correctly.
-  // For the counter demo this is mostly idle work; no harm done.
-  function renderLoop() {
+  // The rAF timestamp drives the demo's animations (gauge easing, status pulse).
+  function renderLoop(now) {
     if (app.should_quit()) {
       term.write(
         '\r\n\x1b[32mApplication has quit.\x1b[0m Refresh the page to restart.\r\n',
@@ -124,8 +164,8 @@ async function run() {
       return;
     }
 
-    const running = app.tick();
-    term.write(app.get_frame());
+    const running = app.tick_with_time(now);
+    writeFrame();
 
     if (running) {
       requestAnimationFrame(renderLoop);
@@ -137,9 +177,13 @@ async function run() {
   // ── Terminal resize handling ───────────────────────────────────────────────
   const resizeObserver = new ResizeObserver(() => {
     fitAddon.fit();
-    app.resize(term.cols, term.rows);
+    try {
+      app.resize(term.cols, term.rows);
+    } catch (e) {
+      console.warn('tui2web: terminal size refused', e);
+    }
     app.tick();
-    term.write(app.get_frame());
+    writeFrame();
   });
   resizeObserver.observe(wrapper);
 